@@ -7,10 +7,7 @@ use std::fs::File;
 use std::io::{BufWriter, Read, Write};
 use std::path::Path;
 
-pub fn atomic_save_bin<N: DeserializeOwned + Serialize>(
-    path: &Path,
-    object: &N,
-) -> OperationResult<()> {
+pub fn atomic_save_bin<N: Serialize>(path: &Path, object: &N) -> OperationResult<()> {
     let af = AtomicFile::new(path, AllowOverwrite);
     af.write(|f| {
         let mut writer = BufWriter::new(f);
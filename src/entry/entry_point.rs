@@ -1,6 +1,7 @@
 use crate::types::{
-    PayloadKeyType, PayloadKeyTypeRef, PayloadType, PointIdType, ScoredPoint, SearchParams,
-    SegmentConfig, SegmentInfo, SegmentType, SeqNumberType, TheMap, VectorElementType, WithPayload,
+    FacetValueHit, Filter, PayloadKeyType, PayloadKeyTypeRef, PayloadType, PointIdType,
+    ScoredPoint, ScrollResult, SearchParams, SegmentConfig, SegmentInfo, SegmentType,
+    SeqNumberType, TheMap, VectorElementType, WithPayload,
 };
 use atomicwrites::Error as AtomicIoError;
 use std::io::Error as IoError;
@@ -23,6 +24,8 @@ pub enum OperationError {
         field_name: PayloadKeyType,
         expected_type: String,
     },
+    #[error("Wrong input: {description}")]
+    ValidationError { description: String },
     #[error("Service runtime error: {description}")]
     ServiceError { description: String },
 }
@@ -95,10 +98,21 @@ pub trait SegmentEntry {
         &self,
         vector: &[VectorElementType],
         with_payload: &WithPayload,
+        with_vector: bool,
+        filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
     ) -> OperationResult<Vec<ScoredPoint>>;
 
+    /// Most frequent values of the payload field with amounts of points having them,
+    /// optionally only among points matching the `filter`
+    fn facet(
+        &self,
+        key: PayloadKeyTypeRef,
+        filter: Option<&Filter>,
+        limit: usize,
+    ) -> OperationResult<Vec<FacetValueHit>>;
+
     fn upsert_point(
         &mut self,
         op_num: SeqNumberType,
@@ -112,16 +126,80 @@ pub trait SegmentEntry {
         point_id: PointIdType,
     ) -> OperationResult<bool>;
 
+    /// Insert or replace a batch of points under a single operation number.
+    /// Points updated by a newer operation are skipped. Returns amount of upserted points
+    fn upsert_points(
+        &mut self,
+        op_num: SeqNumberType,
+        points: &[(PointIdType, Vec<VectorElementType>)],
+    ) -> OperationResult<usize>;
+
+    /// Delete a batch of points under a single operation number.
+    /// Points updated by a newer operation are not deleted. Returns amount of deleted points
+    fn delete_points(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+    ) -> OperationResult<usize>;
+
+    /// Delete all points, which satisfy the filtering condition, under a single operation number.
+    /// Points updated by a newer operation are not deleted. Returns amount of deleted points
+    fn delete_by_filter(
+        &mut self,
+        op_num: SeqNumberType,
+        filter: &Filter,
+    ) -> OperationResult<usize>;
+
+    /// Replace all payload of the point with a given one
+    fn set_full_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        full_payload: TheMap<PayloadKeyType, PayloadType>,
+    ) -> OperationResult<bool>;
+
+    /// Assign payload value to the point under a given key
+    fn set_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        key: PayloadKeyTypeRef,
+        payload: PayloadType,
+    ) -> OperationResult<bool>;
+
+    /// Remove payload value of the point stored under a given key
+    fn delete_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool>;
+
+    /// Remove all payload of the point
+    fn clear_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+    ) -> OperationResult<bool>;
+
     fn vector(&self, point_id: PointIdType) -> OperationResult<Vec<VectorElementType>>;
 
+    /// Get all payload of the point
+    fn payload(&self, point_id: PointIdType)
+        -> OperationResult<TheMap<PayloadKeyType, PayloadType>>;
+
     fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_>;
 
     /// Paginate over points which satisfies filtering condition starting with `offset` id including.
-    fn read_filtered<'a>(
-        &'a self,
+    /// Returns at most `limit` points and the offset of the next page
+    fn read_filtered(
+        &self,
         offset: PointIdType,
         limit: usize,
-    ) -> Vec<PointIdType>;
+        filter: Option<&Filter>,
+        with_payload: &WithPayload,
+        with_vector: bool,
+    ) -> OperationResult<ScrollResult>;
 
     /// Check if there is point with `point_id` in this segment.
     fn has_point(&self, point_id: PointIdType) -> bool;
@@ -132,6 +210,10 @@ pub trait SegmentEntry {
     /// Number of vectors, marked as deleted
     fn deleted_count(&self) -> usize;
 
+    /// Number of points, which satisfy the filtering condition.
+    /// If not `exact`, the amount is estimated with the payload index and random sampling
+    fn count(&self, filter: Option<&Filter>, exact: bool) -> OperationResult<usize>;
+
     /// Get segment type
     fn segment_type(&self) -> SegmentType;
 
@@ -151,6 +233,24 @@ pub trait SegmentEntry {
     /// Removes all persisted data and forces to destroy segment
     fn drop_data(&mut self) -> OperationResult<()>;
 
+    /// Build the field index for the key, if not built before.
+    /// Points are searched by this field faster, if the segment uses struct payload index
+    fn create_field_index(
+        &mut self,
+        op_num: SeqNumberType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool>;
+
+    /// Delete field index, if exists
+    fn delete_field_index(
+        &mut self,
+        op_num: SeqNumberType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool>;
+
+    /// Get indexed fields
+    fn get_indexed_fields(&self) -> Vec<PayloadKeyType>;
+
     /// Checks if segment errored during last operations
     fn check_error(&self) -> Option<SegmentFailedState>;
 }
@@ -1,6 +1,6 @@
 use crate::types::{
-    Condition, FieldCondition, Match, PayloadType, Range as RangeCondition,
-    VectorElementType,
+    Condition, FieldCondition, Filter, GeoBoundingBox, GeoPoint, GeoRadius, Match, PayloadType,
+    Range as RangeCondition, VectorElementType,
 };
 use itertools::Itertools;
 use rand::prelude::ThreadRng;
@@ -38,6 +38,8 @@ const NOUN: &[&str] = &[
 ];
 
 const INT_RANGE: Range<i64> = 0..500;
+const GEO_LON_RANGE: Range<f64> = 10.0..15.0;
+const GEO_LAT_RANGE: Range<f64> = 50.0..55.0;
 
 pub fn random_keyword(rnd_gen: &mut ThreadRng) -> String {
     let random_adj = ADJECTIVE.choose(rnd_gen).unwrap();
@@ -57,6 +59,17 @@ pub fn random_int_payload(rnd_gen: &mut ThreadRng, num_values: usize) -> Payload
     )
 }
 
+pub fn random_geo_point(rnd_gen: &mut ThreadRng) -> GeoPoint {
+    GeoPoint {
+        lon: rnd_gen.gen_range(GEO_LON_RANGE),
+        lat: rnd_gen.gen_range(GEO_LAT_RANGE),
+    }
+}
+
+pub fn random_geo_payload(rnd_gen: &mut ThreadRng, num_values: usize) -> PayloadType {
+    PayloadType::Geo((0..num_values).map(|_| random_geo_point(rnd_gen)).collect_vec())
+}
+
 pub fn random_vector(rnd_gen: &mut ThreadRng, size: usize) -> Vec<VectorElementType> {
     (0..size).map(|_| rnd_gen.gen()).collect()
 }
@@ -69,6 +82,8 @@ pub fn random_field_condition(rnd_gen: &mut ThreadRng) -> Condition {
             r#match: Some(Match {
                 keyword: Some(random_keyword(rnd_gen)),
                 integer: None,
+                text: None,
+                boolean: None,
             }),
             range: None,
             geo_bounding_box: None,
@@ -89,3 +104,110 @@ pub fn random_field_condition(rnd_gen: &mut ThreadRng) -> Condition {
     }
 }
 
+pub fn random_text_condition(rnd_gen: &mut ThreadRng) -> Condition {
+    let text = match rnd_gen.gen::<bool>() {
+        true => random_keyword(rnd_gen).to_uppercase(),
+        false => NOUN.choose(rnd_gen).unwrap().to_string(),
+    };
+    Condition::Field(FieldCondition {
+        key: "kvd".to_string(),
+        r#match: Some(Match {
+            keyword: None,
+            integer: None,
+            text: Some(text),
+            boolean: None,
+        }),
+        range: None,
+        geo_bounding_box: None,
+        geo_radius: None,
+    })
+}
+
+pub fn random_geo_condition(rnd_gen: &mut ThreadRng) -> Condition {
+    let center = random_geo_point(rnd_gen);
+    let radius_or_box: bool = rnd_gen.gen();
+    match radius_or_box {
+        true => Condition::Field(FieldCondition {
+            key: "geo".to_string(),
+            r#match: None,
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: Some(GeoRadius {
+                center,
+                radius: rnd_gen.gen_range(1_000.0..100_000.0),
+            }),
+        }),
+        false => {
+            let lon_size: f64 = rnd_gen.gen_range(0.01..1.0);
+            let lat_size: f64 = rnd_gen.gen_range(0.01..1.0);
+            Condition::Field(FieldCondition {
+                key: "geo".to_string(),
+                r#match: None,
+                range: None,
+                geo_bounding_box: Some(GeoBoundingBox {
+                    top_left: GeoPoint {
+                        lon: center.lon - lon_size,
+                        lat: center.lat + lat_size,
+                    },
+                    bottom_right: GeoPoint {
+                        lon: center.lon + lon_size,
+                        lat: center.lat - lat_size,
+                    },
+                }),
+                geo_radius: None,
+            })
+        }
+    }
+}
+
+pub fn random_must_filter(rnd_gen: &mut ThreadRng, num_conditions: usize) -> Filter {
+    let must_conditions = (0..num_conditions)
+        .map(|_| random_field_condition(rnd_gen))
+        .collect_vec();
+
+    Filter {
+        should: None,
+        must: Some(must_conditions),
+        must_not: None,
+    }
+}
+
+pub fn random_filter(rnd_gen: &mut ThreadRng) -> Filter {
+    let mut rnd1 = rand::thread_rng();
+
+    let should_conditions = (0..=2)
+        .take_while(|_| rnd1.gen::<f64>() > 0.6)
+        .map(|_| random_field_condition(rnd_gen))
+        .collect_vec();
+
+    let should_conditions_opt = match should_conditions.is_empty() {
+        false => Some(should_conditions),
+        true => None,
+    };
+
+    let must_conditions = (0..=2)
+        .take_while(|_| rnd1.gen::<f64>() > 0.6)
+        .map(|_| random_field_condition(rnd_gen))
+        .collect_vec();
+
+    let must_conditions_opt = match must_conditions.is_empty() {
+        false => Some(must_conditions),
+        true => None,
+    };
+
+    let must_not_conditions = (0..=2)
+        .take_while(|_| rnd1.gen::<f64>() > 0.6)
+        .map(|_| random_field_condition(rnd_gen))
+        .collect_vec();
+
+    let must_not_conditions_opt = match must_not_conditions.is_empty() {
+        false => Some(must_not_conditions),
+        true => None,
+    };
+
+    Filter {
+        should: should_conditions_opt,
+        must: must_conditions_opt,
+        must_not: must_not_conditions_opt,
+    }
+}
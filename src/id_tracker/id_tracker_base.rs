@@ -6,9 +6,9 @@ use crate::types::{PointIdType, PointOffsetType, SeqNumberType};
 /// This tracker is used to convert external (i.e. user-facing) point id into internal point id
 /// as well as for keeping track on point version
 /// Internal ids are useful for contiguous-ness
-pub trait IdTracker {
+pub trait IdTracker: Send + Sync {
     /// Returns version of the stored point
-    fn version(&self, external_id: PointIdType) -> Option<SeqNumberType>;
+    fn version(&self, external_id: &PointIdType) -> Option<SeqNumberType>;
 
     /// Update version of the point
     fn set_version(
@@ -18,7 +18,7 @@ pub trait IdTracker {
     ) -> OperationResult<()>;
 
     /// Returns internal ID of the point, which is used inside this segment
-    fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType>;
+    fn internal_id(&self, external_id: &PointIdType) -> Option<PointOffsetType>;
 
     /// Return external ID for internal point, defined by user
     fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType>;
@@ -30,8 +30,36 @@ pub trait IdTracker {
         internal_id: PointOffsetType,
     ) -> OperationResult<()>;
 
+    /// Update versions of multiple points at once
+    fn set_versions(
+        &mut self,
+        external_ids: &[PointIdType],
+        version: SeqNumberType,
+    ) -> OperationResult<()> {
+        for external_id in external_ids {
+            self.set_version(external_id.clone(), version)?;
+        }
+        Ok(())
+    }
+
+    /// Set multiple mappings at once
+    fn set_links(&mut self, links: &[(PointIdType, PointOffsetType)]) -> OperationResult<()> {
+        for (external_id, internal_id) in links {
+            self.set_link(external_id.clone(), *internal_id)?;
+        }
+        Ok(())
+    }
+
     /// Drop mapping
-    fn drop(&mut self, external_id: PointIdType) -> OperationResult<()>;
+    fn drop(&mut self, external_id: &PointIdType) -> OperationResult<()>;
+
+    /// Drop multiple mappings at once
+    fn drop_many(&mut self, external_ids: &[PointIdType]) -> OperationResult<()> {
+        for external_id in external_ids {
+            self.drop(external_id)?;
+        }
+        Ok(())
+    }
 
     /// Iterate over all external ids
     fn iter_external(&self) -> Box<dyn Iterator<Item = PointIdType> + '_>;
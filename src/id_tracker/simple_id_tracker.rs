@@ -1,10 +1,12 @@
 use crate::entry::entry_point::OperationResult;
 use crate::id_tracker::IdTracker;
-use crate::types::{PointIdType, PointOffsetType, SeqNumberType};
+use crate::types::{ExtendedPointId, PointIdType, PointOffsetType, SeqNumberType};
 use bincode;
-use rocksdb::{IteratorMode, Options, DB};
+use rocksdb::{IteratorMode, Options, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use uuid::Uuid;
 
 /// Since sled is used for reading only during the initialization, large read cache is not required
 const DB_CACHE_SIZE: usize = 10 * 1024 * 1024; // 10 mb
@@ -12,6 +14,35 @@ const DB_CACHE_SIZE: usize = 10 * 1024 * 1024; // 10 mb
 const MAPPING_CF: &str = "mapping";
 const VERSIONS_CF: &str = "versions";
 
+/// Storage representation of non-numeric point ids.
+/// Numeric ids are stored as plain `u64`, same as in segments created before
+/// UUID and string ids were introduced. Serialized enum is never 8 bytes long,
+/// so both representations could be distinguished by the key length
+#[derive(Serialize, Deserialize)]
+enum StoredPointId {
+    Uuid(Uuid),
+    String(String),
+}
+
+fn stored_point_id(point_id: &PointIdType) -> Vec<u8> {
+    match point_id {
+        ExtendedPointId::NumId(id) => bincode::serialize(id),
+        ExtendedPointId::Uuid(id) => bincode::serialize(&StoredPointId::Uuid(*id)),
+        ExtendedPointId::String(id) => bincode::serialize(&StoredPointId::String(id.clone())),
+    }
+    .unwrap()
+}
+
+fn restore_point_id(key: &[u8]) -> PointIdType {
+    if key.len() == std::mem::size_of::<u64>() {
+        return ExtendedPointId::NumId(bincode::deserialize(key).unwrap());
+    }
+    match bincode::deserialize(key).unwrap() {
+        StoredPointId::Uuid(id) => ExtendedPointId::Uuid(id),
+        StoredPointId::String(id) => ExtendedPointId::String(id),
+    }
+}
+
 pub struct SimpleIdTracker {
     internal_to_external: HashMap<PointOffsetType, PointIdType>,
     external_to_internal: BTreeMap<PointIdType, PointOffsetType>,
@@ -34,16 +65,16 @@ impl SimpleIdTracker {
         for (key, val) in
             store.iterator_cf(store.cf_handle(MAPPING_CF).unwrap(), IteratorMode::Start)
         {
-            let external_id: PointIdType = bincode::deserialize(&key).unwrap();
+            let external_id = restore_point_id(&key);
             let internal_id: PointOffsetType = bincode::deserialize(&val).unwrap();
-            internal_to_external.insert(internal_id, external_id);
+            internal_to_external.insert(internal_id, external_id.clone());
             external_to_internal.insert(external_id, internal_id);
         }
 
         for (key, val) in
             store.iterator_cf(store.cf_handle(VERSIONS_CF).unwrap(), IteratorMode::Start)
         {
-            let external_id: PointIdType = bincode::deserialize(&key).unwrap();
+            let external_id = restore_point_id(&key);
             let version: SeqNumberType = bincode::deserialize(&val).unwrap();
             external_to_version.insert(external_id, version);
         }
@@ -58,8 +89,8 @@ impl SimpleIdTracker {
 }
 
 impl IdTracker for SimpleIdTracker {
-    fn version(&self, external_id: PointIdType) -> Option<SeqNumberType> {
-        self.external_to_version.get(&external_id).cloned()
+    fn version(&self, external_id: &PointIdType) -> Option<SeqNumberType> {
+        self.external_to_version.get(external_id).cloned()
     }
 
     fn set_version(
@@ -67,17 +98,37 @@ impl IdTracker for SimpleIdTracker {
         external_id: PointIdType,
         version: SeqNumberType,
     ) -> OperationResult<()> {
-        self.external_to_version.insert(external_id, version);
         self.store.put_cf(
             self.store.cf_handle(VERSIONS_CF).unwrap(),
-            bincode::serialize(&external_id).unwrap(),
+            stored_point_id(&external_id),
             bincode::serialize(&version).unwrap(),
         )?;
+        self.external_to_version.insert(external_id, version);
+        Ok(())
+    }
+
+    fn set_versions(
+        &mut self,
+        external_ids: &[PointIdType],
+        version: SeqNumberType,
+    ) -> OperationResult<()> {
+        let versions_cf = self.store.cf_handle(VERSIONS_CF).unwrap();
+        let mut batch = WriteBatch::default();
+        for external_id in external_ids {
+            self.external_to_version
+                .insert(external_id.clone(), version);
+            batch.put_cf(
+                versions_cf,
+                stored_point_id(external_id),
+                bincode::serialize(&version).unwrap(),
+            );
+        }
+        self.store.write(batch)?;
         Ok(())
     }
 
-    fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType> {
-        self.external_to_internal.get(&external_id).cloned()
+    fn internal_id(&self, external_id: &PointIdType) -> Option<PointOffsetType> {
+        self.external_to_internal.get(external_id).cloned()
     }
 
     fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType> {
@@ -89,36 +140,72 @@ impl IdTracker for SimpleIdTracker {
         external_id: PointIdType,
         internal_id: PointOffsetType,
     ) -> OperationResult<()> {
-        self.external_to_internal.insert(external_id, internal_id);
-        self.internal_to_external.insert(internal_id, external_id);
-
         self.store.put_cf(
             self.store.cf_handle(MAPPING_CF).unwrap(),
-            bincode::serialize(&external_id).unwrap(),
+            stored_point_id(&external_id),
             bincode::serialize(&internal_id).unwrap(),
         )?;
+
+        self.internal_to_external
+            .insert(internal_id, external_id.clone());
+        self.external_to_internal.insert(external_id, internal_id);
+        Ok(())
+    }
+
+    fn set_links(&mut self, links: &[(PointIdType, PointOffsetType)]) -> OperationResult<()> {
+        let mapping_cf = self.store.cf_handle(MAPPING_CF).unwrap();
+        let mut batch = WriteBatch::default();
+        for (external_id, internal_id) in links {
+            self.external_to_internal
+                .insert(external_id.clone(), *internal_id);
+            self.internal_to_external
+                .insert(*internal_id, external_id.clone());
+            batch.put_cf(
+                mapping_cf,
+                stored_point_id(external_id),
+                bincode::serialize(internal_id).unwrap(),
+            );
+        }
+        self.store.write(batch)?;
         Ok(())
     }
 
-    fn drop(&mut self, external_id: PointIdType) -> OperationResult<()> {
-        self.external_to_version.remove(&external_id);
+    fn drop(&mut self, external_id: &PointIdType) -> OperationResult<()> {
+        self.external_to_version.remove(external_id);
 
-        let internal_id = self.external_to_internal.remove(&external_id);
+        let internal_id = self.external_to_internal.remove(external_id);
         match internal_id {
             Some(x) => self.internal_to_external.remove(&x),
             None => None,
         };
         self.store.delete_cf(
             self.store.cf_handle(MAPPING_CF).unwrap(),
-            bincode::serialize(&external_id).unwrap(),
+            stored_point_id(external_id),
         )?;
         self.store.delete_cf(
             self.store.cf_handle(VERSIONS_CF).unwrap(),
-            bincode::serialize(&external_id).unwrap(),
+            stored_point_id(external_id),
         )?;
         Ok(())
     }
 
+    fn drop_many(&mut self, external_ids: &[PointIdType]) -> OperationResult<()> {
+        let mapping_cf = self.store.cf_handle(MAPPING_CF).unwrap();
+        let versions_cf = self.store.cf_handle(VERSIONS_CF).unwrap();
+        let mut batch = WriteBatch::default();
+        for external_id in external_ids {
+            self.external_to_version.remove(external_id);
+            if let Some(internal_id) = self.external_to_internal.remove(external_id) {
+                self.internal_to_external.remove(&internal_id);
+            }
+            let key = stored_point_id(external_id);
+            batch.delete_cf(mapping_cf, &key);
+            batch.delete_cf(versions_cf, &key);
+        }
+        self.store.write(batch)?;
+        Ok(())
+    }
+
     fn iter_external(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
         Box::new(self.external_to_internal.keys().cloned())
     }
@@ -133,8 +220,8 @@ impl IdTracker for SimpleIdTracker {
     ) -> Box<dyn Iterator<Item = (PointIdType, PointOffsetType)> + '_> {
         Box::new(
             self.external_to_internal
-                .range(external_id..PointIdType::MAX)
-                .map(|(key, value)| (*key, *value)),
+                .range(external_id..)
+                .map(|(key, value)| (key.clone(), *value)),
         )
     }
 
@@ -159,23 +246,106 @@ mod tests {
 
         let mut id_tracker = SimpleIdTracker::open(dir.path()).unwrap();
 
-        id_tracker.set_link(200, 0).unwrap();
-        id_tracker.set_link(100, 1).unwrap();
-        id_tracker.set_link(150, 2).unwrap();
-        id_tracker.set_link(120, 3).unwrap();
-        id_tracker.set_link(180, 4).unwrap();
-        id_tracker.set_link(110, 5).unwrap();
-        id_tracker.set_link(115, 6).unwrap();
-        id_tracker.set_link(190, 7).unwrap();
-        id_tracker.set_link(177, 8).unwrap();
-        id_tracker.set_link(118, 9).unwrap();
+        id_tracker.set_link(200.into(), 0).unwrap();
+        id_tracker.set_link(100.into(), 1).unwrap();
+        id_tracker.set_link(150.into(), 2).unwrap();
+        id_tracker.set_link(120.into(), 3).unwrap();
+        id_tracker.set_link(180.into(), 4).unwrap();
+        id_tracker.set_link(110.into(), 5).unwrap();
+        id_tracker.set_link(115.into(), 6).unwrap();
+        id_tracker.set_link(190.into(), 7).unwrap();
+        id_tracker.set_link(177.into(), 8).unwrap();
+        id_tracker.set_link(118.into(), 9).unwrap();
 
-        let first_four = id_tracker.iter_from(0).take(4).collect_vec();
+        let first_four = id_tracker.iter_from(0.into()).take(4).collect_vec();
 
         assert_eq!(first_four.len(), 4);
-        assert_eq!(first_four[0].0, 100);
+        assert_eq!(first_four[0].0, 100.into());
 
-        let last = id_tracker.iter_from(first_four[3].0 + 1).collect_vec();
+        let last_id = match first_four[3].0 {
+            ExtendedPointId::NumId(id) => id,
+            _ => panic!("Unexpected id type"),
+        };
+        let last = id_tracker.iter_from((last_id + 1).into()).collect_vec();
         assert_eq!(last.len(), 6);
     }
+
+    #[test]
+    fn test_batch_operations() {
+        let dir = TempDir::new("storage_dir").unwrap();
+
+        {
+            let mut id_tracker = SimpleIdTracker::open(dir.path()).unwrap();
+            id_tracker
+                .set_links(&[(10.into(), 0), (20.into(), 1), (30.into(), 2)])
+                .unwrap();
+            id_tracker
+                .set_versions(&[10.into(), 20.into(), 30.into()], 5)
+                .unwrap();
+            id_tracker.drop_many(&[20.into(), 40.into()]).unwrap();
+            id_tracker.flush().unwrap();
+        }
+
+        let id_tracker = SimpleIdTracker::open(dir.path()).unwrap();
+        assert_eq!(
+            id_tracker.iter_external().collect_vec(),
+            vec![10.into(), 30.into()]
+        );
+        assert_eq!(id_tracker.internal_id(&30.into()), Some(2));
+        assert_eq!(id_tracker.external_id(1), None);
+        assert_eq!(id_tracker.version(&10.into()), Some(5));
+        assert_eq!(id_tracker.version(&20.into()), None);
+    }
+
+    #[test]
+    fn test_extended_ids() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let uuid_id: PointIdType = Uuid::new_v4().into();
+        // 7 bytes string, which would be 8 bytes long with a naive tag prefix
+        let string_id: PointIdType = "par/001".into();
+
+        {
+            // Segment, created with plain `u64` ids
+            let mut options: Options = Options::default();
+            options.create_if_missing(true);
+            options.create_missing_column_families(true);
+            let store = DB::open_cf(&options, dir.path(), [MAPPING_CF, VERSIONS_CF]).unwrap();
+            let key = bincode::serialize(&42u64).unwrap();
+            store
+                .put_cf(
+                    store.cf_handle(MAPPING_CF).unwrap(),
+                    &key,
+                    bincode::serialize(&0u32).unwrap(),
+                )
+                .unwrap();
+            store
+                .put_cf(
+                    store.cf_handle(VERSIONS_CF).unwrap(),
+                    &key,
+                    bincode::serialize(&7u64).unwrap(),
+                )
+                .unwrap();
+        }
+
+        {
+            let mut id_tracker = SimpleIdTracker::open(dir.path()).unwrap();
+            assert_eq!(id_tracker.internal_id(&42.into()), Some(0));
+            assert_eq!(id_tracker.version(&42.into()), Some(7));
+
+            id_tracker.set_link(uuid_id.clone(), 1).unwrap();
+            id_tracker.set_link(string_id.clone(), 2).unwrap();
+            id_tracker.set_version(string_id.clone(), 8).unwrap();
+            id_tracker.set_link(43.into(), 3).unwrap();
+            id_tracker.drop(&43.into()).unwrap();
+            id_tracker.flush().unwrap();
+        }
+
+        let id_tracker = SimpleIdTracker::open(dir.path()).unwrap();
+        assert_eq!(id_tracker.iter_external().count(), 3);
+        assert_eq!(id_tracker.internal_id(&42.into()), Some(0));
+        assert_eq!(id_tracker.internal_id(&uuid_id), Some(1));
+        assert_eq!(id_tracker.external_id(2), Some(string_id.clone()));
+        assert_eq!(id_tracker.version(&string_id), Some(8));
+        assert_eq!(id_tracker.internal_id(&43.into()), None);
+    }
 }
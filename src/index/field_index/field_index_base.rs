@@ -0,0 +1,127 @@
+use crate::index::field_index::full_text_index::FullTextIndex;
+use crate::index::field_index::geo_index::GeoMapIndex;
+use crate::index::field_index::map_index::MapIndex;
+use crate::index::field_index::numeric_index::NumericIndex;
+use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
+use crate::types::{
+    DateTimePayloadType, FieldCondition, FloatPayloadType, IntPayloadType, PayloadKeyType,
+    PayloadType, PointOffsetType,
+};
+use serde::{Deserialize, Serialize};
+
+/// Index over values of a single payload field
+pub trait PayloadFieldIndex {
+    /// Add values of the point into the index.
+    /// Previous values of the point should be removed before
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType);
+
+    /// Remove all values of the point from the index
+    fn remove_point(&mut self, id: PointOffsetType);
+
+    /// Add values of the point, which is not in the index yet, while the whole index is built.
+    /// Index may postpone ordering of the values until `finish_build`
+    fn add_point_on_build(&mut self, id: PointOffsetType, value: &PayloadType) {
+        self.add_point(id, value)
+    }
+
+    /// Make index, filled with `add_point_on_build`, ready for search
+    fn finish_build(&mut self) {}
+
+    /// Get iterator over points fitting given `condition`.
+    /// Returns `None` if the condition can't be processed by this index
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>>;
+
+    /// Return estimation of points amount which satisfy given condition
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation>;
+
+    /// Iterate conditions for payload blocks with minimum size of `threshold`
+    /// Required for building HNSW index
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_>;
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum FieldIndex {
+    IntIndex(NumericIndex<IntPayloadType>),
+    IntMapIndex(MapIndex<IntPayloadType>),
+    KeywordIndex(MapIndex<String>),
+    FloatIndex(NumericIndex<FloatPayloadType>),
+    GeoIndex(GeoMapIndex),
+    FullTextIndex(FullTextIndex),
+    DatetimeIndex(NumericIndex<DateTimePayloadType>),
+    BoolIndex(MapIndex<bool>),
+}
+
+impl FieldIndex {
+    pub fn get_payload_field_index(&self) -> &dyn PayloadFieldIndex {
+        match self {
+            FieldIndex::IntIndex(index) => index,
+            FieldIndex::IntMapIndex(index) => index,
+            FieldIndex::KeywordIndex(index) => index,
+            FieldIndex::FloatIndex(index) => index,
+            FieldIndex::GeoIndex(index) => index,
+            FieldIndex::FullTextIndex(index) => index,
+            FieldIndex::DatetimeIndex(index) => index,
+            FieldIndex::BoolIndex(index) => index,
+        }
+    }
+
+    pub fn get_payload_field_index_mut(&mut self) -> &mut dyn PayloadFieldIndex {
+        match self {
+            FieldIndex::IntIndex(index) => index,
+            FieldIndex::IntMapIndex(index) => index,
+            FieldIndex::KeywordIndex(index) => index,
+            FieldIndex::FloatIndex(index) => index,
+            FieldIndex::GeoIndex(index) => index,
+            FieldIndex::FullTextIndex(index) => index,
+            FieldIndex::DatetimeIndex(index) => index,
+            FieldIndex::BoolIndex(index) => index,
+        }
+    }
+}
+
+impl PayloadFieldIndex for FieldIndex {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        self.get_payload_field_index_mut().add_point(id, value)
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.get_payload_field_index_mut().remove_point(id)
+    }
+
+    fn add_point_on_build(&mut self, id: PointOffsetType, value: &PayloadType) {
+        self.get_payload_field_index_mut()
+            .add_point_on_build(id, value)
+    }
+
+    fn finish_build(&mut self) {
+        self.get_payload_field_index_mut().finish_build()
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        self.get_payload_field_index().filter(condition)
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        self.get_payload_field_index()
+            .estimate_cardinality(condition)
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        self.get_payload_field_index()
+            .payload_blocks(threshold, key)
+    }
+}
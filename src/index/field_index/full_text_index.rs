@@ -0,0 +1,270 @@
+use crate::index::field_index::{
+    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndex, PrimaryCondition,
+};
+use crate::types::{FieldCondition, Match, PayloadKeyType, PayloadType, PointOffsetType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Split text into lowercase words. Any non-alphanumeric character is a separator
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+}
+
+/// Check if all words of `query` are present in `text`
+pub fn check_text_match(query: &str, text: &str) -> bool {
+    let text_tokens: BTreeSet<String> = tokenize(text).collect();
+    tokenize(query).all(|token| text_tokens.contains(&token))
+}
+
+/// Inverted index over words of keyword values.
+/// Postings are built over words of all values of the point, while words of each value are kept
+/// separately, so that all words of the query are matched within a single value
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FullTextIndex {
+    postings: HashMap<String, BTreeSet<PointOffsetType>>,
+    point_to_tokens: HashMap<PointOffsetType, Vec<BTreeSet<String>>>,
+}
+
+impl FullTextIndex {
+    pub fn points_count(&self) -> usize {
+        self.point_to_tokens.len()
+    }
+
+    fn remove(&mut self, id: PointOffsetType) {
+        if let Some(value_tokens) = self.point_to_tokens.remove(&id) {
+            let tokens: BTreeSet<String> = value_tokens.into_iter().flatten().collect();
+            for token in tokens {
+                if let Some(points) = self.postings.get_mut(&token) {
+                    points.remove(&id);
+                    if points.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check if any single value of the point contains all of the `tokens`
+    fn check_point(&self, id: PointOffsetType, tokens: &BTreeSet<String>) -> bool {
+        self.point_to_tokens
+            .get(&id)
+            .map(|value_tokens| {
+                value_tokens
+                    .iter()
+                    .any(|value_tokens| tokens.is_subset(value_tokens))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Posting lists of all query words, shortest first.
+    /// Returns `None` if query has no words, and an empty list if any word is not indexed
+    fn query_postings(&self, tokens: &BTreeSet<String>) -> Option<Vec<&BTreeSet<PointOffsetType>>> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let mut postings = Vec::with_capacity(tokens.len());
+        for token in tokens.iter() {
+            match self.postings.get(token) {
+                None => return Some(vec![]),
+                Some(points) => postings.push(points),
+            }
+        }
+        postings.sort_by_key(|points| points.len());
+        Some(postings)
+    }
+}
+
+/// Returns full-text query if it is the only check of the field condition
+fn exclusive_text(condition: &FieldCondition) -> Option<&String> {
+    match condition {
+        FieldCondition {
+            r#match:
+                Some(Match {
+                    keyword: None,
+                    integer: None,
+                    text: Some(text),
+                    boolean: None,
+                }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+            ..
+        } => Some(text),
+        _ => None,
+    }
+}
+
+impl PayloadFieldIndex for FullTextIndex {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        self.remove(id);
+        if let PayloadType::Keyword(keywords) = value {
+            let value_tokens: Vec<BTreeSet<String>> = keywords
+                .iter()
+                .map(|keyword| tokenize(keyword).collect::<BTreeSet<_>>())
+                .filter(|tokens| !tokens.is_empty())
+                .collect();
+            if value_tokens.is_empty() {
+                return;
+            }
+            for token in value_tokens.iter().flatten() {
+                self.postings.entry(token.clone()).or_default().insert(id);
+            }
+            self.point_to_tokens.insert(id, value_tokens);
+        }
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.remove(id)
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        let tokens: BTreeSet<String> = tokenize(exclusive_text(condition)?).collect();
+        let mut postings = self.query_postings(&tokens)?;
+        if postings.is_empty() {
+            return Some(Box::new(vec![].into_iter()));
+        }
+        let shortest = postings.remove(0);
+        let iter = shortest.iter().cloned().filter(move |&id| {
+            postings.iter().all(|points| points.contains(&id)) && self.check_point(id, &tokens)
+        });
+        Some(Box::new(iter))
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        let tokens: BTreeSet<String> = tokenize(exclusive_text(condition)?).collect();
+        let postings = self.query_postings(&tokens)?;
+        let estimation = match postings.len() {
+            0 => CardinalityEstimation {
+                primary_clauses: vec![],
+                min: 0,
+                exp: 0,
+                max: 0,
+            },
+            1 => CardinalityEstimation {
+                primary_clauses: vec![],
+                min: postings[0].len(),
+                exp: postings[0].len(),
+                max: postings[0].len(),
+            },
+            _ => {
+                // Assume words are distributed independently.
+                // Words of different values of the same point are also counted as a match here
+                let total = self.points_count() as f64;
+                let exp = postings
+                    .iter()
+                    .fold(total, |acc, points| acc * points.len() as f64 / total);
+                CardinalityEstimation {
+                    primary_clauses: vec![],
+                    min: 0,
+                    exp: exp.round() as usize,
+                    max: postings[0].len(),
+                }
+            }
+        };
+        Some(CardinalityEstimation {
+            primary_clauses: vec![PrimaryCondition::Condition(condition.clone())],
+            ..estimation
+        })
+    }
+
+    fn payload_blocks(
+        &self,
+        _threshold: usize,
+        _key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        // Blocks would be built for the most frequent words, which are usually the least
+        // meaningful ones, so no additional HNSW links are built for full-text fields
+        Box::new(std::iter::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_condition(text: &str) -> FieldCondition {
+        FieldCondition {
+            key: "text".to_string(),
+            r#match: Some(Match {
+                keyword: None,
+                integer: None,
+                text: Some(text.to_string()),
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }
+    }
+
+    fn keywords(values: &[&str]) -> PayloadType {
+        PayloadType::Keyword(values.iter().map(|x| x.to_string()).collect())
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<_> = tokenize("Invoice #42, paid-in FULL!").collect();
+        assert_eq!(tokens, vec!["invoice", "42", "paid", "in", "full"]);
+        assert!(check_text_match(
+            "full INVOICE",
+            "Invoice #42, paid-in FULL!"
+        ));
+        assert!(!check_text_match("invoice receipt", "Invoice #42"));
+    }
+
+    #[test]
+    fn test_full_text_index() {
+        let mut index = FullTextIndex::default();
+        index.add_point(0, &keywords(&["The invoice was paid"]));
+        index.add_point(1, &keywords(&["Invoice pending", "second paragraph"]));
+        index.add_point(2, &keywords(&["Nothing to see here"]));
+        index.add_point(3, &PayloadType::Integer(vec![1]));
+
+        assert_eq!(index.points_count(), 3);
+
+        let found: Vec<_> = index.filter(&text_condition("INVOICE")).unwrap().collect();
+        assert_eq!(found, vec![0, 1]);
+
+        let found: Vec<_> = index
+            .filter(&text_condition("paid invoice"))
+            .unwrap()
+            .collect();
+        assert_eq!(found, vec![0]);
+
+        assert_eq!(index.filter(&text_condition("receipt")).unwrap().count(), 0);
+        assert!(index.filter(&text_condition(" ,. ")).is_none());
+
+        let estimation = index
+            .estimate_cardinality(&text_condition("invoice"))
+            .unwrap();
+        assert_eq!(estimation.exp, 2);
+        assert_eq!(estimation.primary_clauses.len(), 1);
+
+        let estimation = index
+            .estimate_cardinality(&text_condition("invoice paragraph"))
+            .unwrap();
+        assert_eq!(estimation.min, 0);
+        assert_eq!(estimation.max, 1);
+
+        // All words should be present in the same value, as in `check_text_match`
+        let condition = text_condition("invoice paragraph");
+        assert_eq!(index.filter(&condition).unwrap().count(), 0);
+        let found: Vec<_> = index
+            .filter(&text_condition("paragraph second"))
+            .unwrap()
+            .collect();
+        assert_eq!(found, vec![1]);
+
+        index.remove_point(0);
+        let found: Vec<_> = index.filter(&text_condition("invoice")).unwrap().collect();
+        assert_eq!(found, vec![1]);
+        assert_eq!(index.filter(&text_condition("paid")).unwrap().count(), 0);
+
+        assert!(index.payload_blocks(1, "text".to_string()).next().is_none());
+    }
+}
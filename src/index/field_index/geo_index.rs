@@ -0,0 +1,673 @@
+use crate::index::field_index::{
+    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndex, PrimaryCondition,
+};
+use crate::types::{
+    FieldCondition, GeoBoundingBox, GeoPoint, GeoRadius, PayloadKeyType, PayloadType,
+    PointOffsetType,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const BASE32_CODES: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Precision of geohashes stored in the index, ~3.7cm x 1.9cm cells
+const GEOHASH_MAX_PRECISION: usize = 12;
+
+/// Max number of geohash cells used to cover a query region
+const MAX_COVERING_CELLS: usize = 16;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Encode coordinates into geohash of a given precision
+pub fn encode_geohash(lon: f64, lat: f64, precision: usize) -> String {
+    let mut lon_interval = (-180.0, 180.0);
+    let mut lat_interval = (-90.0, 90.0);
+    let mut hash = String::with_capacity(precision);
+    let mut is_lon = true;
+    let mut bits = 0;
+    let mut ch = 0usize;
+
+    while hash.len() < precision {
+        let (interval, value) = if is_lon {
+            (&mut lon_interval, lon)
+        } else {
+            (&mut lat_interval, lat)
+        };
+        let mid = (interval.0 + interval.1) / 2.0;
+        ch <<= 1;
+        if value >= mid {
+            ch |= 1;
+            interval.0 = mid;
+        } else {
+            interval.1 = mid;
+        }
+        is_lon = !is_lon;
+        bits += 1;
+        if bits == 5 {
+            hash.push(BASE32_CODES[ch] as char);
+            bits = 0;
+            ch = 0;
+        }
+    }
+    hash
+}
+
+/// Rectangle covered by the geohash: (min_lon, min_lat, max_lon, max_lat)
+pub fn decode_geohash_bounds(hash: &str) -> (f64, f64, f64, f64) {
+    let mut lon_interval = (-180.0, 180.0);
+    let mut lat_interval = (-90.0, 90.0);
+    let mut is_lon = true;
+    for c in hash.bytes() {
+        let code = BASE32_CODES.iter().position(|x| *x == c).unwrap_or(0);
+        for bit in (0..5).rev() {
+            let interval = if is_lon {
+                &mut lon_interval
+            } else {
+                &mut lat_interval
+            };
+            let mid = (interval.0 + interval.1) / 2.0;
+            if (code >> bit) & 1 == 1 {
+                interval.0 = mid;
+            } else {
+                interval.1 = mid;
+            }
+            is_lon = !is_lon;
+        }
+    }
+    (
+        lon_interval.0,
+        lat_interval.0,
+        lon_interval.1,
+        lat_interval.1,
+    )
+}
+
+/// Size of a geohash cell in degrees: (lon, lat)
+fn cell_size(precision: usize) -> (f64, f64) {
+    let bits = 5 * precision;
+    let lon_bits = (bits + 1) / 2;
+    let lat_bits = bits / 2;
+    (
+        360.0 / (1u64 << lon_bits) as f64,
+        180.0 / (1u64 << lat_bits) as f64,
+    )
+}
+
+/// Geohash prefixes, which together cover the rectangle: (min_lon, min_lat, max_lon, max_lat)
+fn covering_geohashes(rect: (f64, f64, f64, f64)) -> Vec<String> {
+    let (min_lon, min_lat, max_lon, max_lat) = rect;
+    if min_lon > max_lon || min_lat > max_lat {
+        return vec![];
+    }
+
+    let cells_at = |precision: usize| {
+        let (lon_step, lat_step) = cell_size(precision);
+        let lon_cells =
+            ((max_lon + 180.0) / lon_step).floor() - ((min_lon + 180.0) / lon_step).floor() + 1.0;
+        let lat_cells =
+            ((max_lat + 90.0) / lat_step).floor() - ((min_lat + 90.0) / lat_step).floor() + 1.0;
+        (lon_step, lat_step, lon_cells * lat_cells)
+    };
+
+    let precision = (1..=GEOHASH_MAX_PRECISION)
+        .take_while(|precision| cells_at(*precision).2 <= MAX_COVERING_CELLS as f64)
+        .last()
+        .unwrap_or(1);
+
+    let (lon_step, lat_step, _) = cells_at(precision);
+    let mut hashes = BTreeSet::new();
+    let mut lon = min_lon;
+    loop {
+        let mut lat = min_lat;
+        loop {
+            hashes.insert(encode_geohash(lon, lat, precision));
+            if lat >= max_lat {
+                break;
+            }
+            lat = (lat + lat_step).min(max_lat);
+        }
+        if lon >= max_lon {
+            break;
+        }
+        lon = (lon + lon_step).min(max_lon);
+    }
+    hashes.into_iter().collect()
+}
+
+fn clamp_lon(lon: f64) -> f64 {
+    lon.max(-180.0).min(180.0)
+}
+
+fn clamp_lat(lat: f64) -> f64 {
+    lat.max(-90.0).min(90.0)
+}
+
+impl GeoBoundingBox {
+    fn rect(&self) -> (f64, f64, f64, f64) {
+        (
+            clamp_lon(self.top_left.lon),
+            clamp_lat(self.bottom_right.lat),
+            clamp_lon(self.bottom_right.lon),
+            clamp_lat(self.top_left.lat),
+        )
+    }
+
+    fn contains_rect(&self, rect: (f64, f64, f64, f64)) -> bool {
+        let (min_lon, min_lat, max_lon, max_lat) = rect;
+        self.top_left.lon < min_lon
+            && max_lon < self.bottom_right.lon
+            && self.bottom_right.lat < min_lat
+            && max_lat < self.top_left.lat
+    }
+}
+
+impl GeoRadius {
+    /// Rectangles, which contain the whole circle.
+    /// Circles, crossing the antimeridian, are covered by two rectangles
+    fn rects(&self) -> Vec<(f64, f64, f64, f64)> {
+        // Small margin to compensate the difference between sphere and degrees arithmetic
+        let angular_radius = (self.radius * 1.01 / EARTH_RADIUS_METERS).to_degrees();
+        let min_lat = self.center.lat - angular_radius;
+        let max_lat = self.center.lat + angular_radius;
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            // Circle covers a pole, so all longitudes are affected
+            return vec![(-180.0, clamp_lat(min_lat), 180.0, clamp_lat(max_lat))];
+        }
+        let lon_delta = (angular_radius.to_radians().sin() / self.center.lat.to_radians().cos())
+            .min(1.0)
+            .asin()
+            .to_degrees();
+        let min_lon = self.center.lon - lon_delta;
+        let max_lon = self.center.lon + lon_delta;
+        if min_lon < -180.0 {
+            vec![
+                (-180.0, min_lat, max_lon, max_lat),
+                (min_lon + 360.0, min_lat, 180.0, max_lat),
+            ]
+        } else if max_lon > 180.0 {
+            vec![
+                (min_lon, min_lat, 180.0, max_lat),
+                (-180.0, min_lat, max_lon - 360.0, max_lat),
+            ]
+        } else {
+            vec![(min_lon, min_lat, max_lon, max_lat)]
+        }
+    }
+
+    fn contains_rect(&self, rect: (f64, f64, f64, f64)) -> bool {
+        let (min_lon, min_lat, max_lon, max_lat) = rect;
+        let corners = [
+            (min_lon, min_lat),
+            (min_lon, max_lat),
+            (max_lon, min_lat),
+            (max_lon, max_lat),
+        ];
+        // Cells are small compared to the circle, so corners are enough
+        corners.iter().all(|(lon, lat)| {
+            self.check_point(&GeoPoint {
+                lon: *lon,
+                lat: *lat,
+            })
+        })
+    }
+}
+
+/// Geo condition, extracted from the field condition
+enum GeoCondition<'a> {
+    BoundingBox(&'a GeoBoundingBox),
+    Radius(&'a GeoRadius),
+}
+
+impl<'a> GeoCondition<'a> {
+    fn from_field_condition(condition: &'a FieldCondition) -> Option<Self> {
+        match condition {
+            FieldCondition {
+                r#match: None,
+                range: None,
+                geo_bounding_box: Some(bounding_box),
+                geo_radius: None,
+                ..
+            } => Some(GeoCondition::BoundingBox(bounding_box)),
+            FieldCondition {
+                r#match: None,
+                range: None,
+                geo_bounding_box: None,
+                geo_radius: Some(radius),
+                ..
+            } => Some(GeoCondition::Radius(radius)),
+            _ => None,
+        }
+    }
+
+    fn covering_geohashes(&self) -> Vec<String> {
+        let rects = match self {
+            GeoCondition::BoundingBox(bounding_box) => vec![bounding_box.rect()],
+            GeoCondition::Radius(radius) => radius.rects(),
+        };
+        let hashes: BTreeSet<_> = rects.into_iter().flat_map(covering_geohashes).collect();
+        hashes.into_iter().collect()
+    }
+
+    fn check_point(&self, point: &GeoPoint) -> bool {
+        match self {
+            GeoCondition::BoundingBox(bounding_box) => bounding_box.check_point(point),
+            GeoCondition::Radius(radius) => radius.check_point(point),
+        }
+    }
+
+    fn contains_rect(&self, rect: (f64, f64, f64, f64)) -> bool {
+        match self {
+            GeoCondition::BoundingBox(bounding_box) => bounding_box.contains_rect(rect),
+            GeoCondition::Radius(radius) => radius.contains_rect(rect),
+        }
+    }
+}
+
+/// Index of geo points, based on geohashes of the points
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GeoMapIndex {
+    /// Full precision geohash -> points located in it
+    points_map: BTreeMap<String, BTreeSet<PointOffsetType>>,
+    point_to_values: HashMap<PointOffsetType, Vec<GeoPoint>>,
+    values_count: usize,
+    max_values_per_point: usize,
+}
+
+impl GeoMapIndex {
+    pub fn points_count(&self) -> usize {
+        self.point_to_values.len()
+    }
+
+    fn add_many(&mut self, id: PointOffsetType, values: &[GeoPoint]) {
+        self.remove(id);
+        if values.is_empty() {
+            return;
+        }
+        for value in values {
+            let hash = encode_geohash(value.lon, value.lat, GEOHASH_MAX_PRECISION);
+            if self.points_map.entry(hash).or_default().insert(id) {
+                self.values_count += 1;
+            }
+        }
+        self.max_values_per_point = max(self.max_values_per_point, values.len());
+        self.point_to_values.insert(id, values.to_vec());
+    }
+
+    fn remove(&mut self, id: PointOffsetType) {
+        if let Some(values) = self.point_to_values.remove(&id) {
+            for value in values {
+                let hash = encode_geohash(value.lon, value.lat, GEOHASH_MAX_PRECISION);
+                if let Some(points) = self.points_map.get_mut(&hash) {
+                    if points.remove(&id) {
+                        self.values_count -= 1;
+                    }
+                    if points.is_empty() {
+                        self.points_map.remove(&hash);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Iterate over all stored geohashes, which start with `prefix`
+    fn prefix_iter<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a BTreeSet<PointOffsetType>)> + 'a {
+        self.points_map
+            .range(prefix.to_string()..)
+            .take_while(move |(hash, _)| hash.starts_with(prefix))
+    }
+
+    fn prefix_values_count(&self, prefix: &str) -> usize {
+        self.prefix_iter(prefix)
+            .map(|(_, points)| points.len())
+            .sum()
+    }
+
+    /// Number of distinct points with at least one value in the geohashes, starting with `prefix`
+    fn prefix_points_count(&self, prefix: &str) -> usize {
+        if self.max_values_per_point <= 1 {
+            return self.prefix_values_count(prefix);
+        }
+        self.prefix_iter(prefix)
+            .flat_map(|(_, points)| points.iter())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn matched_points(&self, condition: &GeoCondition) -> Vec<PointOffsetType> {
+        let mut candidates: Vec<_> = condition
+            .covering_geohashes()
+            .iter()
+            .flat_map(|prefix| {
+                self.prefix_iter(prefix)
+                    .flat_map(|(_, points)| points.iter().cloned())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+            .into_iter()
+            .filter(|id| {
+                self.point_to_values
+                    .get(id)
+                    .map(|values| values.iter().any(|value| condition.check_point(value)))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    fn estimate(&self, condition: &GeoCondition) -> CardinalityEstimation {
+        let mut min_values = 0;
+        let mut max_values = 0;
+        for prefix in condition.covering_geohashes() {
+            let count = self.prefix_values_count(&prefix);
+            max_values += count;
+            if condition.contains_rect(decode_geohash_bounds(&prefix)) {
+                min_values += count;
+            }
+        }
+        let values_per_point = max(1, self.max_values_per_point);
+        let expected_min = if min_values > 0 {
+            max(1, min_values / values_per_point)
+        } else {
+            0
+        };
+        let expected_max = min(self.points_count(), max_values);
+        CardinalityEstimation {
+            primary_clauses: vec![],
+            min: expected_min,
+            exp: (expected_min + expected_max) / 2,
+            max: expected_max,
+        }
+    }
+
+    /// Largest geohash regions with at least `threshold` points
+    fn collect_blocks(&self, prefix: String, threshold: usize, blocks: &mut Vec<(String, usize)>) {
+        let count = self.prefix_values_count(&prefix);
+        if count < threshold {
+            return;
+        }
+        if prefix.len() >= GEOHASH_MAX_PRECISION {
+            blocks.push((prefix, count));
+            return;
+        }
+        let mut children_count = 0;
+        let mut children = vec![];
+        for code in BASE32_CODES.iter() {
+            let child = format!("{}{}", prefix, *code as char);
+            let child_count = self.prefix_values_count(&child);
+            if child_count >= threshold {
+                children_count += child_count;
+                children.push(child);
+            }
+        }
+        if children.is_empty() || count - children_count >= threshold {
+            blocks.push((prefix, count));
+        }
+        for child in children {
+            self.collect_blocks(child, threshold, blocks);
+        }
+    }
+}
+
+impl PayloadFieldIndex for GeoMapIndex {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        if let PayloadType::Geo(points) = value {
+            self.add_many(id, points)
+        }
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.remove(id)
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        GeoCondition::from_field_condition(condition).map(|geo_condition| {
+            let points: Box<dyn Iterator<Item = PointOffsetType>> =
+                Box::new(self.matched_points(&geo_condition).into_iter());
+            points
+        })
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        GeoCondition::from_field_condition(condition).map(|geo_condition| {
+            let mut estimation = self.estimate(&geo_condition);
+            estimation
+                .primary_clauses
+                .push(PrimaryCondition::Condition(condition.clone()));
+            estimation
+        })
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        let mut blocks = vec![];
+        for code in BASE32_CODES.iter() {
+            self.collect_blocks((*code as char).to_string(), max(threshold, 1), &mut blocks);
+        }
+        Box::new(blocks.into_iter().map(move |(hash, _)| {
+            let (min_lon, min_lat, max_lon, max_lat) = decode_geohash_bounds(&hash);
+            PayloadBlockCondition {
+                condition: FieldCondition {
+                    key: key.clone(),
+                    r#match: None,
+                    range: None,
+                    geo_bounding_box: Some(GeoBoundingBox {
+                        top_left: GeoPoint {
+                            lon: min_lon,
+                            lat: max_lat,
+                        },
+                        bottom_right: GeoPoint {
+                            lon: max_lon,
+                            lat: min_lat,
+                        },
+                    }),
+                    geo_radius: None,
+                },
+                cardinality: self.prefix_points_count(&hash),
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload_storage::condition_checker::ValueChecker;
+    use itertools::Itertools;
+    use rand::{thread_rng, Rng};
+
+    const BERLIN: GeoPoint = GeoPoint {
+        lat: 52.52197645,
+        lon: 13.413637435864272,
+    };
+
+    fn random_payloads(num_points: usize) -> Vec<PayloadType> {
+        let mut rnd = thread_rng();
+        (0..num_points)
+            .map(|_| {
+                PayloadType::Geo(vec![GeoPoint {
+                    lon: BERLIN.lon + rnd.gen_range(-1.0..1.0),
+                    lat: BERLIN.lat + rnd.gen_range(-1.0..1.0),
+                }])
+            })
+            .collect()
+    }
+
+    fn build_index(payloads: &[PayloadType]) -> GeoMapIndex {
+        let mut index = GeoMapIndex::default();
+        for (id, payload) in payloads.iter().enumerate() {
+            index.add_point(id as PointOffsetType, payload);
+        }
+        index
+    }
+
+    fn geo_condition(
+        geo_bounding_box: Option<GeoBoundingBox>,
+        geo_radius: Option<GeoRadius>,
+    ) -> FieldCondition {
+        FieldCondition {
+            key: "location".to_string(),
+            r#match: None,
+            range: None,
+            geo_bounding_box,
+            geo_radius,
+        }
+    }
+
+    #[test]
+    fn test_geohash_encoding() {
+        assert_eq!(encode_geohash(10.40744, 57.64911, 11), "u4pruydqqvj");
+        let (min_lon, min_lat, max_lon, max_lat) = decode_geohash_bounds("u4pruydqqvj");
+        assert!(min_lon <= 10.40744 && 10.40744 <= max_lon);
+        assert!(min_lat <= 57.64911 && 57.64911 <= max_lat);
+    }
+
+    #[test]
+    fn test_radius_filter() {
+        let payloads = random_payloads(1000);
+        let index = build_index(&payloads);
+
+        for radius in [1_000.0, 20_000.0, 50_000.0, 500_000.0].iter() {
+            let geo_radius = GeoRadius {
+                center: BERLIN,
+                radius: *radius,
+            };
+            let condition = geo_condition(None, Some(geo_radius.clone()));
+
+            let found = index.filter(&condition).unwrap().collect_vec();
+            let expected = (0..payloads.len() as PointOffsetType)
+                .filter(|id| geo_radius.check(&payloads[*id as usize]))
+                .collect_vec();
+            assert_eq!(found, expected);
+
+            let estimation = index.estimate_cardinality(&condition).unwrap();
+            assert!(estimation.min <= found.len());
+            assert!(found.len() <= estimation.max);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box_filter() {
+        let payloads = random_payloads(1000);
+        let mut index = build_index(&payloads);
+
+        let bounding_box = GeoBoundingBox {
+            top_left: GeoPoint {
+                lon: BERLIN.lon - 0.3,
+                lat: BERLIN.lat + 0.2,
+            },
+            bottom_right: GeoPoint {
+                lon: BERLIN.lon + 0.1,
+                lat: BERLIN.lat - 0.4,
+            },
+        };
+        let condition = geo_condition(Some(bounding_box.clone()), None);
+
+        let found = index.filter(&condition).unwrap().collect_vec();
+        let expected = (0..payloads.len() as PointOffsetType)
+            .filter(|id| bounding_box.check(&payloads[*id as usize]))
+            .collect_vec();
+        assert_eq!(found, expected);
+
+        let estimation = index.estimate_cardinality(&condition).unwrap();
+        assert!(estimation.min <= found.len());
+        assert!(found.len() <= estimation.max);
+
+        if let Some(first) = found.first() {
+            index.remove_point(*first);
+            assert_eq!(index.filter(&condition).unwrap().count(), found.len() - 1);
+        }
+    }
+
+    #[test]
+    fn test_radius_across_antimeridian() {
+        let mut index = GeoMapIndex::default();
+        index.add_point(
+            0,
+            &PayloadType::Geo(vec![GeoPoint {
+                lon: 179.9,
+                lat: 0.0,
+            }]),
+        );
+        index.add_point(
+            1,
+            &PayloadType::Geo(vec![GeoPoint {
+                lon: -179.9,
+                lat: 0.0,
+            }]),
+        );
+        index.add_point(2, &PayloadType::Geo(vec![GeoPoint { lon: 0.0, lat: 0.0 }]));
+
+        let condition = geo_condition(
+            None,
+            Some(GeoRadius {
+                center: GeoPoint {
+                    lon: 180.0,
+                    lat: 0.0,
+                },
+                radius: 50_000.0,
+            }),
+        );
+        assert_eq!(index.filter(&condition).unwrap().collect_vec(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_geo_payload_blocks() {
+        let payloads = random_payloads(1000);
+        let index = build_index(&payloads);
+
+        let blocks = index
+            .payload_blocks(100, "location".to_string())
+            .collect_vec();
+        assert!(!blocks.is_empty());
+        for block in blocks {
+            assert!(block.cardinality >= 100);
+            let bounding_box = block.condition.geo_bounding_box.unwrap();
+            let inside = payloads
+                .iter()
+                .filter(|payload| bounding_box.check(payload))
+                .count();
+            assert!(inside <= block.cardinality);
+        }
+    }
+
+    #[test]
+    fn test_multi_value_geo_payload_blocks() {
+        // Each point has several values in a few meters from each other
+        let payloads = random_payloads(1000)
+            .into_iter()
+            .map(|payload| match payload {
+                PayloadType::Geo(points) => PayloadType::Geo(
+                    (0..3)
+                        .map(|i| GeoPoint {
+                            lon: points[0].lon + i as f64 * 1e-5,
+                            lat: points[0].lat,
+                        })
+                        .collect(),
+                ),
+                _ => unreachable!(),
+            })
+            .collect_vec();
+        let index = build_index(&payloads);
+
+        let blocks = index
+            .payload_blocks(100, "location".to_string())
+            .collect_vec();
+        assert!(!blocks.is_empty());
+        for block in blocks {
+            let condition = GeoCondition::from_field_condition(&block.condition).unwrap();
+            assert_eq!(index.matched_points(&condition).len(), block.cardinality);
+        }
+    }
+}
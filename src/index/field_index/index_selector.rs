@@ -0,0 +1,24 @@
+use crate::index::field_index::full_text_index::FullTextIndex;
+use crate::index::field_index::geo_index::GeoMapIndex;
+use crate::index::field_index::map_index::MapIndex;
+use crate::index::field_index::numeric_index::NumericIndex;
+use crate::index::field_index::FieldIndex;
+use crate::types::PayloadSchemaType;
+
+/// Selects index types based on field type
+pub fn index_selector(payload_type: &PayloadSchemaType) -> Vec<FieldIndex> {
+    match payload_type {
+        PayloadSchemaType::Keyword => vec![
+            FieldIndex::KeywordIndex(MapIndex::default()),
+            FieldIndex::FullTextIndex(FullTextIndex::default()),
+        ],
+        PayloadSchemaType::Integer => vec![
+            FieldIndex::IntMapIndex(MapIndex::default()),
+            FieldIndex::IntIndex(NumericIndex::default()),
+        ],
+        PayloadSchemaType::Float => vec![FieldIndex::FloatIndex(NumericIndex::default())],
+        PayloadSchemaType::Geo => vec![FieldIndex::GeoIndex(GeoMapIndex::default())],
+        PayloadSchemaType::Datetime => vec![FieldIndex::DatetimeIndex(NumericIndex::default())],
+        PayloadSchemaType::Bool => vec![FieldIndex::BoolIndex(MapIndex::default())],
+    }
+}
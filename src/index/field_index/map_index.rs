@@ -0,0 +1,331 @@
+use crate::index::field_index::{
+    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndex, PrimaryCondition,
+};
+use crate::types::{
+    FieldCondition, IntPayloadType, Match, PayloadKeyType, PayloadType, PointOffsetType,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+/// Inverted index: maps each value of the field into a set of points, which have this value
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapIndex<N: Hash + Eq + Clone> {
+    map: HashMap<N, BTreeSet<PointOffsetType>>,
+    point_to_values: HashMap<PointOffsetType, Vec<N>>,
+    values_count: usize,
+}
+
+impl<N: Hash + Eq + Clone> Default for MapIndex<N> {
+    fn default() -> Self {
+        MapIndex {
+            map: Default::default(),
+            point_to_values: Default::default(),
+            values_count: 0,
+        }
+    }
+}
+
+impl<N: Hash + Eq + Clone> MapIndex<N> {
+    pub fn points_count(&self) -> usize {
+        self.point_to_values.len()
+    }
+
+    /// Iterate over all indexed values together with amount of points, which have this value
+    pub fn values_cardinality(&self) -> impl Iterator<Item = (&N, usize)> + '_ {
+        self.map.iter().map(|(value, points)| (value, points.len()))
+    }
+
+    pub fn get_points(&self, value: &N) -> Option<&BTreeSet<PointOffsetType>> {
+        self.map.get(value)
+    }
+
+    pub fn get_values(&self, id: PointOffsetType) -> Option<&Vec<N>> {
+        self.point_to_values.get(&id)
+    }
+
+    fn add_many(&mut self, id: PointOffsetType, values: &[N]) {
+        self.remove(id);
+        if values.is_empty() {
+            return;
+        }
+        for value in values {
+            if self.map.entry(value.clone()).or_default().insert(id) {
+                self.values_count += 1;
+            }
+        }
+        self.point_to_values.insert(id, values.to_vec());
+    }
+
+    fn remove(&mut self, id: PointOffsetType) {
+        if let Some(values) = self.point_to_values.remove(&id) {
+            for value in values {
+                if let Some(points) = self.map.get_mut(&value) {
+                    if points.remove(&id) {
+                        self.values_count -= 1;
+                    }
+                    if points.is_empty() {
+                        self.map.remove(&value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn match_cardinality(&self, value: &N) -> CardinalityEstimation {
+        let count = self.map.get(value).map(|x| x.len()).unwrap_or(0);
+        CardinalityEstimation {
+            primary_clauses: vec![],
+            min: count,
+            exp: count,
+            max: count,
+        }
+    }
+
+    fn get_iterator(&self, value: &N) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        match self.map.get(value) {
+            None => Box::new(vec![].into_iter()),
+            Some(points) => Box::new(points.iter().cloned()),
+        }
+    }
+
+    fn blocks<'a, F>(
+        &'a self,
+        threshold: usize,
+        key: PayloadKeyType,
+        to_match: F,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + 'a>
+    where
+        F: Fn(&N) -> Match + 'a,
+    {
+        let iter = self
+            .map
+            .iter()
+            .filter(move |(_value, points)| points.len() >= threshold)
+            .map(move |(value, points)| PayloadBlockCondition {
+                condition: FieldCondition {
+                    key: key.clone(),
+                    r#match: Some(to_match(value)),
+                    range: None,
+                    geo_bounding_box: None,
+                    geo_radius: None,
+                },
+                cardinality: points.len(),
+            });
+        Box::new(iter)
+    }
+}
+
+/// Returns match condition if it is the only check of the field condition
+fn exclusive_match(condition: &FieldCondition) -> Option<&Match> {
+    match condition {
+        FieldCondition {
+            r#match: Some(r#match),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+            ..
+        } => Some(r#match),
+        _ => None,
+    }
+}
+
+/// Returns keyword to match if full-text match is not requested along with it
+fn exclusive_keyword(condition: &FieldCondition) -> Option<&String> {
+    exclusive_match(condition)
+        .filter(|r#match| r#match.text.is_none())
+        .and_then(|r#match| r#match.keyword.as_ref())
+}
+
+fn with_primary_clause(
+    mut estimation: CardinalityEstimation,
+    condition: &FieldCondition,
+) -> CardinalityEstimation {
+    estimation
+        .primary_clauses
+        .push(PrimaryCondition::Condition(condition.clone()));
+    estimation
+}
+
+impl PayloadFieldIndex for MapIndex<String> {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        if let PayloadType::Keyword(keywords) = value {
+            self.add_many(id, keywords)
+        }
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.remove(id)
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        exclusive_keyword(condition).map(|keyword| self.get_iterator(keyword))
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        exclusive_keyword(condition)
+            .map(|keyword| with_primary_clause(self.match_cardinality(keyword), condition))
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        self.blocks(threshold, key, |value| Match {
+            keyword: Some(value.clone()),
+            integer: None,
+            text: None,
+            boolean: None,
+        })
+    }
+}
+
+impl PayloadFieldIndex for MapIndex<IntPayloadType> {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        if let PayloadType::Integer(numbers) = value {
+            self.add_many(id, numbers)
+        }
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.remove(id)
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        exclusive_match(condition)
+            .and_then(|r#match| r#match.integer.as_ref())
+            .map(|number| self.get_iterator(number))
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        exclusive_match(condition)
+            .and_then(|r#match| r#match.integer.as_ref())
+            .map(|number| with_primary_clause(self.match_cardinality(number), condition))
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        self.blocks(threshold, key, |value| Match {
+            keyword: None,
+            integer: Some(*value),
+            text: None,
+            boolean: None,
+        })
+    }
+}
+
+impl PayloadFieldIndex for MapIndex<bool> {
+    fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+        if let PayloadType::Bool(flags) = value {
+            self.add_many(id, flags)
+        }
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) {
+        self.remove(id)
+    }
+
+    fn filter(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        exclusive_match(condition)
+            .and_then(|r#match| r#match.boolean.as_ref())
+            .map(|flag| self.get_iterator(flag))
+    }
+
+    fn estimate_cardinality(&self, condition: &FieldCondition) -> Option<CardinalityEstimation> {
+        exclusive_match(condition)
+            .and_then(|r#match| r#match.boolean.as_ref())
+            .map(|flag| with_primary_clause(self.match_cardinality(flag), condition))
+    }
+
+    fn payload_blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        self.blocks(threshold, key, |value| Match {
+            keyword: None,
+            integer: None,
+            text: None,
+            boolean: Some(*value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyword_condition(keyword: &str) -> FieldCondition {
+        FieldCondition {
+            key: "color".to_string(),
+            r#match: Some(Match {
+                keyword: Some(keyword.to_string()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }
+    }
+
+    #[test]
+    fn test_keyword_index() {
+        let mut index: MapIndex<String> = MapIndex::default();
+        index.add_point(
+            0,
+            &PayloadType::Keyword(vec!["red".to_string(), "blue".to_string()]),
+        );
+        index.add_point(1, &PayloadType::Keyword(vec!["red".to_string()]));
+        index.add_point(2, &PayloadType::Keyword(vec!["green".to_string()]));
+        index.add_point(3, &PayloadType::Integer(vec![1]));
+
+        assert_eq!(index.points_count(), 3);
+
+        let red: Vec<_> = index.filter(&keyword_condition("red")).unwrap().collect();
+        assert_eq!(red, vec![0, 1]);
+
+        let estimation = index
+            .estimate_cardinality(&keyword_condition("blue"))
+            .unwrap();
+        assert_eq!(estimation.exp, 1);
+        assert_eq!(estimation.primary_clauses.len(), 1);
+
+        index.remove_point(0);
+        let red: Vec<_> = index.filter(&keyword_condition("red")).unwrap().collect();
+        assert_eq!(red, vec![1]);
+        assert_eq!(index.filter(&keyword_condition("blue")).unwrap().count(), 0);
+        assert_eq!(index.points_count(), 2);
+
+        let blocks: Vec<_> = index.payload_blocks(1, "color".to_string()).collect();
+        assert_eq!(blocks.len(), 2);
+        assert!(index
+            .payload_blocks(2, "color".to_string())
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_int_map_index_ignores_keyword_match() {
+        let mut index: MapIndex<IntPayloadType> = MapIndex::default();
+        index.add_point(0, &PayloadType::Integer(vec![1, 2]));
+        assert!(index.filter(&keyword_condition("1")).is_none());
+        assert!(index
+            .estimate_cardinality(&keyword_condition("1"))
+            .is_none());
+    }
+}
@@ -0,0 +1,421 @@
+use crate::index::field_index::{
+    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndex, PrimaryCondition,
+};
+use crate::types::{
+    FieldCondition, FloatPayloadType, IntPayloadType, PayloadKeyType, PayloadType, PointOffsetType,
+    Range,
+};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min, Ordering};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Element<N> {
+    value: N,
+    id: PointOffsetType,
+}
+
+/// Sorted list of field values, allows to select points by value range
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NumericIndex<N> {
+    /// Values of all points, ordered by value
+    elements: Vec<Element<N>>,
+    point_to_values: HashMap<PointOffsetType, Vec<N>>,
+    max_values_per_point: usize,
+}
+
+impl<N> Default for NumericIndex<N> {
+    fn default() -> Self {
+        NumericIndex {
+            elements: vec![],
+            point_to_values: Default::default(),
+            max_values_per_point: 1,
+        }
+    }
+}
+
+fn cmp_elements<N: ToPrimitive>(
+    a: &N,
+    a_id: PointOffsetType,
+    b: &N,
+    b_id: PointOffsetType,
+) -> Ordering {
+    let a_val = a.to_f64().unwrap_or(FloatPayloadType::NAN);
+    let b_val = b.to_f64().unwrap_or(FloatPayloadType::NAN);
+    a_val
+        .partial_cmp(&b_val)
+        .unwrap_or(Ordering::Equal)
+        .then(a_id.cmp(&b_id))
+}
+
+impl<N: ToPrimitive + Copy> NumericIndex<N> {
+    pub fn points_count(&self) -> usize {
+        self.point_to_values.len()
+    }
+
+    fn add_many(&mut self, id: PointOffsetType, values: &[N]) {
+        self.remove(id);
+        if values.is_empty() {
+            return;
+        }
+        for value in values {
+            let position = self
+                .elements
+                .binary_search_by(|element| cmp_elements(&element.value, element.id, value, id))
+                .unwrap_or_else(|x| x);
+            self.elements
+                .insert(position, Element { value: *value, id });
+        }
+        self.max_values_per_point = max(self.max_values_per_point, values.len());
+        self.point_to_values.insert(id, values.to_vec());
+    }
+
+    /// Add values of the point, which is not in the index yet, to the end of `elements`.
+    /// Elements should be sorted with `sort_elements` after all points are added
+    fn push_many(&mut self, id: PointOffsetType, values: &[N]) {
+        if values.is_empty() {
+            return;
+        }
+        self.elements
+            .extend(values.iter().map(|value| Element { value: *value, id }));
+        self.max_values_per_point = max(self.max_values_per_point, values.len());
+        self.point_to_values.insert(id, values.to_vec());
+    }
+
+    fn sort_elements(&mut self) {
+        self.elements
+            .sort_unstable_by(|a, b| cmp_elements(&a.value, a.id, &b.value, b.id));
+    }
+
+    fn remove(&mut self, id: PointOffsetType) {
+        if let Some(values) = self.point_to_values.remove(&id) {
+            for value in values {
+                if let Ok(position) = self.elements.binary_search_by(|element| {
+                    cmp_elements(&element.value, element.id, &value, id)
+                }) {
+                    self.elements.remove(position);
+                }
+            }
+        }
+    }
+
+    fn value(element: &Element<N>) -> FloatPayloadType {
+        element.value.to_f64().unwrap_or(FloatPayloadType::NAN)
+    }
+
+    /// Bounds of the `elements` slice, which values are satisfying the range
+    fn range_bounds(&self, range: &Range) -> (usize, usize) {
+        let lower = self.elements.partition_point(|element| {
+            let value = Self::value(element);
+            !(range.gt.map_or(true, |x| value > x) && range.gte.map_or(true, |x| value >= x))
+        });
+        let upper = self.elements.partition_point(|element| {
+            let value = Self::value(element);
+            range.lt.map_or(true, |x| value < x) && range.lte.map_or(true, |x| value <= x)
+        });
+        (lower, max(lower, upper))
+    }
+
+    fn range_cardinality(&self, range: &Range) -> CardinalityEstimation {
+        let (lower, upper) = self.range_bounds(range);
+        let values_count = upper - lower;
+        let points_count = self.points_count();
+        let total_values = self.elements.len();
+
+        let expected_min = if values_count > 0 {
+            max(1, values_count / self.max_values_per_point)
+        } else {
+            0
+        };
+        let expected_max = min(points_count, values_count);
+        // Assume values are evenly distributed among points
+        let expected = if total_values > 0 {
+            values_count * points_count / total_values
+        } else {
+            0
+        };
+
+        CardinalityEstimation {
+            primary_clauses: vec![],
+            min: expected_min,
+            exp: min(expected_max, max(expected_min, expected)),
+            max: expected_max,
+        }
+    }
+
+    fn range_iterator(&self, range: &Range) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        let (lower, upper) = self.range_bounds(range);
+        let mut ids: Vec<_> = self.elements[lower..upper]
+            .iter()
+            .map(|element| element.id)
+            .collect();
+        if self.max_values_per_point > 1 {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        Box::new(ids.into_iter())
+    }
+
+    /// Number of distinct points among `elements[start..end]`
+    fn block_points_count(&self, start: usize, end: usize) -> usize {
+        if self.max_values_per_point == 1 {
+            return end - start;
+        }
+        self.elements[start..end]
+            .iter()
+            .map(|element| element.id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn blocks(
+        &self,
+        threshold: usize,
+        key: PayloadKeyType,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        let threshold = max(threshold, 1);
+        let total = self.elements.len();
+        let mut blocks = vec![];
+        if total < threshold {
+            return Box::new(blocks.into_iter());
+        }
+        let mut start = 0;
+        while start < total {
+            let mut end = min(start + threshold, total);
+            // Elements with the same value should always be in the same block
+            while end < total
+                && Self::value(&self.elements[end]) == Self::value(&self.elements[end - 1])
+            {
+                end += 1;
+            }
+            // Do not leave a tail which is smaller than a threshold
+            if total - end < threshold {
+                end = total;
+            }
+            let lower = Self::value(&self.elements[start]);
+            let range = if end < total {
+                Range {
+                    lt: Some(Self::value(&self.elements[end])),
+                    gt: None,
+                    gte: Some(lower),
+                    lte: None,
+                }
+            } else {
+                Range {
+                    lt: None,
+                    gt: None,
+                    gte: Some(lower),
+                    lte: Some(Self::value(&self.elements[total - 1])),
+                }
+            };
+            blocks.push(PayloadBlockCondition {
+                condition: FieldCondition {
+                    key: key.clone(),
+                    r#match: None,
+                    range: Some(range),
+                    geo_bounding_box: None,
+                    geo_radius: None,
+                },
+                cardinality: self.block_points_count(start, end),
+            });
+            start = end;
+        }
+        Box::new(blocks.into_iter())
+    }
+}
+
+/// Returns range condition if it is the only check of the field condition
+fn exclusive_range(condition: &FieldCondition) -> Option<&Range> {
+    match condition {
+        FieldCondition {
+            r#match: None,
+            range: Some(range),
+            geo_bounding_box: None,
+            geo_radius: None,
+            ..
+        } => Some(range),
+        _ => None,
+    }
+}
+
+macro_rules! impl_numeric_field_index {
+    ($value_type:ty, $($payload_variant:path),+) => {
+        impl PayloadFieldIndex for NumericIndex<$value_type> {
+            fn add_point(&mut self, id: PointOffsetType, value: &PayloadType) {
+                match value {
+                    $($payload_variant(numbers))|+ => self.add_many(id, numbers),
+                    _ => {}
+                }
+            }
+
+            fn remove_point(&mut self, id: PointOffsetType) {
+                self.remove(id)
+            }
+
+            fn add_point_on_build(&mut self, id: PointOffsetType, value: &PayloadType) {
+                match value {
+                    $($payload_variant(numbers))|+ => self.push_many(id, numbers),
+                    _ => {}
+                }
+            }
+
+            fn finish_build(&mut self) {
+                self.sort_elements()
+            }
+
+            fn filter(
+                &self,
+                condition: &FieldCondition,
+            ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+                exclusive_range(condition).map(|range| self.range_iterator(range))
+            }
+
+            fn estimate_cardinality(
+                &self,
+                condition: &FieldCondition,
+            ) -> Option<CardinalityEstimation> {
+                exclusive_range(condition).map(|range| {
+                    let mut estimation = self.range_cardinality(range);
+                    estimation
+                        .primary_clauses
+                        .push(PrimaryCondition::Condition(condition.clone()));
+                    estimation
+                })
+            }
+
+            fn payload_blocks(
+                &self,
+                threshold: usize,
+                key: PayloadKeyType,
+            ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+                self.blocks(threshold, key)
+            }
+        }
+    };
+}
+
+impl_numeric_field_index!(FloatPayloadType, PayloadType::Float);
+// Datetime is stored as an integer timestamp, so the same index is used for both
+impl_numeric_field_index!(IntPayloadType, PayloadType::Integer, PayloadType::Datetime);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload_storage::condition_checker::ValueChecker;
+    use itertools::Itertools;
+
+    fn range_condition(range: Range) -> FieldCondition {
+        FieldCondition {
+            key: "price".to_string(),
+            r#match: None,
+            range: Some(range),
+            geo_bounding_box: None,
+            geo_radius: None,
+        }
+    }
+
+    #[test]
+    fn test_range_filter() {
+        let mut index: NumericIndex<FloatPayloadType> = NumericIndex::default();
+        let payloads: Vec<_> = (0..100)
+            .map(|i| PayloadType::Float(vec![i as f64 / 10.0, i as f64]))
+            .collect();
+        for (id, payload) in payloads.iter().enumerate() {
+            index.add_point(id as PointOffsetType, payload);
+        }
+
+        let condition = range_condition(Range {
+            lt: Some(5.0),
+            gt: None,
+            gte: Some(2.5),
+            lte: None,
+        });
+
+        let found = index.filter(&condition).unwrap().collect_vec();
+        let expected = (0..100)
+            .filter(|id| condition.range.unwrap().check(&payloads[*id as usize]))
+            .collect_vec();
+        assert_eq!(found, expected);
+
+        let estimation = index.estimate_cardinality(&condition).unwrap();
+        assert!(estimation.min <= found.len());
+        assert!(found.len() <= estimation.max);
+
+        index.remove_point(30);
+        let found_after_remove = index.filter(&condition).unwrap().collect_vec();
+        assert_eq!(found_after_remove.len(), found.len() - 1);
+        assert!(!found_after_remove.contains(&30));
+    }
+
+    #[test]
+    fn test_build_index() {
+        let payloads: Vec<_> = (0..100)
+            .map(|i| PayloadType::Integer(vec![(i * 37 % 100) as i64, (i % 7) as i64]))
+            .collect();
+        let mut index: NumericIndex<IntPayloadType> = NumericIndex::default();
+        let mut built_index: NumericIndex<IntPayloadType> = NumericIndex::default();
+        for (id, payload) in payloads.iter().enumerate() {
+            index.add_point(id as PointOffsetType, payload);
+            built_index.add_point_on_build(id as PointOffsetType, payload);
+        }
+        built_index.finish_build();
+
+        let condition = range_condition(Range {
+            lt: Some(50.0),
+            gt: None,
+            gte: Some(5.0),
+            lte: None,
+        });
+        assert_eq!(
+            built_index.filter(&condition).unwrap().collect_vec(),
+            index.filter(&condition).unwrap().collect_vec()
+        );
+
+        // Built index accepts updates of single points
+        built_index.add_point(100, &PayloadType::Integer(vec![10]));
+        built_index.remove_point(0);
+        index.add_point(100, &PayloadType::Integer(vec![10]));
+        index.remove_point(0);
+        assert_eq!(
+            built_index.filter(&condition).unwrap().collect_vec(),
+            index.filter(&condition).unwrap().collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_payload_blocks() {
+        let mut index: NumericIndex<IntPayloadType> = NumericIndex::default();
+        for id in 0..95 {
+            index.add_point(id, &PayloadType::Integer(vec![(id % 50) as i64]));
+        }
+
+        let blocks = index.payload_blocks(10, "price".to_string()).collect_vec();
+        assert!(!blocks.is_empty());
+        let mut covered = 0;
+        for block in blocks.iter() {
+            assert!(block.cardinality >= 10);
+            let points = index.filter(&block.condition).unwrap().count();
+            assert_eq!(points, block.cardinality);
+            covered += points;
+        }
+        assert_eq!(covered, 95);
+    }
+
+    #[test]
+    fn test_multi_value_payload_blocks() {
+        let mut index: NumericIndex<IntPayloadType> = NumericIndex::default();
+        for id in 0..95 {
+            let value = (id % 50) as i64;
+            index.add_point(id, &PayloadType::Integer(vec![value, value, value + 1]));
+        }
+
+        // Cardinality of the block is a number of points, not values
+        let blocks = index.payload_blocks(10, "price".to_string()).collect_vec();
+        assert!(!blocks.is_empty());
+        for block in blocks.iter() {
+            let points = index.filter(&block.condition).unwrap().count();
+            assert_eq!(points, block.cardinality);
+        }
+    }
+}
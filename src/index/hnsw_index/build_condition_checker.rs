@@ -1,6 +1,9 @@
 use crate::index::visited_pool::VisitedList;
-use crate::types::{PointOffsetType};
+use crate::payload_storage::ConditionChecker;
+use crate::types::{Filter, PointOffsetType};
 
+/// Condition checker, used while building additional graph links for a single payload block.
+/// Matches only points, which are marked in `filter_list`
 pub struct BuildConditionChecker {
     pub filter_list: VisitedList,
     pub current_point: PointOffsetType,
@@ -13,4 +16,13 @@ impl BuildConditionChecker {
             current_point: PointOffsetType::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+impl ConditionChecker for BuildConditionChecker {
+    fn check(&self, point_id: PointOffsetType, _query: &Filter) -> bool {
+        if point_id == self.current_point {
+            return false; // Do not match current point while inserting it to the graph
+        }
+        self.filter_list.check(point_id)
+    }
+}
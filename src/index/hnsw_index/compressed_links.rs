@@ -0,0 +1,164 @@
+//! Compact in-memory representation of the finalized HNSW graph links.
+//!
+//! Each link list is sorted and stored as varint-encoded deltas between neighbour ids.
+//! All lists are packed into a single buffer, so there are no per-node allocations and no reserved capacity.
+
+use crate::index::hnsw_index::graph_layers::{LayersContainer, LinkContainer};
+use crate::types::PointOffsetType;
+
+/// Links of the graph, packed into a single byte buffer.
+/// Lists are sorted on compression, so the original order of links is not preserved
+#[derive(Debug, Default, Clone)]
+pub struct CompressedLinks {
+    data: Vec<u8>,
+    /// Index of the first link list of each point, `num_points + 1` values
+    level_starts: Vec<usize>,
+    /// Position of each link list in `data`, `num_lists + 1` values
+    list_offsets: Vec<usize>,
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: PointOffsetType) {
+    while value >= 0x80 {
+        data.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Decodes links of a single list one by one, without allocation
+pub struct CompressedLinksIterator<'a> {
+    encoded: std::slice::Iter<'a, u8>,
+    prev: PointOffsetType,
+}
+
+impl<'a> Iterator for CompressedLinksIterator<'a> {
+    type Item = PointOffsetType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut value: PointOffsetType = 0;
+        let mut shift = 0;
+        for &byte in &mut self.encoded {
+            value |= ((byte & 0x7f) as PointOffsetType) << shift;
+            if byte & 0x80 == 0 {
+                self.prev += value;
+                return Some(self.prev);
+            }
+            shift += 7;
+        }
+        None
+    }
+}
+
+impl CompressedLinks {
+    /// Pack links of the points, given in order of their ids
+    pub fn from_layers<I, L>(links_layers: I) -> Self
+    where
+        I: IntoIterator<Item = L>,
+        L: AsRef<[LinkContainer]>,
+    {
+        let links_layers = links_layers.into_iter();
+        let mut compressed = CompressedLinks {
+            data: vec![],
+            level_starts: Vec::with_capacity(links_layers.size_hint().0 + 1),
+            list_offsets: vec![0],
+        };
+        compressed.level_starts.push(0);
+        let mut sorted_links = LinkContainer::new();
+        for layers in links_layers {
+            for links in layers.as_ref() {
+                sorted_links.clone_from(links);
+                sorted_links.sort_unstable();
+                let mut prev = 0;
+                for &link in &sorted_links {
+                    write_varint(&mut compressed.data, link - prev);
+                    prev = link;
+                }
+                compressed.list_offsets.push(compressed.data.len());
+            }
+            let num_lists = compressed.list_offsets.len() - 1;
+            compressed.level_starts.push(num_lists);
+        }
+        compressed.data.shrink_to_fit();
+        compressed
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.level_starts.len() - 1
+    }
+
+    pub fn point_level(&self, point_id: PointOffsetType) -> usize {
+        let point_id = point_id as usize;
+        self.level_starts[point_id + 1] - self.level_starts[point_id] - 1
+    }
+
+    /// Iterate over links of the point on the given level, decoding them on the fly
+    pub fn links(&self, point_id: PointOffsetType, level: usize) -> CompressedLinksIterator<'_> {
+        let list_id = self.level_starts[point_id as usize] + level;
+        let encoded = &self.data[self.list_offsets[list_id]..self.list_offsets[list_id + 1]];
+        CompressedLinksIterator {
+            encoded: encoded.iter(),
+            prev: 0,
+        }
+    }
+
+    /// Decode links of the point on the given level into `links`
+    pub fn decode_links(&self, point_id: PointOffsetType, level: usize, links: &mut LinkContainer) {
+        links.clear();
+        links.extend(self.links(point_id, level));
+    }
+
+    pub fn to_layers(&self) -> Vec<LayersContainer> {
+        (0..self.num_points() as PointOffsetType)
+            .map(|point_id| {
+                (0..=self.point_level(point_id))
+                    .map(|level| {
+                        let mut links = vec![];
+                        self.decode_links(point_id, level, &mut links);
+                        links
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_links() {
+        let links_layers: Vec<LayersContainer> = vec![
+            vec![vec![300, 2, 1_000_000]],
+            vec![vec![0, 2], vec![]],
+            vec![vec![], vec![1], vec![PointOffsetType::MAX, 0]],
+        ];
+        let compressed = CompressedLinks::from_layers(&links_layers);
+        assert_eq!(compressed.num_points(), 3);
+        assert_eq!(compressed.point_level(1), 1);
+        assert_eq!(compressed.links(0, 0).nth(1), Some(300));
+        assert_eq!(compressed.links(2, 1).collect::<Vec<_>>(), vec![1]);
+
+        let mut links = vec![];
+        compressed.decode_links(0, 0, &mut links);
+        assert_eq!(links, vec![2, 300, 1_000_000]);
+        compressed.decode_links(1, 1, &mut links);
+        assert!(links.is_empty());
+        compressed.decode_links(2, 2, &mut links);
+        assert_eq!(links, vec![0, PointOffsetType::MAX]);
+
+        let sorted_layers: Vec<LayersContainer> = links_layers
+            .into_iter()
+            .map(|layers| {
+                layers
+                    .into_iter()
+                    .map(|mut links| {
+                        links.sort_unstable();
+                        links
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(compressed.to_layers(), sorted_layers);
+    }
+}
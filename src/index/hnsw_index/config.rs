@@ -16,16 +16,30 @@ pub struct HnswGraphConfig {
     pub ef: usize,
     /// Minimal number of vectors to perform indexing
     pub indexing_threshold: usize,
+    /// Number of threads to build the graph with, 0 - all available CPUs
+    #[serde(default)]
+    pub max_indexing_threads: usize,
+    /// Store links of the built graph in compressed form
+    #[serde(default)]
+    pub compress_links: bool,
 }
 
 impl HnswGraphConfig {
-    pub fn new(m: usize, ef_construct: usize, indexing_threshold: usize) -> Self {
+    pub fn new(
+        m: usize,
+        ef_construct: usize,
+        indexing_threshold: usize,
+        max_indexing_threads: usize,
+        compress_links: bool,
+    ) -> Self {
         HnswGraphConfig {
             m,
             m0: m * 2,
             ef_construct,
             ef: ef_construct,
             indexing_threshold,
+            max_indexing_threads,
+            compress_links,
         }
     }
 
@@ -91,6 +91,22 @@ impl EntryPoints {
         None
     }
 
+    /// Remove entry points, which do not satisfy `checker` any more, e.g. deleted ones.
+    /// If no main entry point is left, the highest of `candidates` takes its place
+    pub fn replace_invalid<F>(&mut self, candidates: impl Iterator<Item = EntryPoint>, checker: F)
+    where
+        F: Fn(PointOffsetType) -> bool,
+    {
+        self.entry_points.retain(|entry| checker(entry.point_id));
+        self.extra_entry_points
+            .retain(|entry| checker(entry.point_id));
+        if self.entry_points.is_empty() {
+            if let Some(best_candidate) = candidates.filter(|entry| checker(entry.point_id)).max() {
+                self.entry_points.push(best_candidate);
+            }
+        }
+    }
+
     /// Find the highest EntryPoint which satisfies filtering condition of `checker`
     pub fn get_entry_point<F>(&self, checker: F) -> Option<EntryPoint>
     where
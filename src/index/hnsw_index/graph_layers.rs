@@ -1,7 +1,8 @@
 use crate::common::file_operations::{atomic_save_bin, read_bin};
 use crate::common::utils::rev_range;
 use crate::entry::entry_point::OperationResult;
-use crate::index::hnsw_index::entry_points::EntryPoints;
+use crate::index::hnsw_index::entry_points::{EntryPoint, EntryPoints};
+use crate::index::hnsw_index::graph_links::{DeletedPoints, GraphLinks, LinksIterator};
 use crate::index::hnsw_index::point_scorer::FilteredScorer;
 use crate::index::hnsw_index::search_context::SearchContext;
 use crate::index::visited_pool::{VisitedList, VisitedPool};
@@ -21,6 +22,10 @@ pub type LinkContainerRef<'a> = &'a [PointOffsetType];
 pub type LayersContainer = Vec<LinkContainer>;
 
 pub const HNSW_GRAPH_FILE: &str = "graph.bin";
+pub const HNSW_LINKS_FILE: &str = "links.bin";
+
+/// Share of deleted points, after which entry points are replaced
+const ENTRY_POINTS_REFRESH_RATIO: f64 = 0.1;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct GraphLayers {
@@ -32,14 +37,143 @@ pub struct GraphLayers {
     // Exclude points according to "not closer than base" heuristic?
     use_heuristic: bool,
     // Factor of level probability
-    links_layers: Vec<LayersContainer>,
+    // Links are stored in a separate file, which is memory-mapped on load
+    #[serde(skip)]
+    links_layers: GraphLinks,
     entry_points: EntryPoints,
+    // Deleted points are traversed while searching, but never returned.
+    // Flags are stored together with links
+    #[serde(skip)]
+    deleted: DeletedPoints,
+    // Number of deletions since the last replacement of entry points
+    deleted_since_refresh: usize,
 
     // Fields used on construction phase only
     #[serde(skip)]
     visited_pool: VisitedPool,
 }
 
+/// Search routines, shared by the graph and by its concurrent builder
+pub trait GraphLayersBase {
+    fn get_visited_list_from_pool(&self) -> VisitedList;
+
+    fn return_visited_list_to_pool(&self, visited_list: VisitedList);
+
+    /// Call `f` with iterator over links of the point on the given level
+    fn with_links<F, R>(&self, point_id: PointOffsetType, level: usize, f: F) -> R
+    where
+        F: FnOnce(LinksIterator) -> R;
+
+    /// Get M based on current level
+    fn get_m(&self, level: usize) -> usize;
+
+    fn is_deleted(&self, point_id: PointOffsetType) -> bool;
+
+    /// Check if point could be returned as a search result
+    fn is_result_point(&self, point_id: PointOffsetType, points_scorer: &FilteredScorer) -> bool {
+        !self.is_deleted(point_id) && points_scorer.check_point(point_id)
+    }
+
+    /// Greedy search for closest points within a single graph layer
+    fn _search_on_level(
+        &self,
+        searcher: &mut SearchContext,
+        level: usize,
+        visited_list: &mut VisitedList,
+        points_scorer: &FilteredScorer,
+    ) {
+        while let Some(candidate) = searcher.candidates.pop() {
+            if candidate.score < searcher.lower_bound() {
+                break;
+            }
+            self.with_links(candidate.idx, level, |links| {
+                // Deleted points are only passed through, the rest should match the filter
+                let mut links_iter = links
+                    .filter(|point_id| !visited_list.check_and_update_visited(*point_id))
+                    .filter(|point_id| {
+                        self.is_deleted(*point_id) || points_scorer.check_filter(*point_id)
+                    });
+
+                points_scorer.score_iterable_points_with_deleted(
+                    &mut links_iter,
+                    self.get_m(level),
+                    |score_point| {
+                        if self.is_result_point(score_point.idx, points_scorer) {
+                            searcher.process_candidate(score_point)
+                        } else {
+                            searcher.process_transit_candidate(score_point)
+                        }
+                    },
+                );
+            });
+        }
+    }
+
+    fn search_on_level(
+        &self,
+        level_entry: ScoredPointOffset,
+        level: usize,
+        ef: usize,
+        points_scorer: &FilteredScorer,
+        existing_links: LinkContainerRef,
+    ) -> FixedLengthPriorityQueue<ScoredPointOffset> {
+        let mut visited_list = self.get_visited_list_from_pool();
+        visited_list.check_and_update_visited(level_entry.idx);
+        let mut search_context = SearchContext::new(level_entry, ef);
+
+        self._search_on_level(&mut search_context, level, &mut visited_list, points_scorer);
+
+        for &existing_link in existing_links {
+            if !visited_list.check(existing_link) && !self.is_deleted(existing_link) {
+                search_context.process_candidate(ScoredPointOffset {
+                    idx: existing_link,
+                    score: points_scorer.score_point(existing_link),
+                })
+            }
+        }
+
+        self.return_visited_list_to_pool(visited_list);
+        search_context.nearest
+    }
+
+    /// Greedy searches for entry point of level `target_level`.
+    /// Beam size is 1.
+    fn search_entry(
+        &self,
+        entry_point: PointOffsetType,
+        top_level: usize,
+        target_level: usize,
+        points_scorer: &FilteredScorer,
+    ) -> ScoredPointOffset {
+        let mut current_point = ScoredPointOffset {
+            idx: entry_point,
+            score: points_scorer.score_point(entry_point),
+        };
+        for level in rev_range(top_level, target_level) {
+            let mut changed = true;
+            while changed {
+                changed = false;
+                self.with_links(current_point.idx, level, |links| {
+                    let mut links_iter = links.filter(|point_id| {
+                        self.is_deleted(*point_id) || points_scorer.check_filter(*point_id)
+                    });
+                    points_scorer.score_iterable_points_with_deleted(
+                        &mut links_iter,
+                        self.get_m(level),
+                        |score_point| {
+                            if score_point.score > current_point.score {
+                                changed = true;
+                                current_point = score_point;
+                            }
+                        },
+                    );
+                });
+            }
+        }
+        current_point
+    }
+}
+
 /// Object contains links between nodes for HNSW search
 ///
 /// Assume all scores are similarities. Larger score = closer points
@@ -70,8 +204,10 @@ impl GraphLayers {
             ef_construct,
             level_factor: 1.0 / (m as f64).ln(),
             use_heuristic,
-            links_layers,
+            links_layers: GraphLinks::from_layers(links_layers),
             entry_points: EntryPoints::new(entry_points_num),
+            deleted: DeletedPoints::default(),
+            deleted_since_refresh: 0,
             visited_pool: VisitedPool::new(),
         }
     }
@@ -95,26 +231,61 @@ impl GraphLayers {
         )
     }
 
+    /// Assemble graph from links and entry points, which were built separately
+    pub fn from_built_links(
+        m: usize,
+        m0: usize,
+        ef_construct: usize,
+        use_heuristic: bool,
+        links_layers: Vec<LayersContainer>,
+        entry_points: EntryPoints,
+    ) -> Self {
+        let max_level = links_layers
+            .iter()
+            .map(|layers| layers.len().saturating_sub(1))
+            .max()
+            .unwrap_or(0);
+        GraphLayers {
+            max_level,
+            m,
+            m0,
+            ef_construct,
+            level_factor: 1.0 / (m as f64).ln(),
+            use_heuristic,
+            links_layers: GraphLinks::from_layers(links_layers),
+            entry_points,
+            deleted: DeletedPoints::default(),
+            deleted_since_refresh: 0,
+            visited_pool: VisitedPool::new(),
+        }
+    }
+
     fn num_points(&self) -> usize {
-        self.links_layers.len()
+        self.links_layers.num_points()
+    }
+
+    /// Check if graph has a container for the point, so it might be already linked
+    pub fn contains_point(&self, point_id: PointOffsetType) -> bool {
+        (point_id as usize) < self.num_points()
+    }
+
+    fn check_deleted(deleted: &DeletedPoints, point_id: PointOffsetType) -> bool {
+        deleted.contains(point_id)
     }
 
     pub fn point_level(&self, point_id: PointOffsetType) -> usize {
-        self.links_layers[point_id as usize].len() - 1
+        self.links_layers.point_level(point_id)
     }
 
     /// Get links of current point
-    fn links(&self, point_id: PointOffsetType, level: usize) -> LinkContainerRef {
-        &self.links_layers[point_id as usize][level]
+    fn links(&self, point_id: PointOffsetType, level: usize) -> LinksIterator<'_> {
+        self.links_layers.links(point_id, level)
     }
 
-    /// Get M based on current level
-    fn get_m(&self, level: usize) -> usize {
-        if level == 0 {
-            self.m0
-        } else {
-            self.m
-        }
+    /// Pack links into compact read-only representation, see `CompressedLinks`.
+    /// Links of points, modified afterwards, are kept unpacked
+    pub fn compress_links(&mut self) {
+        self.links_layers.compress();
     }
 
     /// Generate random level for a new point, according to geometric distribution
@@ -122,17 +293,11 @@ impl GraphLayers {
     where
         R: Rng + ?Sized,
     {
-        let distribution = Uniform::new(0.0, 1.0);
-        let sample: f64 = rng.sample(distribution);
-        let picked_level = -sample.ln() * self.level_factor;
-        picked_level.round() as usize
+        random_layer(self.level_factor, rng)
     }
 
     fn set_levels(&mut self, point_id: PointOffsetType, level: usize) {
-        if self.links_layers.len() <= point_id as usize {
-            self.links_layers.resize(point_id as usize, vec![]);
-        }
-        let point_layers = &mut self.links_layers[point_id as usize];
+        let point_layers = self.links_layers.point_layers_mut(point_id);
         while point_layers.len() <= level {
             let mut links = vec![];
             links.reserve(self.m);
@@ -141,90 +306,8 @@ impl GraphLayers {
         self.max_level = max(level, self.max_level);
     }
 
-    /// Greedy search for closest points within a single graph layer
-    fn _search_on_level(
-        &self,
-        searcher: &mut SearchContext,
-        level: usize,
-        visited_list: &mut VisitedList,
-        points_scorer: &FilteredScorer,
-    ) {
-        while let Some(candidate) = searcher.candidates.pop() {
-            if candidate.score < searcher.lower_bound() {
-                break;
-            }
-            let mut links_iter = self
-                .links(candidate.idx, level)
-                .iter()
-                .cloned()
-                .filter(|point_id| !visited_list.check_and_update_visited(*point_id));
-
-            points_scorer.score_iterable_points(
-                &mut links_iter,
-                self.get_m(level),
-                |score_point| searcher.process_candidate(score_point),
-            );
-        }
-    }
-
-    fn search_on_level(
-        &self,
-        level_entry: ScoredPointOffset,
-        level: usize,
-        ef: usize,
-        points_scorer: &FilteredScorer,
-        existing_links: LinkContainerRef,
-    ) -> FixedLengthPriorityQueue<ScoredPointOffset> {
-        let mut visited_list = self.visited_pool.get(self.num_points());
-        visited_list.check_and_update_visited(level_entry.idx);
-        let mut search_context = SearchContext::new(level_entry, ef);
-
-        self._search_on_level(&mut search_context, level, &mut visited_list, points_scorer);
-
-        for &existing_link in existing_links {
-            if !visited_list.check(existing_link) {
-                search_context.process_candidate(ScoredPointOffset {
-                    idx: existing_link,
-                    score: points_scorer.score_point(existing_link),
-                })
-            }
-        }
-
-        self.visited_pool.return_back(visited_list);
-        search_context.nearest
-    }
-
-    /// Greedy searches for entry point of level `target_level`.
-    /// Beam size is 1.
-    fn search_entry(
-        &self,
-        entry_point: PointOffsetType,
-        top_level: usize,
-        target_level: usize,
-        points_scorer: &FilteredScorer,
-    ) -> ScoredPointOffset {
-        let mut current_point = ScoredPointOffset {
-            idx: entry_point,
-            score: points_scorer.score_point(entry_point),
-        };
-        for level in rev_range(top_level, target_level) {
-            let mut changed = true;
-            while changed {
-                changed = false;
-                let mut links = self.links(current_point.idx, level).iter().cloned();
-                points_scorer.score_iterable_points(&mut links, self.get_m(level), |score_point| {
-                    if score_point.score > current_point.score {
-                        changed = true;
-                        current_point = score_point;
-                    }
-                });
-            }
-        }
-        current_point
-    }
-
     /// Connect new point to links, so that links contains only closest points
-    fn connect_new_point<F>(
+    pub fn connect_new_point<F>(
         links: &mut LinkContainer,
         new_point_id: PointOffsetType,
         target_point_id: PointOffsetType,
@@ -234,6 +317,9 @@ impl GraphLayers {
         F: FnMut(PointOffsetType, PointOffsetType) -> ScoreType,
     {
         // ToDo: binary search here ? (most likely does not worth it)
+        if links.contains(&new_point_id) {
+            return;
+        }
         let new_to_target = score_internal(target_point_id, new_point_id);
 
         let mut id_to_insert = links.len();
@@ -254,7 +340,7 @@ impl GraphLayers {
     }
 
     /// https://github.com/nmslib/hnswlib/issues/99
-    fn select_candidate_with_heuristic_from_sorted<F>(
+    pub fn select_candidate_with_heuristic_from_sorted<F>(
         candidates: impl Iterator<Item = ScoredPointOffset>,
         m: usize,
         mut score_internal: F,
@@ -285,8 +371,8 @@ impl GraphLayers {
     }
 
     /// https://github.com/nmslib/hnswlib/issues/99
-    fn select_candidates_with_heuristic<F>(
-        candidates: FixedLengthPriorityQueue<ScoredPointOffset>,
+    pub fn select_candidates_with_heuristic<F>(
+        candidates: impl IntoIterator<Item = ScoredPointOffset>,
         m: usize,
         score_internal: F,
     ) -> Vec<PointOffsetType>
@@ -297,6 +383,51 @@ impl GraphLayers {
         Self::select_candidate_with_heuristic_from_sorted(closest_iter, m, score_internal)
     }
 
+    /// Add new point to links of the target point.
+    /// If there are too many links already, only diverse ones are kept, see heuristic above
+    pub fn connect_new_point_with_heuristic<F>(
+        links: &mut LinkContainer,
+        new_point_id: PointOffsetType,
+        target_point_id: PointOffsetType,
+        level_m: usize,
+        deleted: &DeletedPoints,
+        mut score_internal: F,
+    ) where
+        F: FnMut(PointOffsetType, PointOffsetType) -> ScoreType,
+    {
+        if links.contains(&new_point_id) {
+            return;
+        }
+        if links.len() < level_m {
+            // If linked point is lack of neighbours
+            links.push(new_point_id);
+            return;
+        }
+        let mut candidates = BinaryHeap::with_capacity(level_m + 1);
+        candidates.push(ScoredPointOffset {
+            idx: new_point_id,
+            score: score_internal(new_point_id, target_point_id),
+        });
+        for link in links
+            .iter()
+            .take(level_m)
+            .cloned()
+            .filter(|x| !Self::check_deleted(deleted, *x))
+        {
+            candidates.push(ScoredPointOffset {
+                idx: link,
+                score: score_internal(link, target_point_id),
+            });
+        }
+        let selected_candidates = Self::select_candidate_with_heuristic_from_sorted(
+            candidates.into_sorted_vec().into_iter().rev(),
+            level_m,
+            score_internal,
+        );
+        links.clear();
+        links.extend(selected_candidates);
+    }
+
     pub fn link_new_point(
         &mut self,
         point_id: PointOffsetType,
@@ -309,8 +440,9 @@ impl GraphLayers {
 
         self.set_levels(point_id, level);
 
+        let deleted = &self.deleted;
         let entry_point_opt = self.entry_points.new_point(point_id, level, |point_id| {
-            points_scorer.check_point(point_id)
+            !Self::check_deleted(deleted, point_id) && points_scorer.check_point(point_id)
         });
         match entry_point_opt {
             // New point is a new empty entry (for this filter, at least)
@@ -343,59 +475,46 @@ impl GraphLayers {
 
                 for curr_level in (0..=linking_level).rev() {
                     let level_m = self.get_m(curr_level);
-                    let existing_links = &self.links_layers[point_id as usize][curr_level];
+                    let existing_links = self.links(point_id, curr_level).collect_vec();
 
                     let nearest_points = self.search_on_level(
                         level_entry,
                         curr_level,
                         self.ef_construct,
                         points_scorer,
-                        existing_links,
+                        &existing_links,
                     );
 
+                    // Point, which is linked again, could find itself among the nearest ones.
+                    // Deleted entry of the search should not be linked as well
+                    let is_other = |nearest_point: &ScoredPointOffset| {
+                        nearest_point.idx != point_id
+                            && !Self::check_deleted(deleted, nearest_point.idx)
+                    };
+
                     if self.use_heuristic {
-                        let selected_nearest =
-                            Self::select_candidates_with_heuristic(nearest_points, level_m, scorer);
-                        self.links_layers[point_id as usize][curr_level]
+                        let selected_nearest = Self::select_candidates_with_heuristic(
+                            nearest_points.into_iter().filter(is_other),
+                            level_m,
+                            scorer,
+                        );
+                        self.links_layers.point_layers_mut(point_id)[curr_level]
                             .clone_from(&selected_nearest);
 
                         for &other_point in &selected_nearest {
-                            let other_point_links =
-                                &mut self.links_layers[other_point as usize][curr_level];
-                            if other_point_links.len() < level_m {
-                                // If linked point is lack of neighbours
-                                other_point_links.push(point_id);
-                            } else {
-                                let mut candidates = BinaryHeap::with_capacity(level_m + 1);
-                                candidates.push(ScoredPointOffset {
-                                    idx: point_id,
-                                    score: scorer(point_id, other_point),
-                                });
-                                for other_point_link in
-                                    other_point_links.iter().take(level_m).cloned()
-                                {
-                                    candidates.push(ScoredPointOffset {
-                                        idx: other_point_link,
-                                        score: scorer(other_point_link, other_point),
-                                    });
-                                }
-                                let selected_candidates =
-                                    Self::select_candidate_with_heuristic_from_sorted(
-                                        candidates.into_sorted_vec().into_iter().rev(),
-                                        level_m,
-                                        scorer,
-                                    );
-                                for (idx, selected) in
-                                    selected_candidates.iter().cloned().enumerate()
-                                {
-                                    other_point_links[idx] = selected;
-                                }
-                            }
+                            Self::connect_new_point_with_heuristic(
+                                &mut self.links_layers.point_layers_mut(other_point)[curr_level],
+                                point_id,
+                                other_point,
+                                level_m,
+                                deleted,
+                                scorer,
+                            );
                         }
                     } else {
-                        for nearest_point in &nearest_points {
+                        for nearest_point in nearest_points.iter().filter(|x| is_other(x)) {
                             Self::connect_new_point(
-                                &mut self.links_layers[point_id as usize][curr_level],
+                                &mut self.links_layers.point_layers_mut(point_id)[curr_level],
                                 nearest_point.idx,
                                 point_id,
                                 level_m,
@@ -403,7 +522,8 @@ impl GraphLayers {
                             );
 
                             Self::connect_new_point(
-                                &mut self.links_layers[nearest_point.idx as usize][curr_level],
+                                &mut self.links_layers.point_layers_mut(nearest_point.idx)
+                                    [curr_level],
                                 point_id,
                                 nearest_point.idx,
                                 level_m,
@@ -421,11 +541,13 @@ impl GraphLayers {
 
     pub fn merge_from_other(&mut self, other: GraphLayers) {
         let mut visited_list = self.visited_pool.get(self.num_points());
-        if other.links_layers.len() > self.links_layers.len() {
-            self.links_layers.resize(other.links_layers.len(), vec![])
+        let links_layers = self.links_layers.layers_mut();
+        let other_links_layers = other.links_layers.into_layers();
+        if other_links_layers.len() > links_layers.len() {
+            links_layers.resize(other_links_layers.len(), vec![])
         }
-        for (point_id, layers) in other.links_layers.into_iter().enumerate() {
-            let current_layers = &mut self.links_layers[point_id];
+        for (point_id, layers) in other_links_layers.into_iter().enumerate() {
+            let current_layers = &mut links_layers[point_id];
             for (level, other_links) in layers.into_iter().enumerate() {
                 if current_layers.len() <= level {
                     current_layers.push(other_links)
@@ -455,10 +577,16 @@ impl GraphLayers {
         ef: usize,
         points_scorer: &FilteredScorer,
     ) -> Vec<ScoredPointOffset> {
+        // Prefer entry point, matching the filter. Otherwise search starts from any live point,
+        // and continues through its neighbours, which match the filter
         let entry_point = match self
             .entry_points
-            .get_entry_point(|point_id| points_scorer.check_point(point_id))
-        {
+            .get_entry_point(|point_id| self.is_result_point(point_id, points_scorer))
+            .or_else(|| {
+                self.entry_points.get_entry_point(|point_id| {
+                    !self.is_deleted(point_id) && points_scorer.raw_scorer.check_point(point_id)
+                })
+            }) {
             None => return vec![],
             Some(ep) => ep,
         };
@@ -467,27 +595,181 @@ impl GraphLayers {
             self.search_entry(entry_point.point_id, entry_point.level, 0, points_scorer);
 
         let nearest = self.search_on_level(zero_level_entry, 0, max(top, ef), points_scorer, &[]);
-        nearest.into_iter().take(top).collect_vec()
+        nearest
+            .into_iter()
+            .filter(|scored_point| self.is_result_point(scored_point.idx, points_scorer))
+            .take(top)
+            .collect_vec()
+    }
+
+    /// Mark point as deleted and reconnect its neighbours with each other,
+    /// so they stay reachable without passing through the deleted point.
+    /// Should be called while vector of the point is still available for `points_scorer`
+    pub fn delete_point(&mut self, point_id: PointOffsetType, points_scorer: &FilteredScorer) {
+        if !self.contains_point(point_id) || self.is_deleted(point_id) {
+            return;
+        }
+        self.deleted.insert(point_id);
+        self.deleted_since_refresh += 1;
+
+        let scorer = |a, b| points_scorer.score_internal(a, b);
+
+        for level in 0..=self.point_level(point_id) {
+            let level_m = self.get_m(level);
+            let neighbours = self
+                .links(point_id, level)
+                .filter(|x| !self.is_deleted(*x))
+                .collect_vec();
+
+            for &neighbour in &neighbours {
+                let candidates = self
+                    .links(neighbour, level)
+                    .chain(neighbours.iter().cloned())
+                    .filter(|x| *x != neighbour && !self.is_deleted(*x))
+                    .unique()
+                    .map(|x| ScoredPointOffset {
+                        idx: x,
+                        score: scorer(x, neighbour),
+                    })
+                    .sorted()
+                    .rev();
+
+                let selected = if self.use_heuristic {
+                    Self::select_candidate_with_heuristic_from_sorted(candidates, level_m, scorer)
+                } else {
+                    candidates.take(level_m).map(|x| x.idx).collect()
+                };
+                self.links_layers.point_layers_mut(neighbour)[level] = selected;
+            }
+        }
+
+        let deleted_ratio = self.deleted_since_refresh as f64 / self.num_points() as f64;
+        let no_entry_left = self
+            .entry_points
+            .get_entry_point(|x| !self.is_deleted(x))
+            .is_none();
+        if deleted_ratio > ENTRY_POINTS_REFRESH_RATIO || no_entry_left {
+            self.replace_entry_points();
+        }
+    }
+
+    /// Replace deleted entry points with the highest of remaining points
+    fn replace_entry_points(&mut self) {
+        let deleted = &self.deleted;
+        let links_layers = &self.links_layers;
+        let candidates = (0..self.num_points() as PointOffsetType).map(|point_id| EntryPoint {
+            point_id,
+            level: links_layers.point_level(point_id),
+        });
+        self.entry_points
+            .replace_invalid(candidates, |x| !Self::check_deleted(deleted, x));
+        self.deleted_since_refresh = 0;
     }
 
     pub fn get_path(path: &Path) -> PathBuf {
         path.join(HNSW_GRAPH_FILE)
     }
 
-    pub fn load(path: &Path) -> OperationResult<Self> {
-        read_bin(path)
+    pub fn get_links_path(path: &Path) -> PathBuf {
+        path.join(HNSW_LINKS_FILE)
     }
 
-    pub fn save(&self, path: &Path) -> OperationResult<()> {
-        atomic_save_bin(path, self)
+    /// Load graph parameters and map its links from the file without reading them.
+    /// Graph of previous versions, which keeps links in `graph.bin`, is converted first
+    pub fn load(graph_path: &Path, links_path: &Path) -> OperationResult<Self> {
+        if !links_path.exists() {
+            Self::convert_from_legacy(graph_path, links_path)?;
+        }
+        let mut graph: Self = read_bin(graph_path)?;
+        let (links_layers, deleted) = GraphLinks::load(links_path)?;
+        graph.links_layers = links_layers;
+        graph.deleted = deleted;
+        Ok(graph)
+    }
+
+    /// Move links of the graph, stored within `graph.bin`, into links file
+    fn convert_from_legacy(graph_path: &Path, links_path: &Path) -> OperationResult<()> {
+        let legacy: LegacyGraphLayersV0 = read_bin(graph_path)?;
+        let graph = GraphLayers {
+            max_level: legacy.max_level,
+            m: legacy.m,
+            m0: legacy.m0,
+            ef_construct: legacy.ef_construct,
+            level_factor: legacy.level_factor,
+            use_heuristic: legacy.use_heuristic,
+            links_layers: GraphLinks::from_layers(legacy.links_layers),
+            entry_points: legacy.entry_points,
+            deleted: DeletedPoints::from_flags(vec![]),
+            deleted_since_refresh: 0,
+            visited_pool: VisitedPool::new(),
+        };
+        graph.save(graph_path, links_path)
+    }
+
+    pub fn save(&self, graph_path: &Path, links_path: &Path) -> OperationResult<()> {
+        self.links_layers.save(links_path, &self.deleted)?;
+        atomic_save_bin(graph_path, self)
     }
 }
 
+/// Graph with links in `graph.bin`, as it was stored before links were moved into separate file
+#[derive(Deserialize, Serialize, Debug)]
+struct LegacyGraphLayersV0 {
+    max_level: usize,
+    m: usize,
+    m0: usize,
+    ef_construct: usize,
+    level_factor: f64,
+    use_heuristic: bool,
+    links_layers: Vec<LayersContainer>,
+    entry_points: EntryPoints,
+}
+
+impl GraphLayersBase for GraphLayers {
+    fn get_visited_list_from_pool(&self) -> VisitedList {
+        self.visited_pool.get(self.num_points())
+    }
+
+    fn return_visited_list_to_pool(&self, visited_list: VisitedList) {
+        self.visited_pool.return_back(visited_list);
+    }
+
+    fn with_links<F, R>(&self, point_id: PointOffsetType, level: usize, f: F) -> R
+    where
+        F: FnOnce(LinksIterator) -> R,
+    {
+        f(self.links(point_id, level))
+    }
+
+    fn get_m(&self, level: usize) -> usize {
+        if level == 0 {
+            self.m0
+        } else {
+            self.m
+        }
+    }
+
+    fn is_deleted(&self, point_id: PointOffsetType) -> bool {
+        Self::check_deleted(&self.deleted, point_id)
+    }
+}
+
+/// Generate random level for a new point, according to geometric distribution
+pub fn random_layer<R>(level_factor: f64, rng: &mut R) -> usize
+where
+    R: Rng + ?Sized,
+{
+    let distribution = Uniform::new(0.0, 1.0);
+    let sample: f64 = rng.sample(distribution);
+    let picked_level = -sample.ln() * level_factor;
+    picked_level.round() as usize
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::fixtures::index_fixtures::{
-        random_vector, TestRawScorerProducer,
+        random_vector, FakeConditionChecker, TestRawScorerProducer,
     };
     use crate::types::{Distance, VectorElementType};
     use itertools::Itertools;
@@ -547,14 +829,23 @@ mod tests {
         for &id in &insert_ids {
             let level_m = graph_layers.get_m(0);
             GraphLayers::connect_new_point(
-                &mut graph_layers.links_layers[0][0],
+                &mut graph_layers.links_layers.point_layers_mut(0)[0],
                 id,
                 0,
                 level_m,
                 scorer,
             )
         }
-        assert_eq!(graph_layers.links(0, 0), &vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            graph_layers.links(0, 0).collect_vec(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+
+        // Links, not selected by the heuristic, are removed together with deleted ones
+        let mut links = vec![1, 2, 3, 4, 5, 6];
+        let deleted = DeletedPoints::from_flags(vec![false, false, true]);
+        GraphLayers::connect_new_point_with_heuristic(&mut links, 7, 0, m, &deleted, scorer);
+        assert_eq!(links, vec![1, 3, 6]);
     }
 
     fn search_in_graph(
@@ -563,9 +854,12 @@ mod tests {
         vector_storage: &TestRawScorerProducer,
         graph: &GraphLayers,
     ) -> Vec<ScoredPointOffset> {
+        let fake_condition_checker = FakeConditionChecker {};
         let raw_scorer = vector_storage.get_raw_scorer(query.to_owned());
         let scorer = FilteredScorer {
             raw_scorer: &raw_scorer,
+            condition_checker: &fake_condition_checker,
+            filter: None,
         };
         let ef = 16;
         graph.search(top, ef, &scorer)
@@ -597,11 +891,14 @@ mod tests {
             use_heuristic,
         );
 
+        let fake_condition_checker = FakeConditionChecker {};
         for idx in 0..(num_vectors as PointOffsetType) {
             let added_vector = vector_holder.vectors[idx as usize].to_vec();
             let raw_scorer = vector_holder.get_raw_scorer(added_vector.clone());
             let scorer = FilteredScorer {
                 raw_scorer: &raw_scorer,
+                condition_checker: &fake_condition_checker,
+                filter: None,
             };
             let level = graph_layers.get_random_layer(rng);
             graph_layers.link_new_point(idx, level, &scorer);
@@ -625,14 +922,17 @@ mod tests {
         let mut graph_layers =
             GraphLayers::new(num_vectors, m, m * 2, ef_construct, entry_points_num, false);
 
-        graph_layers.links_layers[0][0] = vec![1, 2, 3, 4, 5, 6];
+        graph_layers.links_layers.point_layers_mut(0)[0] = vec![1, 2, 3, 4, 5, 6];
 
         let linking_idx: PointOffsetType = 7;
 
+        let fake_condition_checker = FakeConditionChecker {};
         let added_vector = vector_holder.vectors[linking_idx as usize].to_vec();
         let raw_scorer = vector_holder.get_raw_scorer(added_vector);
         let scorer = FilteredScorer {
             raw_scorer: &raw_scorer,
+            condition_checker: &fake_condition_checker,
+            filter: None,
         };
 
         let nearest_on_level = graph_layers.search_on_level(
@@ -646,10 +946,7 @@ mod tests {
             &[],
         );
 
-        assert_eq!(
-            nearest_on_level.len(),
-            graph_layers.links_layers[0][0].len() + 1
-        );
+        assert_eq!(nearest_on_level.len(), graph_layers.links(0, 0).count() + 1);
 
         for nearest in &nearest_on_level {
             // eprintln!("nearest = {:#?}", nearest);
@@ -677,15 +974,170 @@ mod tests {
         let dir = TempDir::new("graph_dir").unwrap();
 
         let path = GraphLayers::get_path(dir.path());
-        graph_layers.save(&path).unwrap();
+        let links_path = GraphLayers::get_links_path(dir.path());
+        graph_layers.save(&path, &links_path).unwrap();
 
-        let graph2 = GraphLayers::load(&path).unwrap();
+        let graph2 = GraphLayers::load(&path, &links_path).unwrap();
+        assert!(graph2.links_layers.is_mapped());
 
         let res2 = search_in_graph(&query, top, &vector_holder, &graph2);
 
         assert_eq!(res1, res2)
     }
 
+    #[test]
+    fn test_update_loaded_graph() {
+        let num_vectors = 100;
+        let dim = 8;
+        let top = 5;
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        // The last vector is linked after loading
+        let vector_holder =
+            TestRawScorerProducer::new(dim, num_vectors + 1, Distance::Cosine, &mut rng);
+        let mut graph_layers = GraphLayers::new(num_vectors, M, M * 2, 16, 10, true);
+        let fake_condition_checker = FakeConditionChecker {};
+        for idx in 0..num_vectors as PointOffsetType {
+            let raw_scorer =
+                vector_holder.get_raw_scorer(vector_holder.vectors[idx as usize].to_vec());
+            let scorer = FilteredScorer {
+                raw_scorer: &raw_scorer,
+                condition_checker: &fake_condition_checker,
+                filter: None,
+            };
+            let level = graph_layers.get_random_layer(&mut rng);
+            graph_layers.link_new_point(idx, level, &scorer);
+        }
+
+        let dir = TempDir::new("graph_dir").unwrap();
+        let path = GraphLayers::get_path(dir.path());
+        let links_path = GraphLayers::get_links_path(dir.path());
+        graph_layers.save(&path, &links_path).unwrap();
+        let mut graph_layers = GraphLayers::load(&path, &links_path).unwrap();
+
+        let deleted_id = graph_layers.links(0, 0).next().unwrap();
+        let raw_scorer =
+            vector_holder.get_raw_scorer(vector_holder.vectors[deleted_id as usize].to_vec());
+        let scorer = FilteredScorer {
+            raw_scorer: &raw_scorer,
+            condition_checker: &fake_condition_checker,
+            filter: None,
+        };
+        graph_layers.delete_point(deleted_id, &scorer);
+
+        let new_id = num_vectors as PointOffsetType;
+        let raw_scorer =
+            vector_holder.get_raw_scorer(vector_holder.vectors[new_id as usize].to_vec());
+        let scorer = FilteredScorer {
+            raw_scorer: &raw_scorer,
+            condition_checker: &fake_condition_checker,
+            filter: None,
+        };
+        graph_layers.link_new_point(new_id, 0, &scorer);
+
+        // Only changed points are copied from the mapped file
+        assert!(graph_layers.links_layers.is_mapped());
+        assert!(graph_layers.links_layers.changed_count() < num_vectors / 2);
+        assert_eq!(graph_layers.num_points(), num_vectors + 1);
+        assert!(graph_layers.links(new_id, 0).next().is_some());
+
+        let query = random_vector(&mut rng, dim);
+        let res1 = search_in_graph(&query, top, &vector_holder, &graph_layers);
+        assert!(res1.iter().all(|x| x.idx != deleted_id));
+
+        graph_layers.save(&path, &links_path).unwrap();
+        let graph2 = GraphLayers::load(&path, &links_path).unwrap();
+        assert_eq!(graph2.links_layers.changed_count(), 0);
+        assert!(graph2.is_deleted(deleted_id));
+        assert_eq!(graph2.num_points(), num_vectors + 1);
+        for point_id in 0..=num_vectors as PointOffsetType {
+            for level in 0..=graph_layers.point_level(point_id) {
+                assert_eq!(
+                    graph2.links(point_id, level).collect_vec(),
+                    graph_layers.links(point_id, level).collect_vec()
+                );
+            }
+        }
+
+        let res2 = search_in_graph(&query, top, &vector_holder, &graph2);
+        assert_eq!(res1, res2)
+    }
+
+    #[test]
+    fn test_load_legacy_graph() {
+        let num_vectors = 100;
+        let dim = 8;
+        let top = 5;
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let (vector_holder, graph_layers) = create_graph_layer(num_vectors, dim, true, &mut rng);
+        let query = random_vector(&mut rng, dim);
+        let res1 = search_in_graph(&query, top, &vector_holder, &graph_layers);
+
+        let legacy = LegacyGraphLayersV0 {
+            max_level: graph_layers.max_level,
+            m: graph_layers.m,
+            m0: graph_layers.m0,
+            ef_construct: graph_layers.ef_construct,
+            level_factor: graph_layers.level_factor,
+            use_heuristic: graph_layers.use_heuristic,
+            links_layers: graph_layers.links_layers.to_layers(),
+            entry_points: graph_layers.entry_points.clone(),
+        };
+
+        let dir = TempDir::new("graph_dir").unwrap();
+        let path = GraphLayers::get_path(dir.path());
+        let links_path = GraphLayers::get_links_path(dir.path());
+
+        std::fs::write(&path, bincode::serialize(&legacy).unwrap()).unwrap();
+
+        let graph2 = GraphLayers::load(&path, &links_path).unwrap();
+        assert!(links_path.exists());
+        assert!(graph2.links_layers.is_mapped());
+        assert_eq!(graph2.links_layers.to_layers(), legacy.links_layers);
+        let res2 = search_in_graph(&query, top, &vector_holder, &graph2);
+        assert_eq!(res1, res2);
+
+        // Converted graph is loaded as is
+        let graph3 = GraphLayers::load(&path, &links_path).unwrap();
+        assert_eq!(graph3.links_layers.to_layers(), legacy.links_layers);
+    }
+
+    #[test]
+    fn test_compressed_links_search() {
+        let num_vectors = 100;
+        let dim = 8;
+        let top = 5;
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let (vector_holder, mut graph_layers) =
+            create_graph_layer(num_vectors, dim, true, &mut rng);
+
+        let query = random_vector(&mut rng, dim);
+        let res1 = search_in_graph(&query, top, &vector_holder, &graph_layers);
+
+        graph_layers.compress_links();
+        assert!(graph_layers.links_layers.is_compressed());
+        let res2 = search_in_graph(&query, top, &vector_holder, &graph_layers);
+
+        assert_eq!(res1, res2);
+
+        // Compressed links are saved in flat layout and packed again after loading
+        let dir = TempDir::new("graph_dir").unwrap();
+        let path = GraphLayers::get_path(dir.path());
+        let links_path = GraphLayers::get_links_path(dir.path());
+        graph_layers.save(&path, &links_path).unwrap();
+        let mut graph2 = GraphLayers::load(&path, &links_path).unwrap();
+        graph2.compress_links();
+        assert!(graph2.links_layers.is_compressed());
+        let res3 = search_in_graph(&query, top, &vector_holder, &graph2);
+
+        assert_eq!(res1, res3)
+    }
+
     #[test]
     fn test_add_points() {
         let num_vectors = 1000;
@@ -702,15 +1154,15 @@ mod tests {
 
         assert!(main_entry.level > 0);
 
-        let num_levels = graph_layers
-            .links_layers
-            .iter()
-            .map(|x| x.len())
+        let num_levels = (0..num_vectors as PointOffsetType)
+            .map(|x| graph_layers.point_level(x) + 1)
             .max()
             .unwrap();
         assert_eq!(main_entry.level + 1, num_levels);
 
-        let total_links_0: usize = graph_layers.links_layers.iter().map(|x| x[0].len()).sum();
+        let total_links_0: usize = (0..num_vectors as PointOffsetType)
+            .map(|x| graph_layers.links(x, 0).count())
+            .sum();
 
         assert!(total_links_0 > 0);
 
@@ -737,6 +1189,82 @@ mod tests {
         assert_eq!(reference_top.into_vec(), graph_search);
     }
 
+    #[test]
+    fn test_delete_points() {
+        let num_vectors = 1000;
+        let dim = 8;
+        let top = 5;
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let (mut vector_holder, mut graph_layers) =
+            create_graph_layer(num_vectors, dim, true, &mut rng);
+
+        let main_entry = graph_layers
+            .entry_points
+            .get_entry_point(|_x| true)
+            .unwrap();
+        let fake_condition_checker = FakeConditionChecker {};
+        let deleted_points = (0..num_vectors as PointOffsetType)
+            .filter(|idx| idx % 2 == 0 || *idx == main_entry.point_id)
+            .collect_vec();
+        for &idx in &deleted_points {
+            let raw_scorer =
+                vector_holder.get_raw_scorer(vector_holder.vectors[idx as usize].to_vec());
+            let scorer = FilteredScorer {
+                raw_scorer: &raw_scorer,
+                condition_checker: &fake_condition_checker,
+                filter: None,
+            };
+            graph_layers.delete_point(idx, &scorer);
+        }
+        for &idx in &deleted_points {
+            vector_holder.deleted.set(idx as usize, true);
+        }
+
+        let new_entry = graph_layers
+            .entry_points
+            .get_entry_point(|x| !graph_layers.is_deleted(x))
+            .unwrap();
+        assert_ne!(new_entry.point_id, main_entry.point_id);
+
+        // Neighbours of deleted points are linked with each other
+        for idx in 0..num_vectors as PointOffsetType {
+            if !graph_layers.is_deleted(idx) {
+                let mut links = graph_layers.links(idx, 0);
+                assert!(links.any(|x| !graph_layers.is_deleted(x)));
+            }
+        }
+
+        let attempts = 100;
+        let mut hits = 0;
+        for _ in 0..attempts {
+            let query = random_vector(&mut rng, dim);
+            let processed_query = Array::from(
+                vector_holder
+                    .metric
+                    .preprocess(&query)
+                    .unwrap_or_else(|| query.clone()),
+            );
+            let mut reference_top = FixedLengthPriorityQueue::new(top);
+            for (idx, vec) in vector_holder.vectors.iter().enumerate() {
+                if !vector_holder.deleted[idx] {
+                    reference_top.push(ScoredPointOffset {
+                        idx: idx as PointOffsetType,
+                        score: vector_holder.metric.blas_similarity(vec, &processed_query),
+                    });
+                }
+            }
+
+            let graph_search = search_in_graph(&query, top, &vector_holder, &graph_layers);
+            assert!(graph_search.iter().all(|x| !graph_layers.is_deleted(x.idx)));
+            if reference_top.into_vec() == graph_search {
+                hits += 1;
+            }
+        }
+        assert!(attempts - hits < 15, "hits: {} of {}", hits, attempts);
+    }
+
     #[test]
     #[ignore]
     fn test_draw_hnsw_graph() {
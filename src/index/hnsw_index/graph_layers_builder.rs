@@ -0,0 +1,351 @@
+use crate::index::hnsw_index::entry_points::{EntryPoint, EntryPoints};
+use crate::index::hnsw_index::graph_layers::{
+    random_layer, GraphLayers, GraphLayersBase, LayersContainer, LinkContainer,
+};
+use crate::index::hnsw_index::graph_links::{DeletedPoints, LinksIterator};
+use crate::index::hnsw_index::point_scorer::FilteredScorer;
+use crate::index::visited_pool::{VisitedList, VisitedPool};
+use crate::types::PointOffsetType;
+use crate::vector_storage::ScoredPointOffset;
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use std::cmp::min;
+
+pub type LockedLayersContainer = Vec<RwLock<LinkContainer>>;
+
+/// Same as `GraphLayers`, but allows to link points from multiple threads.
+/// Links of each node are locked separately, levels of all points should be set before linking
+pub struct GraphLayersBuilder {
+    m: usize,
+    m0: usize,
+    ef_construct: usize,
+    level_factor: f64,
+    use_heuristic: bool,
+    links_layers: Vec<LockedLayersContainer>,
+    entry_points: Mutex<EntryPoints>,
+    // Points, which are already linked. Only these could be used as entry points
+    ready_list: RwLock<Vec<bool>>,
+    visited_pool: VisitedPool,
+}
+
+impl GraphLayersBase for GraphLayersBuilder {
+    fn get_visited_list_from_pool(&self) -> VisitedList {
+        self.visited_pool.get(self.links_layers.len())
+    }
+
+    fn return_visited_list_to_pool(&self, visited_list: VisitedList) {
+        self.visited_pool.return_back(visited_list);
+    }
+
+    fn with_links<F, R>(&self, point_id: PointOffsetType, level: usize, f: F) -> R
+    where
+        F: FnOnce(LinksIterator) -> R,
+    {
+        let links = self.links_layers[point_id as usize][level].read();
+        f(links.as_slice().into())
+    }
+
+    fn get_m(&self, level: usize) -> usize {
+        if level == 0 {
+            self.m0
+        } else {
+            self.m
+        }
+    }
+
+    fn is_deleted(&self, _point_id: PointOffsetType) -> bool {
+        false
+    }
+}
+
+impl GraphLayersBuilder {
+    pub fn new(
+        num_vectors: usize,
+        m: usize,
+        m0: usize,
+        ef_construct: usize,
+        entry_points_num: usize,
+        use_heuristic: bool,
+    ) -> Self {
+        let links_layers = (0..num_vectors)
+            .map(|_| vec![RwLock::new(Vec::with_capacity(m0))])
+            .collect();
+
+        GraphLayersBuilder {
+            m,
+            m0,
+            ef_construct,
+            level_factor: 1.0 / (m as f64).ln(),
+            use_heuristic,
+            links_layers,
+            entry_points: Mutex::new(EntryPoints::new(entry_points_num)),
+            ready_list: RwLock::new(vec![false; num_vectors]),
+            visited_pool: VisitedPool::new(),
+        }
+    }
+
+    pub fn get_random_layer<R>(&self, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        random_layer(self.level_factor, rng)
+    }
+
+    pub fn set_levels(&mut self, point_id: PointOffsetType, level: usize) {
+        if self.links_layers.len() <= point_id as usize {
+            let m0 = self.m0;
+            self.links_layers.resize_with(point_id as usize + 1, || {
+                vec![RwLock::new(Vec::with_capacity(m0))]
+            });
+            self.ready_list
+                .get_mut()
+                .resize(point_id as usize + 1, false);
+        }
+        let point_layers = &mut self.links_layers[point_id as usize];
+        while point_layers.len() <= level {
+            point_layers.push(RwLock::new(Vec::with_capacity(self.m)));
+        }
+    }
+
+    pub fn point_level(&self, point_id: PointOffsetType) -> usize {
+        self.links_layers[point_id as usize].len() - 1
+    }
+
+    /// Link point, which level is already set. Could be called from multiple threads
+    pub fn link_new_point(&self, point_id: PointOffsetType, points_scorer: &FilteredScorer) {
+        let level = self.point_level(point_id);
+
+        // Points, which are still being linked by other threads, are not used as entries
+        let entry_point_opt = self.entry_points.lock().get_entry_point(|point_id| {
+            self.is_ready(point_id) && points_scorer.check_point(point_id)
+        });
+
+        if let Some(entry_point) = entry_point_opt {
+            self.link_with_entry(point_id, level, entry_point, points_scorer);
+        }
+        self.ready_list.write()[point_id as usize] = true;
+        self.entry_points
+            .lock()
+            .new_point(point_id, level, |point_id| {
+                points_scorer.check_point(point_id)
+            });
+    }
+
+    fn is_ready(&self, point_id: PointOffsetType) -> bool {
+        self.ready_list.read()[point_id as usize]
+    }
+
+    fn link_with_entry(
+        &self,
+        point_id: PointOffsetType,
+        level: usize,
+        entry_point: EntryPoint,
+        points_scorer: &FilteredScorer,
+    ) {
+        let mut level_entry = if entry_point.level > level {
+            self.search_entry(
+                entry_point.point_id,
+                entry_point.level,
+                level,
+                points_scorer,
+            )
+        } else {
+            ScoredPointOffset {
+                idx: entry_point.point_id,
+                score: points_scorer.score_internal(point_id, entry_point.point_id),
+            }
+        };
+        // minimal common level for entry points
+        let linking_level = min(level, entry_point.level);
+
+        let scorer = |a, b| points_scorer.score_internal(a, b);
+
+        for curr_level in (0..=linking_level).rev() {
+            let level_m = self.get_m(curr_level);
+            // Other threads could already link some points to the new one
+            let existing_links = self.links_layers[point_id as usize][curr_level]
+                .read()
+                .clone();
+
+            let nearest_points = self.search_on_level(
+                level_entry,
+                curr_level,
+                self.ef_construct,
+                points_scorer,
+                &existing_links,
+            );
+            let is_other = |nearest_point: &ScoredPointOffset| nearest_point.idx != point_id;
+
+            if self.use_heuristic {
+                let selected_nearest = GraphLayers::select_candidates_with_heuristic(
+                    nearest_points.into_iter().filter(is_other),
+                    level_m,
+                    scorer,
+                );
+                {
+                    let mut links = self.links_layers[point_id as usize][curr_level].write();
+                    // Other threads could link their points to this one, before or during the search.
+                    // These links are kept the same way, as if the points were linked after this one
+                    let added_by_others = links
+                        .iter()
+                        .cloned()
+                        .filter(|x| !selected_nearest.contains(x))
+                        .collect::<Vec<_>>();
+                    links.clone_from(&selected_nearest);
+                    for other_point in added_by_others {
+                        GraphLayers::connect_new_point_with_heuristic(
+                            &mut links,
+                            other_point,
+                            point_id,
+                            level_m,
+                            &DeletedPoints::default(),
+                            scorer,
+                        );
+                    }
+                }
+
+                for &other_point in &selected_nearest {
+                    GraphLayers::connect_new_point_with_heuristic(
+                        &mut self.links_layers[other_point as usize][curr_level].write(),
+                        point_id,
+                        other_point,
+                        level_m,
+                        &DeletedPoints::default(),
+                        scorer,
+                    );
+                }
+            } else {
+                for nearest_point in nearest_points.iter().filter(|x| is_other(x)) {
+                    GraphLayers::connect_new_point(
+                        &mut self.links_layers[point_id as usize][curr_level].write(),
+                        nearest_point.idx,
+                        point_id,
+                        level_m,
+                        scorer,
+                    );
+
+                    GraphLayers::connect_new_point(
+                        &mut self.links_layers[nearest_point.idx as usize][curr_level].write(),
+                        point_id,
+                        nearest_point.idx,
+                        level_m,
+                        scorer,
+                    );
+                    if nearest_point.score > level_entry.score {
+                        level_entry = *nearest_point
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn into_graph_layers(self) -> GraphLayers {
+        let links_layers: Vec<LayersContainer> = self
+            .links_layers
+            .into_iter()
+            .map(|layers| layers.into_iter().map(RwLock::into_inner).collect())
+            .collect();
+
+        GraphLayers::from_built_links(
+            self.m,
+            self.m0,
+            self.ef_construct,
+            self.use_heuristic,
+            links_layers,
+            self.entry_points.into_inner(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::index_fixtures::{
+        random_vector, FakeConditionChecker, TestRawScorerProducer,
+    };
+    use crate::spaces::tools::FixedLengthPriorityQueue;
+    use crate::types::Distance;
+    use ndarray::Array;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rayon::prelude::*;
+    use rayon::ThreadPoolBuilder;
+
+    #[test]
+    fn test_parallel_graph_build() {
+        let num_vectors = 1000;
+        let dim = 8;
+        let m = 8;
+        let ef_construct = 32;
+        let top = 5;
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let vector_holder =
+            TestRawScorerProducer::new(dim, num_vectors, Distance::Cosine, &mut rng);
+        let mut builder = GraphLayersBuilder::new(num_vectors, m, m * 2, ef_construct, 10, true);
+        for idx in 0..(num_vectors as PointOffsetType) {
+            let level = builder.get_random_layer(&mut rng);
+            builder.set_levels(idx, level);
+        }
+
+        let fake_condition_checker = FakeConditionChecker {};
+        let link_point = |idx: PointOffsetType| {
+            let added_vector = vector_holder.vectors[idx as usize].to_vec();
+            let raw_scorer = vector_holder.get_raw_scorer(added_vector);
+            let scorer = FilteredScorer {
+                raw_scorer: &raw_scorer,
+                condition_checker: &fake_condition_checker,
+                filter: None,
+            };
+            builder.link_new_point(idx, &scorer);
+        };
+
+        // Same as in the index building: first points are linked in a single thread
+        let sequential_points = 32;
+        (0..sequential_points).for_each(link_point);
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        pool.install(|| {
+            (sequential_points..num_vectors as PointOffsetType)
+                .into_par_iter()
+                .for_each(link_point)
+        });
+
+        let graph_layers = builder.into_graph_layers();
+
+        let attempts = 100;
+        let mut found = 0;
+        for _ in 0..attempts {
+            let query = random_vector(&mut rng, dim);
+            let processed_query = Array::from(
+                vector_holder
+                    .metric
+                    .preprocess(&query)
+                    .unwrap_or_else(|| query.clone()),
+            );
+            let mut reference_top = FixedLengthPriorityQueue::new(top);
+            for (idx, vec) in vector_holder.vectors.iter().enumerate() {
+                reference_top.push(ScoredPointOffset {
+                    idx: idx as PointOffsetType,
+                    score: vector_holder.metric.blas_similarity(vec, &processed_query),
+                });
+            }
+
+            let raw_scorer = vector_holder.get_raw_scorer(query);
+            let scorer = FilteredScorer {
+                raw_scorer: &raw_scorer,
+                condition_checker: &fake_condition_checker,
+                filter: None,
+            };
+            let graph_search = graph_layers.search(top, 16, &scorer);
+            found += reference_top
+                .into_vec()
+                .iter()
+                .filter(|x| graph_search.iter().any(|y| y.idx == x.idx))
+                .count();
+        }
+        // Parallel build depends on the order, in which threads link points, so only recall is checked
+        let recall = found as f64 / (attempts * top) as f64;
+        assert!(recall > 0.95, "recall: {}", recall);
+    }
+}
@@ -0,0 +1,534 @@
+//! Flat on-disk layout of HNSW graph links, which could be memory-mapped and searched directly.
+//!
+//! File layout, all numbers are stored with native byte order:
+//!
+//! * header: number of points and number of link lists, `u64` each
+//! * `level_starts`: `num_points + 1` values of `u64`, index of the first link list of each point.
+//!   Point has one link list per level, so its levels count is `level_starts[i + 1] - level_starts[i]`
+//! * `list_offsets`: `num_lists + 1` values of `u64`, position of each link list in `links`
+//! * `links`: all link lists one after another, `PointOffsetType` each
+//! * `deleted`: `num_points` bytes, non-zero if the point is deleted
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::index::hnsw_index::compressed_links::{CompressedLinks, CompressedLinksIterator};
+use crate::index::hnsw_index::graph_layers::{LayersContainer, LinkContainerRef};
+use crate::types::PointOffsetType;
+use atomicwrites::AtomicFile;
+use atomicwrites::OverwriteBehavior::AllowOverwrite;
+use memmap::{Mmap, MmapOptions};
+use std::cmp::max;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const HEADER_SIZE: usize = 2 * size_of::<u64>();
+
+/// Write links and deleted flags of all points into a file with flat layout
+pub fn save_graph_links(
+    path: &Path,
+    links_layers: &[LayersContainer],
+    deleted: &DeletedPoints,
+) -> OperationResult<()> {
+    let num_lists: usize = links_layers.iter().map(|layers| layers.len()).sum();
+
+    let af = AtomicFile::new(path, AllowOverwrite);
+    af.write(|f| {
+        let mut writer = BufWriter::new(f);
+        writer.write_all(&(links_layers.len() as u64).to_ne_bytes())?;
+        writer.write_all(&(num_lists as u64).to_ne_bytes())?;
+
+        let mut level_start = 0u64;
+        writer.write_all(&level_start.to_ne_bytes())?;
+        for layers in links_layers {
+            level_start += layers.len() as u64;
+            writer.write_all(&level_start.to_ne_bytes())?;
+        }
+
+        let mut list_offset = 0u64;
+        writer.write_all(&list_offset.to_ne_bytes())?;
+        for links in links_layers.iter().flatten() {
+            list_offset += links.len() as u64;
+            writer.write_all(&list_offset.to_ne_bytes())?;
+        }
+
+        for link in links_layers.iter().flatten().flatten() {
+            writer.write_all(&link.to_ne_bytes())?;
+        }
+
+        for point_id in 0..links_layers.len() as PointOffsetType {
+            writer.write_all(&[deleted.contains(point_id) as u8])?;
+        }
+        writer.flush()
+    })?;
+    Ok(())
+}
+
+/// Read-only links of the graph, mapped from the file with flat layout
+#[derive(Debug)]
+pub struct GraphLinksMmap {
+    path: PathBuf,
+    mmap: Mmap,
+    num_points: usize,
+    num_lists: usize,
+}
+
+impl GraphLinksMmap {
+    pub fn open(path: &Path) -> OperationResult<Self> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        let corrupted = || OperationError::ServiceError {
+            description: format!("Corrupted graph links file {}", path.display()),
+        };
+
+        if mmap.len() < HEADER_SIZE {
+            return Err(corrupted());
+        }
+        let mut graph_links = GraphLinksMmap {
+            path: path.to_owned(),
+            mmap,
+            num_points: 0,
+            num_lists: 0,
+        };
+        graph_links.num_points = graph_links.read_u64(0) as usize;
+        graph_links.num_lists = graph_links.read_u64(1) as usize;
+
+        let links_start = graph_links.links_start();
+        if graph_links.mmap.len() < links_start {
+            return Err(corrupted());
+        }
+        if graph_links.mmap.len() != graph_links.deleted_start() + graph_links.num_points {
+            return Err(corrupted());
+        }
+        Ok(graph_links)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    /// Raw content of the mapped file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Read `u64` number, stored at the given position of the file (in `u64` numbers)
+    fn read_u64(&self, idx: usize) -> u64 {
+        let offset = idx * size_of::<u64>();
+        let mut bytes = [0u8; size_of::<u64>()];
+        bytes.copy_from_slice(&self.mmap[offset..offset + size_of::<u64>()]);
+        u64::from_ne_bytes(bytes)
+    }
+
+    fn level_start(&self, point_id: usize) -> usize {
+        self.read_u64(2 + point_id) as usize
+    }
+
+    fn list_offset(&self, list_id: usize) -> u64 {
+        self.read_u64(2 + self.num_points + 1 + list_id)
+    }
+
+    fn links_start(&self) -> usize {
+        HEADER_SIZE + (self.num_points + 1 + self.num_lists + 1) * size_of::<u64>()
+    }
+
+    fn deleted_start(&self) -> usize {
+        let num_links = self.list_offset(self.num_lists) as usize;
+        self.links_start() + num_links * size_of::<PointOffsetType>()
+    }
+
+    pub fn point_level(&self, point_id: PointOffsetType) -> usize {
+        let point_id = point_id as usize;
+        self.level_start(point_id + 1) - self.level_start(point_id) - 1
+    }
+
+    pub fn links(&self, point_id: PointOffsetType, level: usize) -> LinkContainerRef<'_> {
+        let list_id = self.level_start(point_id as usize) + level;
+        let start = self.list_offset(list_id) as usize;
+        let end = self.list_offset(list_id + 1) as usize;
+        let links_bytes = &self.mmap[self.links_start()..self.deleted_start()];
+        // Links start is aligned, as mmap starts from the page boundary and the offsets are u64
+        let all_links: &[PointOffsetType] = unsafe {
+            std::slice::from_raw_parts(
+                links_bytes.as_ptr() as *const PointOffsetType,
+                links_bytes.len() / size_of::<PointOffsetType>(),
+            )
+        };
+        &all_links[start..end]
+    }
+
+    pub fn is_deleted(&self, point_id: PointOffsetType) -> bool {
+        let point_id = point_id as usize;
+        point_id < self.num_points && self.mmap[self.deleted_start() + point_id] != 0
+    }
+
+    /// Copy links of the point into memory
+    pub fn point_layers(&self, point_id: PointOffsetType) -> LayersContainer {
+        (0..=self.point_level(point_id))
+            .map(|level| self.links(point_id, level).to_vec())
+            .collect()
+    }
+
+    /// Read all links into memory
+    pub fn to_layers(&self) -> Vec<LayersContainer> {
+        (0..self.num_points as PointOffsetType)
+            .map(|point_id| self.point_layers(point_id))
+            .collect()
+    }
+}
+
+/// Deleted flags of the graph points.
+/// Flags, saved with the links, are read from the mapped file, later deletions are kept in memory
+#[derive(Debug, Default)]
+pub struct DeletedPoints {
+    stored: Option<Arc<GraphLinksMmap>>,
+    deleted: Vec<bool>,
+}
+
+impl DeletedPoints {
+    pub fn from_flags(deleted: Vec<bool>) -> Self {
+        DeletedPoints {
+            stored: None,
+            deleted,
+        }
+    }
+
+    pub fn contains(&self, point_id: PointOffsetType) -> bool {
+        self.deleted
+            .get(point_id as usize)
+            .cloned()
+            .unwrap_or(false)
+            || self
+                .stored
+                .as_ref()
+                .map(|stored| stored.is_deleted(point_id))
+                .unwrap_or(false)
+    }
+
+    pub fn insert(&mut self, point_id: PointOffsetType) {
+        if self.deleted.len() <= point_id as usize {
+            self.deleted.resize(point_id as usize + 1, false);
+        }
+        self.deleted[point_id as usize] = true;
+    }
+
+    /// Check if there are deletions, not saved into the links file yet
+    fn has_changes(&self) -> bool {
+        self.deleted.iter().any(|&deleted| deleted)
+    }
+}
+
+/// Links of the point on some level, either read from the slice or decoded on the fly
+pub enum LinksIterator<'a> {
+    Slice(std::iter::Cloned<std::slice::Iter<'a, PointOffsetType>>),
+    Compressed(CompressedLinksIterator<'a>),
+}
+
+impl<'a> From<LinkContainerRef<'a>> for LinksIterator<'a> {
+    fn from(links: LinkContainerRef<'a>) -> Self {
+        LinksIterator::Slice(links.iter().cloned())
+    }
+}
+
+impl<'a> Iterator for LinksIterator<'a> {
+    type Item = PointOffsetType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            LinksIterator::Slice(links) => links.next(),
+            LinksIterator::Compressed(links) => links.next(),
+        }
+    }
+}
+
+/// Storage of the graph links, see `GraphLinks`
+#[derive(Debug)]
+enum LinksStorage {
+    Ram(Vec<LayersContainer>),
+    Compressed(CompressedLinks),
+    Mmap(Arc<GraphLinksMmap>),
+}
+
+impl LinksStorage {
+    fn num_points(&self) -> usize {
+        match self {
+            LinksStorage::Ram(layers) => layers.len(),
+            LinksStorage::Compressed(compressed) => compressed.num_points(),
+            LinksStorage::Mmap(mmap) => mmap.num_points(),
+        }
+    }
+
+    fn point_level(&self, point_id: PointOffsetType) -> usize {
+        match self {
+            LinksStorage::Ram(layers) => layers[point_id as usize].len() - 1,
+            LinksStorage::Compressed(compressed) => compressed.point_level(point_id),
+            LinksStorage::Mmap(mmap) => mmap.point_level(point_id),
+        }
+    }
+
+    fn links(&self, point_id: PointOffsetType, level: usize) -> LinksIterator<'_> {
+        match self {
+            LinksStorage::Ram(layers) => layers[point_id as usize][level].as_slice().into(),
+            LinksStorage::Compressed(compressed) => {
+                LinksIterator::Compressed(compressed.links(point_id, level))
+            }
+            LinksStorage::Mmap(mmap) => mmap.links(point_id, level).into(),
+        }
+    }
+
+    fn point_layers(&self, point_id: PointOffsetType) -> LayersContainer {
+        (0..=self.point_level(point_id))
+            .map(|level| self.links(point_id, level).collect())
+            .collect()
+    }
+}
+
+/// Links of the graph: either loaded into memory, compressed or mapped from the file.
+/// Compressed and mapped links are read-only, so links of changed and new points are kept
+/// in memory on top of them. This way a single update does not load the whole graph
+#[derive(Debug)]
+pub struct GraphLinks {
+    storage: LinksStorage,
+    /// Points of read-only storage, changed or added after loading
+    changed: HashMap<PointOffsetType, LayersContainer>,
+    /// Number of points, including the ones added on top of read-only storage
+    num_points: usize,
+}
+
+impl Default for GraphLinks {
+    fn default() -> Self {
+        GraphLinks::from_layers(vec![])
+    }
+}
+
+impl GraphLinks {
+    pub fn from_layers(links_layers: Vec<LayersContainer>) -> Self {
+        GraphLinks {
+            num_points: links_layers.len(),
+            storage: LinksStorage::Ram(links_layers),
+            changed: HashMap::new(),
+        }
+    }
+
+    pub fn from_mmap(mmap: Arc<GraphLinksMmap>) -> Self {
+        GraphLinks {
+            num_points: mmap.num_points(),
+            storage: LinksStorage::Mmap(mmap),
+            changed: HashMap::new(),
+        }
+    }
+
+    pub fn num_points(&self) -> usize {
+        max(self.num_points, self.storage.num_points())
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, LinksStorage::Mmap(_))
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.storage, LinksStorage::Compressed(_))
+    }
+
+    /// Number of points, which links are kept in memory on top of read-only storage
+    pub fn changed_count(&self) -> usize {
+        self.changed.len()
+    }
+
+    pub fn point_level(&self, point_id: PointOffsetType) -> usize {
+        match self.changed.get(&point_id) {
+            Some(layers) => layers.len() - 1,
+            None if point_id as usize >= self.storage.num_points() => 0,
+            None => self.storage.point_level(point_id),
+        }
+    }
+
+    /// Links of the point on the given level, compressed links are decoded on the fly
+    pub fn links(&self, point_id: PointOffsetType, level: usize) -> LinksIterator<'_> {
+        match self.changed.get(&point_id) {
+            Some(layers) => layers[level].as_slice().into(),
+            None if point_id as usize >= self.storage.num_points() => LinksIterator::from(&[][..]),
+            None => self.storage.links(point_id, level),
+        }
+    }
+
+    /// Copy links of the point into memory
+    fn point_layers(&self, point_id: PointOffsetType) -> LayersContainer {
+        match self.changed.get(&point_id) {
+            Some(layers) => layers.clone(),
+            None if point_id as usize >= self.storage.num_points() => vec![vec![]],
+            None => self.storage.point_layers(point_id),
+        }
+    }
+
+    /// Get links of the point for modification, the point is added if it is not in the graph yet.
+    /// Links of read-only storage are copied into memory for this point only
+    pub fn point_layers_mut(&mut self, point_id: PointOffsetType) -> &mut LayersContainer {
+        let GraphLinks {
+            storage,
+            changed,
+            num_points,
+        } = self;
+        *num_points = max(*num_points, point_id as usize + 1);
+        match storage {
+            LinksStorage::Ram(layers) => {
+                if layers.len() <= point_id as usize {
+                    layers.resize_with(point_id as usize + 1, || vec![vec![]]);
+                }
+                &mut layers[point_id as usize]
+            }
+            storage => changed.entry(point_id).or_insert_with(|| {
+                if (point_id as usize) < storage.num_points() {
+                    storage.point_layers(point_id)
+                } else {
+                    vec![vec![]]
+                }
+            }),
+        }
+    }
+
+    /// Get links of all points for modification, loads all read-only links into memory
+    pub fn layers_mut(&mut self) -> &mut Vec<LayersContainer> {
+        if !matches!(self.storage, LinksStorage::Ram(_)) {
+            *self = GraphLinks::from_layers(self.to_layers());
+        }
+        match &mut self.storage {
+            LinksStorage::Ram(layers) => layers,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Copy links of all points into memory
+    pub fn to_layers(&self) -> Vec<LayersContainer> {
+        (0..self.num_points() as PointOffsetType)
+            .map(|point_id| self.point_layers(point_id))
+            .collect()
+    }
+
+    pub fn into_layers(self) -> Vec<LayersContainer> {
+        match self.storage {
+            LinksStorage::Ram(layers) => layers,
+            _ => self.to_layers(),
+        }
+    }
+
+    /// Pack links into memory, mapped links are read from the file point by point
+    pub fn compress(&mut self) {
+        let compressed = match &self.storage {
+            LinksStorage::Compressed(_) if self.changed.is_empty() => return,
+            LinksStorage::Ram(layers) => CompressedLinks::from_layers(layers),
+            _ => CompressedLinks::from_layers(
+                (0..self.num_points() as PointOffsetType)
+                    .map(|point_id| self.point_layers(point_id)),
+            ),
+        };
+        self.storage = LinksStorage::Compressed(compressed);
+        self.changed.clear();
+    }
+
+    pub fn save(&self, path: &Path, deleted: &DeletedPoints) -> OperationResult<()> {
+        match &self.storage {
+            LinksStorage::Ram(layers) => save_graph_links(path, layers, deleted),
+            LinksStorage::Mmap(mmap) if self.changed.is_empty() && !deleted.has_changes() => {
+                // Unchanged links are already stored in the mapped file
+                if mmap.path() != path {
+                    let af = AtomicFile::new(path, AllowOverwrite);
+                    af.write(|f| f.write_all(mmap.as_bytes()))?;
+                }
+                Ok(())
+            }
+            _ => save_graph_links(path, &self.to_layers(), deleted),
+        }
+    }
+
+    /// Map links and deleted flags from the file without reading them
+    pub fn load(path: &Path) -> OperationResult<(Self, DeletedPoints)> {
+        let mmap = Arc::new(GraphLinksMmap::open(path)?);
+        let deleted = DeletedPoints {
+            stored: Some(mmap.clone()),
+            deleted: vec![],
+        };
+        Ok((GraphLinks::from_mmap(mmap), deleted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_save_and_load_links() {
+        let links_layers: Vec<LayersContainer> = vec![
+            vec![vec![1, 2, 3]],
+            vec![vec![0, 2], vec![2]],
+            vec![vec![], vec![1], vec![]],
+            vec![vec![0]],
+        ];
+        let dir = TempDir::new("graph_links_dir").unwrap();
+        let path = dir.path().join("links.bin");
+        let deleted = DeletedPoints::from_flags(vec![false, true]);
+        save_graph_links(&path, &links_layers, &deleted).unwrap();
+
+        let (mut graph_links, mut deleted) = GraphLinks::load(&path).unwrap();
+        assert_eq!(graph_links.num_points(), 4);
+        assert_eq!(graph_links.point_level(0), 0);
+        assert_eq!(graph_links.point_level(2), 2);
+        assert_eq!(graph_links.links(1, 0).collect_vec(), vec![0, 2]);
+        assert_eq!(graph_links.links(2, 1).collect_vec(), vec![1]);
+        assert!(graph_links.links(2, 2).next().is_none());
+        assert!(deleted.contains(1));
+        assert!(!deleted.contains(2));
+
+        // Changes are kept on top of the mapped file
+        graph_links.point_layers_mut(3)[0].push(1);
+        graph_links.point_layers_mut(5)[0].push(3);
+        deleted.insert(0);
+        assert!(graph_links.is_mapped());
+        assert_eq!(graph_links.changed_count(), 2);
+        assert_eq!(graph_links.num_points(), 6);
+        assert_eq!(graph_links.links(3, 0).collect_vec(), vec![0, 1]);
+        assert_eq!(graph_links.links(5, 0).collect_vec(), vec![3]);
+        assert!(graph_links.links(4, 0).next().is_none());
+        assert_eq!(graph_links.point_level(4), 0);
+
+        graph_links.save(&path, &deleted).unwrap();
+        drop(graph_links);
+        drop(deleted);
+        let (mut graph_links, deleted) = GraphLinks::load(&path).unwrap();
+        assert_eq!(graph_links.num_points(), 6);
+        assert_eq!(graph_links.changed_count(), 0);
+        assert_eq!(graph_links.links(3, 0).collect_vec(), vec![0, 1]);
+        assert_eq!(graph_links.links(5, 0).collect_vec(), vec![3]);
+        assert!(deleted.contains(0));
+        assert!(deleted.contains(1));
+        assert!(!deleted.contains(5));
+
+        graph_links.compress();
+        assert!(graph_links.is_compressed());
+        assert_eq!(graph_links.num_points(), 6);
+        assert_eq!(graph_links.links(3, 0).collect_vec(), vec![0, 1]);
+        assert_eq!(graph_links.links(2, 1).collect_vec(), vec![1]);
+
+        let mut graph_links = GraphLinks::from_layers(links_layers);
+        graph_links.compress();
+        assert!(graph_links.is_compressed());
+        assert_eq!(graph_links.links(1, 0).collect_vec(), vec![0, 2]);
+        assert_eq!(graph_links.point_level(2), 2);
+        graph_links.point_layers_mut(3)[0].push(1);
+        assert!(graph_links.is_compressed());
+        assert_eq!(graph_links.links(3, 0).collect_vec(), vec![0, 1]);
+
+        let empty_path = dir.path().join("empty.bin");
+        save_graph_links(&empty_path, &[], &DeletedPoints::default()).unwrap();
+        assert_eq!(GraphLinks::load(&empty_path).unwrap().0.num_points(), 0);
+    }
+}
@@ -1,29 +1,37 @@
-use crate::entry::entry_point::OperationResult;
+use crate::entry::entry_point::{OperationError, OperationResult};
 use crate::index::hnsw_index::build_condition_checker::BuildConditionChecker;
 use crate::index::hnsw_index::config::HnswGraphConfig;
 use crate::index::hnsw_index::graph_layers::GraphLayers;
+use crate::index::hnsw_index::graph_layers_builder::GraphLayersBuilder;
 use crate::index::hnsw_index::point_scorer::FilteredScorer;
 use crate::index::sample_estimation::sample_check_cardinality;
-use crate::index::{VectorIndex};
+use crate::index::{PayloadIndex, VectorIndex};
+use crate::payload_storage::ConditionChecker;
 use crate::types::Condition::Field;
 use crate::types::{
-    FieldCondition, HnswConfig, PointOffsetType, SearchParams, VectorElementType,
+    FieldCondition, Filter, HnswConfig, PointOffsetType, SearchParams, VectorElementType,
 };
 use crate::vector_storage::{ScoredPointOffset, VectorStorage};
 use atomic_refcell::AtomicRefCell;
 use log::debug;
 use rand::prelude::ThreadRng;
 use rand::thread_rng;
-use std::cmp::max;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::cmp::{max, min};
 use std::fs::create_dir_all;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 const HNSW_USE_HEURISTIC: bool = true;
+/// Number of points, linked in a single thread before the parallel build starts
+const SINGLE_THREADED_BUILD_POINTS: usize = 32;
 
 pub struct HNSWIndex {
     vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+    condition_checker: Arc<dyn ConditionChecker>,
+    payload_index: Arc<AtomicRefCell<dyn PayloadIndex>>,
     config: HnswGraphConfig,
     path: PathBuf,
     thread_rng: ThreadRng,
@@ -34,6 +42,8 @@ impl HNSWIndex {
     pub fn open(
         path: &Path,
         vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+        condition_checker: Arc<dyn ConditionChecker>,
+        payload_index: Arc<AtomicRefCell<dyn PayloadIndex>>,
         hnsw_config: HnswConfig,
     ) -> OperationResult<Self> {
         create_dir_all(path)?;
@@ -47,12 +57,20 @@ impl HNSWIndex {
                 hnsw_config.m,
                 hnsw_config.ef_construct,
                 hnsw_config.full_scan_threshold,
+                hnsw_config.max_indexing_threads,
+                hnsw_config.compress_links,
             )
         };
 
         let graph_path = GraphLayers::get_path(path);
+        let graph_links_path = GraphLayers::get_links_path(path);
         let graph = if graph_path.exists() {
-            GraphLayers::load(&graph_path)?
+            let mut graph = GraphLayers::load(&graph_path, &graph_links_path)?;
+            // Links are stored uncompressed, so they are packed again on every load
+            if config.compress_links {
+                graph.compress_links();
+            }
+            graph
         } else {
             let total_points = vector_storage.borrow().total_vector_count();
             GraphLayers::new(
@@ -67,6 +85,8 @@ impl HNSWIndex {
 
         Ok(HNSWIndex {
             vector_storage,
+            condition_checker,
+            payload_index,
             config,
             path: path.to_owned(),
             thread_rng: rng,
@@ -81,7 +101,8 @@ impl HNSWIndex {
 
     fn save_graph(&self) -> OperationResult<()> {
         let graph_path = GraphLayers::get_path(&self.path);
-        self.graph.save(&graph_path)
+        let graph_links_path = GraphLayers::get_links_path(&self.path);
+        self.graph.save(&graph_path, &graph_links_path)
     }
 
     pub fn save(&self) -> OperationResult<()> {
@@ -96,6 +117,9 @@ impl HNSWIndex {
             .link_new_point(point_id, point_level, points_scorer);
     }
 
+    /// Link points of the payload block with each other in a separate `graph`.
+    /// Links of this graph are merged into the main graph later,
+    /// so filtered search is able to reach all points of the block
     pub fn build_filtered_graph(
         &self,
         graph: &mut GraphLayers,
@@ -104,12 +128,37 @@ impl HNSWIndex {
     ) {
         block_condition_checker.filter_list.next_iteration();
 
+        let filter = Filter::new_must(Field(condition));
+
+        let payload_index = self.payload_index.borrow();
         let vector_storage = self.vector_storage.borrow();
+
+        let points_to_index: Vec<_> = payload_index.query_points(&filter).collect();
+
+        for block_point_id in points_to_index.iter().cloned() {
+            block_condition_checker
+                .filter_list
+                .check_and_update_visited(block_point_id);
+        }
+
+        for block_point_id in points_to_index.iter().cloned() {
+            let raw_scorer = vector_storage.raw_scorer_internal(block_point_id);
+            block_condition_checker.current_point = block_point_id;
+            let points_scorer = FilteredScorer {
+                raw_scorer: raw_scorer.as_ref(),
+                condition_checker: block_condition_checker,
+                filter: Some(&filter),
+            };
+
+            let level = self.graph.point_level(block_point_id);
+            graph.link_new_point(block_point_id, level, &points_scorer);
+        }
     }
 
     pub fn search_with_graph(
         &self,
         vector: &[VectorElementType],
+        filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
     ) -> Vec<ScoredPointOffset> {
@@ -125,8 +174,20 @@ impl HNSWIndex {
 
         let points_scorer = FilteredScorer {
             raw_scorer: raw_scorer.as_ref(),
+            condition_checker: self.condition_checker.deref(),
+            filter,
         };
 
+        if vector_storage.rescore_required() {
+            // Approximate scores are only used to select candidates, final order is given by original vectors
+            let num_candidates = max(ef, vector_storage.rescore_limit(top));
+            let candidates = self
+                .graph
+                .search(num_candidates, num_candidates, &points_scorer);
+            let mut candidate_ids = candidates.iter().map(|candidate| candidate.idx);
+            return vector_storage.score_points(vector, &mut candidate_ids, top);
+        }
+
         self.graph.search(top, ef, &points_scorer)
     }
 }
@@ -135,10 +196,54 @@ impl VectorIndex for HNSWIndex {
     fn search(
         &self,
         vector: &[VectorElementType],
+        filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
     ) -> Vec<ScoredPointOffset> {
-        self.search_with_graph(vector, top, params)
+        match filter {
+            None => self.search_with_graph(vector, None, top, params),
+            Some(query_filter) => {
+                // depending on the amount of filtered-out points the optimal strategy could be
+                // - to retrieve possible points and score them after
+                // - to use HNSW index with filtering condition
+                let payload_index = self.payload_index.borrow();
+                let vector_storage = self.vector_storage.borrow();
+
+                let plain_search = || -> Vec<ScoredPointOffset> {
+                    let mut filtered_points = payload_index.query_points(query_filter);
+                    vector_storage.score_points(vector, &mut filtered_points, top)
+                };
+
+                let search_with_graph =
+                    || -> Vec<ScoredPointOffset> { self.search_with_graph(vector, filter, top, params) };
+
+                let query_cardinality = payload_index.estimate_cardinality(query_filter);
+
+                if query_cardinality.max < self.config.indexing_threshold {
+                    // if cardinality is small - use plain index
+                    return plain_search();
+                }
+
+                if query_cardinality.min > self.config.indexing_threshold {
+                    // if cardinality is high enough - use HNSW index
+                    return search_with_graph();
+                }
+
+                // Fast cardinality estimation is not enough, do sample estimation of cardinality
+                if sample_check_cardinality(
+                    vector_storage.sample_ids(),
+                    |idx| self.condition_checker.check(idx, query_filter),
+                    self.config.indexing_threshold,
+                    vector_storage.vector_count(),
+                ) {
+                    // if cardinality is high enough - use HNSW index
+                    search_with_graph()
+                } else {
+                    // if cardinality is small - use plain index
+                    plain_search()
+                }
+            }
+        }
     }
 
     fn build_index(&mut self) -> OperationResult<()> {
@@ -149,33 +254,156 @@ impl VectorIndex for HNSWIndex {
         let total_points = vector_storage.total_vector_count();
 
         debug!("building hnsw for {}", total_points);
-        self.graph = GraphLayers::new(
-            total_points,
-            self.config.m,
-            self.config.m0,
-            self.config.ef_construct,
-            max(1, total_points / self.config.indexing_threshold * 10),
-            HNSW_USE_HEURISTIC,
-        );
-
-        for vector_id in vector_storage.iter_ids() {
-            let vector = vector_storage.get_vector(vector_id).unwrap();
-            let raw_scorer = vector_storage.raw_scorer(vector);
-            let points_scorer = FilteredScorer {
-                raw_scorer: raw_scorer.as_ref(),
+        let entry_points_num = max(1, total_points / self.config.indexing_threshold * 10);
+
+        if self.config.max_indexing_threads == 1 {
+            self.graph = GraphLayers::new(
+                total_points,
+                self.config.m,
+                self.config.m0,
+                self.config.ef_construct,
+                entry_points_num,
+                HNSW_USE_HEURISTIC,
+            );
+
+            for vector_id in vector_storage.iter_ids() {
+                let raw_scorer = vector_storage.raw_scorer_internal(vector_id);
+                let points_scorer = FilteredScorer {
+                    raw_scorer: raw_scorer.as_ref(),
+                    condition_checker: self.condition_checker.deref(),
+                    filter: None,
+                };
+
+                let level = self.graph.get_random_layer(&mut rng);
+                self.graph.link_new_point(vector_id, level, &points_scorer);
+            }
+        } else {
+            let mut graph_builder = GraphLayersBuilder::new(
+                total_points,
+                self.config.m,
+                self.config.m0,
+                self.config.ef_construct,
+                entry_points_num,
+                HNSW_USE_HEURISTIC,
+            );
+
+            let ids: Vec<_> = vector_storage.iter_ids().collect();
+            for vector_id in ids.iter().cloned() {
+                let level = graph_builder.get_random_layer(&mut rng);
+                graph_builder.set_levels(vector_id, level);
+            }
+
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(self.config.max_indexing_threads)
+                .build()
+                .map_err(|err| OperationError::ServiceError {
+                    description: format!("Failed to create indexing thread pool: {}", err),
+                })?;
+
+            let storage = vector_storage.deref();
+            let condition_checker = self.condition_checker.deref();
+            let graph_builder_ref = &graph_builder;
+            let link_point = |vector_id: PointOffsetType| {
+                let raw_scorer = storage.raw_scorer_internal(vector_id);
+                let points_scorer = FilteredScorer {
+                    raw_scorer: raw_scorer.as_ref(),
+                    condition_checker,
+                    filter: None,
+                };
+                graph_builder_ref.link_new_point(vector_id, &points_scorer);
             };
 
-            let level = self.graph.get_random_layer(&mut rng);
-            self.graph.link_new_point(vector_id, level, &points_scorer);
+            // First points are linked sequentially, so threads start with a connected graph
+            let sequential_points = min(ids.len(), SINGLE_THREADED_BUILD_POINTS);
+            ids[..sequential_points]
+                .iter()
+                .cloned()
+                .for_each(link_point);
+            pool.install(|| {
+                ids[sequential_points..]
+                    .par_iter()
+                    .cloned()
+                    .for_each(link_point)
+            });
+
+            self.graph = graph_builder.into_graph_layers();
         }
 
         debug!("finish main graph");
 
+        let payload_index = self.payload_index.borrow();
         let total_vectors_count = vector_storage.total_vector_count();
         let mut block_condition_checker = BuildConditionChecker::new(total_vectors_count);
 
+        for field in payload_index.indexed_fields() {
+            debug!("building additional index for field {}", &field);
+
+            for payload_block in payload_index.payload_blocks(&field, self.config.indexing_threshold)
+            {
+                if payload_block.cardinality >= vector_storage.vector_count() {
+                    // Block covers the whole segment, main graph is good enough
+                    continue;
+                }
+                let mut additional_graph = GraphLayers::new_with_params(
+                    total_vectors_count,
+                    self.config.m,
+                    self.config.m0,
+                    self.config.ef_construct,
+                    1,
+                    HNSW_USE_HEURISTIC,
+                    false,
+                );
+                self.build_filtered_graph(
+                    &mut additional_graph,
+                    payload_block.condition,
+                    &mut block_condition_checker,
+                );
+                self.graph.merge_from_other(additional_graph);
+            }
+        }
+
+        debug!("finish additional payload field indexing");
+
+        if self.config.compress_links {
+            self.graph.compress_links();
+        }
 
-        
         self.save()
     }
+
+    fn update_vector(&mut self, point_id: PointOffsetType) -> OperationResult<()> {
+        let vector_storage = self.vector_storage.borrow();
+        let raw_scorer = vector_storage.raw_scorer_internal(point_id);
+        let points_scorer = FilteredScorer {
+            raw_scorer: raw_scorer.as_ref(),
+            condition_checker: self.condition_checker.deref(),
+            filter: None,
+        };
+
+        // Changed point keeps its level, so entry points remain valid
+        let level = if self.graph.contains_point(point_id) {
+            self.graph.point_level(point_id)
+        } else {
+            self.graph.get_random_layer(&mut self.thread_rng)
+        };
+        self.graph.link_new_point(point_id, level, &points_scorer);
+        Ok(())
+    }
+
+    fn delete_vector(&mut self, point_id: PointOffsetType) -> OperationResult<()> {
+        let vector_storage = self.vector_storage.borrow();
+        let raw_scorer = vector_storage.raw_scorer_internal(point_id);
+        let points_scorer = FilteredScorer {
+            raw_scorer: raw_scorer.as_ref(),
+            condition_checker: self.condition_checker.deref(),
+            filter: None,
+        };
+
+        self.graph.delete_point(point_id, &points_scorer);
+        Ok(())
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        self.save_graph()
+    }
 }
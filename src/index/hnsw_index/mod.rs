@@ -1,8 +1,11 @@
 mod build_cache;
 pub mod build_condition_checker;
+pub mod compressed_links;
 mod config;
 mod entry_points;
 pub mod graph_layers;
+pub mod graph_layers_builder;
+pub mod graph_links;
 pub mod hnsw;
 pub mod point_scorer;
 mod search_context;
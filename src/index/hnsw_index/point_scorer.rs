@@ -1,13 +1,24 @@
-use crate::types::{PointOffsetType, ScoreType};
+use crate::payload_storage::ConditionChecker;
+use crate::types::{Filter, PointOffsetType, ScoreType};
 use crate::vector_storage::{RawScorer, ScoredPointOffset};
 
 pub struct FilteredScorer<'a> {
     pub raw_scorer: &'a dyn RawScorer,
+    pub condition_checker: &'a dyn ConditionChecker,
+    pub filter: Option<&'a Filter>,
 }
 
 impl FilteredScorer<'_> {
     pub fn check_point(&self, point_id: PointOffsetType) -> bool {
-        self.raw_scorer.check_point(point_id)
+        self.raw_scorer.check_point(point_id) && self.check_filter(point_id)
+    }
+
+    /// Check only payload conditions of the point, regardless of whether the vector is deleted
+    pub fn check_filter(&self, point_id: PointOffsetType) -> bool {
+        match self.filter {
+            None => true,
+            Some(filter) => self.condition_checker.check(point_id, filter),
+        }
     }
 
     pub fn score_iterable_points<F>(
@@ -18,9 +29,36 @@ impl FilteredScorer<'_> {
     ) where
         F: FnMut(ScoredPointOffset),
     {
-        self
-            .raw_scorer
-            .score_points(points_iterator)
+        match self.filter {
+            None => self
+                .raw_scorer
+                .score_points(points_iterator)
+                .take(limit)
+                .for_each(action),
+            Some(filter) => {
+                let mut points_filtered_iterator =
+                    points_iterator.filter(move |id| self.condition_checker.check(*id, filter));
+                self.raw_scorer
+                    .score_points(&mut points_filtered_iterator)
+                    .take(limit)
+                    .for_each(action)
+            }
+        }
+    }
+
+    /// Score all given points, including deleted ones if possible, ignoring the filter.
+    /// Required to traverse graph through deleted nodes: they have no payload,
+    /// so they would never pass any field condition. Other points should be filtered by the caller
+    pub fn score_iterable_points_with_deleted<F>(
+        &self,
+        points_iterator: &mut dyn Iterator<Item = PointOffsetType>,
+        limit: usize,
+        action: F,
+    ) where
+        F: FnMut(ScoredPointOffset),
+    {
+        self.raw_scorer
+            .score_points_with_deleted(points_iterator)
             .take(limit)
             .for_each(action)
     }
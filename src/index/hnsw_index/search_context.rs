@@ -38,4 +38,9 @@ impl SearchContext {
             self.candidates.push(score_point)
         }
     }
+
+    /// Deleted point is never returned as a result, but it is used to reach its neighbours
+    pub fn process_transit_candidate(&mut self, score_point: ScoredPointOffset) {
+        self.candidates.push(score_point)
+    }
 }
@@ -1,7 +1,11 @@
 use crate::entry::entry_point::OperationResult;
-use crate::types::{PayloadKeyType, PayloadKeyTypeRef, PointOffsetType, SearchParams, VectorElementType,
+use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
+use crate::types::{
+    FacetValue, Filter, PayloadKeyType, PayloadKeyTypeRef, PointOffsetType, SearchParams,
+    VectorElementType,
 };
 use crate::vector_storage::ScoredPointOffset;
+use std::collections::HashMap;
 
 /// Trait for vector searching
 pub trait VectorIndex {
@@ -9,10 +13,66 @@ pub trait VectorIndex {
     fn search(
         &self,
         vector: &[VectorElementType],
+        filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
     ) -> Vec<ScoredPointOffset>;
 
     /// Force internal index rebuild.
     fn build_index(&mut self) -> OperationResult<()>;
+
+    /// Add new or changed vector of the point into the index without full rebuild.
+    /// Should be called after the vector is written into the storage
+    fn update_vector(&mut self, point_id: PointOffsetType) -> OperationResult<()>;
+
+    /// Exclude the point from search results and repair index structure around it.
+    /// Should be called before the vector is deleted from the storage
+    fn delete_vector(&mut self, point_id: PointOffsetType) -> OperationResult<()>;
+
+    /// Persist index state on disk
+    fn flush(&self) -> OperationResult<()>;
+}
+
+/// Trait for payload searching
+pub trait PayloadIndex {
+    /// Get indexed fields
+    fn indexed_fields(&self) -> Vec<PayloadKeyType>;
+
+    /// Mark field as one which should be indexed
+    fn set_indexed(&mut self, field: &PayloadKeyType) -> OperationResult<()>;
+
+    /// Remove index
+    fn drop_index(&mut self, field: &PayloadKeyType) -> OperationResult<()>;
+
+    /// Re-read payload of the point and update indexed values.
+    /// Should be called after any payload change of the point
+    fn update_point(&mut self, point_id: PointOffsetType) -> OperationResult<()>;
+
+    /// Estimate amount of points (min, max) which satisfies filtering condition.
+    fn estimate_cardinality(&self, query: &Filter) -> CardinalityEstimation;
+
+    /// Return list of all point ids, which satisfy filtering criteria
+    fn query_points<'a>(
+        &'a self,
+        query: &'a Filter,
+    ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a>;
+
+    /// Count points with each value of the field, optionally only among points matching `filter`.
+    /// Returns `None` if there is no inverted index for the field
+    fn facet_counts(
+        &self,
+        field: PayloadKeyTypeRef,
+        filter: Option<&Filter>,
+    ) -> Option<HashMap<FacetValue, usize>>;
+
+    /// Iterate conditions for payload blocks with minimum size of `threshold`
+    /// Required for building HNSW index
+    fn payload_blocks(
+        &self,
+        field: &PayloadKeyType,
+        threshold: usize,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_>;
+
+    /// Persist index state on disk
+    fn flush(&self) -> OperationResult<()>;
 }
@@ -1,6 +1,11 @@
+pub mod field_index;
 pub mod hnsw_index;
 mod index_base;
-mod sample_estimation;
+pub mod payload_config;
+pub mod plain_payload_index;
+mod query_estimator;
+pub mod sample_estimation;
+pub mod struct_payload_index;
 mod visited_pool;
 pub mod plain_index;
 
@@ -1,38 +1,98 @@
+use std::cmp::max;
 use std::sync::Arc;
 
 use atomic_refcell::AtomicRefCell;
 
-use crate::{entry::entry_point::OperationResult, types::{SearchParams, VectorElementType}, vector_storage::{ScoredPointOffset, VectorStorage}};
-
-use super::VectorIndex;
-
+use crate::{
+    entry::entry_point::OperationResult,
+    spaces::tools::peek_top_scores_iterable,
+    types::{Filter, PointOffsetType, SearchParams, VectorElementType},
+    vector_storage::{ScoredPointOffset, VectorStorage},
+};
 
+use super::{PayloadIndex, VectorIndex};
 
 pub struct PlainIndex {
     vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+    payload_index: Arc<AtomicRefCell<dyn PayloadIndex>>,
 }
 
 impl PlainIndex {
     pub fn new(
         vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+        payload_index: Arc<AtomicRefCell<dyn PayloadIndex>>,
     ) -> PlainIndex {
         PlainIndex {
             vector_storage,
+            payload_index,
         }
     }
+
+    /// Score compressed vectors and, if required, rescore top candidates with original vectors
+    fn search_quantized(
+        &self,
+        vector: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        params: Option<&SearchParams>,
+    ) -> Vec<ScoredPointOffset> {
+        let vector_storage = self.vector_storage.borrow();
+        let payload_index = self.payload_index.borrow();
+        let mut point_ids = match filter {
+            Some(filter) => payload_index.query_points(filter),
+            None => vector_storage.iter_ids(),
+        };
+        let raw_scorer = vector_storage.raw_scorer(vector.to_owned());
+        let scores = raw_scorer.score_points(&mut point_ids);
+
+        if !vector_storage.rescore_required() {
+            return peek_top_scores_iterable(scores, top);
+        }
+        let num_candidates = max(
+            params.and_then(|params| params.hnsw_ef).unwrap_or(0),
+            vector_storage.rescore_limit(top),
+        );
+        let candidates = peek_top_scores_iterable(scores, num_candidates);
+        let mut candidate_ids = candidates.iter().map(|candidate| candidate.idx);
+        vector_storage.score_points(vector, &mut candidate_ids, top)
+    }
 }
 
 impl VectorIndex for PlainIndex {
     fn search(
         &self,
         vector: &[VectorElementType],
+        filter: Option<&Filter>,
         top: usize,
-        _params: Option<&SearchParams>,
+        params: Option<&SearchParams>,
     ) -> Vec<ScoredPointOffset> {
-        self.vector_storage.borrow().score_all(vector, top)
+        if self.vector_storage.borrow().is_quantized() {
+            return self.search_quantized(vector, filter, top, params);
+        }
+        let vector_storage = self.vector_storage.borrow();
+        match filter {
+            Some(filter) => {
+                let payload_index = self.payload_index.borrow();
+                let mut filtered_ids = payload_index.query_points(filter);
+                vector_storage.score_points(vector, &mut filtered_ids, top)
+            }
+            None => vector_storage.score_all(vector, top),
+        }
     }
 
     fn build_index(&mut self) -> OperationResult<()> {
         Ok(())
     }
+
+    fn update_vector(&mut self, _point_id: PointOffsetType) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn delete_vector(&mut self, _point_id: PointOffsetType) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        Ok(())
+    }
 }
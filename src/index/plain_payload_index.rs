@@ -0,0 +1,123 @@
+use crate::entry::entry_point::OperationResult;
+use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
+use crate::index::payload_config::PayloadConfig;
+use crate::index::PayloadIndex;
+use crate::payload_storage::ConditionChecker;
+use crate::types::{FacetValue, Filter, PayloadKeyType, PayloadKeyTypeRef, PointOffsetType};
+use crate::vector_storage::VectorStorage;
+use atomic_refcell::AtomicRefCell;
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Implementation of `PayloadIndex` which does not really indexes anything.
+///
+/// Used for small segments, which are easier to keep simple for faster updates,
+/// rather than spend time for index re-building
+pub struct PlainPayloadIndex {
+    condition_checker: Arc<dyn ConditionChecker>,
+    vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+    config: PayloadConfig,
+    path: PathBuf,
+}
+
+impl PlainPayloadIndex {
+    fn config_path(&self) -> PathBuf {
+        PayloadConfig::get_config_path(&self.path)
+    }
+
+    fn save_config(&self) -> OperationResult<()> {
+        let config_path = self.config_path();
+        self.config.save(&config_path)
+    }
+
+    pub fn open(
+        condition_checker: Arc<dyn ConditionChecker>,
+        vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+        path: &Path,
+    ) -> OperationResult<Self> {
+        create_dir_all(path)?;
+        let config_path = PayloadConfig::get_config_path(path);
+        let config = if config_path.exists() {
+            PayloadConfig::load(&config_path)?
+        } else {
+            PayloadConfig::default()
+        };
+
+        let index = PlainPayloadIndex {
+            condition_checker,
+            vector_storage,
+            config,
+            path: path.to_owned(),
+        };
+
+        if !index.config_path().exists() {
+            index.save_config()?;
+        }
+
+        Ok(index)
+    }
+}
+
+impl PayloadIndex for PlainPayloadIndex {
+    fn indexed_fields(&self) -> Vec<PayloadKeyType> {
+        self.config.indexed_fields.clone()
+    }
+
+    fn set_indexed(&mut self, field: &PayloadKeyType) -> OperationResult<()> {
+        if !self.config.indexed_fields.contains(field) {
+            self.config.indexed_fields.push(field.clone());
+            return self.save_config();
+        }
+        Ok(())
+    }
+
+    fn drop_index(&mut self, field: &PayloadKeyType) -> OperationResult<()> {
+        self.config.indexed_fields.retain(|x| x != field);
+        self.save_config()
+    }
+
+    fn update_point(&mut self, _point_id: PointOffsetType) -> OperationResult<()> {
+        Ok(())
+    }
+
+    fn estimate_cardinality(&self, _query: &Filter) -> CardinalityEstimation {
+        let total_points = self.vector_storage.borrow().vector_count();
+        CardinalityEstimation::unknown(total_points)
+    }
+
+    fn query_points<'a>(
+        &'a self,
+        query: &'a Filter,
+    ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a> {
+        let vector_storage_ref = self.vector_storage.borrow();
+        let matched_points: Vec<_> = vector_storage_ref
+            .iter_ids()
+            .filter(|point_id| self.condition_checker.check(*point_id, query))
+            .collect();
+        Box::new(matched_points.into_iter())
+    }
+
+    fn facet_counts(
+        &self,
+        _field: PayloadKeyTypeRef,
+        _filter: Option<&Filter>,
+    ) -> Option<HashMap<FacetValue, usize>> {
+        // No inverted index for un-indexed payload
+        None
+    }
+
+    fn payload_blocks(
+        &self,
+        _field: &PayloadKeyType,
+        _threshold: usize,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        // No blocks for un-indexed payload
+        Box::new(vec![].into_iter())
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        Ok(())
+    }
+}
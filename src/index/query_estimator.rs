@@ -0,0 +1,305 @@
+use crate::index::field_index::{CardinalityEstimation, PrimaryCondition};
+use crate::types::{Condition, Filter};
+use itertools::Itertools;
+use std::cmp::{max, min};
+
+/// Combine estimations of conditions, at least one of which should be satisfied
+pub fn combine_should_estimations(
+    estimations: &[CardinalityEstimation],
+    total: usize,
+) -> CardinalityEstimation {
+    let mut clauses: Vec<PrimaryCondition> = vec![];
+    for estimation in estimations {
+        if estimation.primary_clauses.is_empty() {
+            // If some branch is un-indexed - we can't make
+            // any assumptions about the whole `should` clause
+            clauses = vec![];
+            break;
+        }
+        clauses.append(&mut estimation.primary_clauses.clone());
+    }
+    let element_not_hit_prob: f64 = estimations
+        .iter()
+        .map(|x| total.saturating_sub(x.exp) as f64 / (total as f64))
+        .product();
+    let element_hit_prob = 1.0 - element_not_hit_prob;
+    let expected_count = (element_hit_prob * (total as f64)).round() as usize;
+    CardinalityEstimation {
+        primary_clauses: clauses,
+        min: estimations.iter().map(|x| x.min).max().unwrap_or(0),
+        exp: expected_count,
+        max: min(estimations.iter().map(|x| x.max).sum(), total),
+    }
+}
+
+/// Combine estimations of conditions, all of which should be satisfied
+pub fn combine_must_estimations(
+    estimations: &[CardinalityEstimation],
+    total: usize,
+) -> CardinalityEstimation {
+    let min_estimation = estimations
+        .iter()
+        .map(|x| x.min)
+        .fold(total as i64, |acc, x| {
+            max(0, acc + (x as i64) - (total as i64))
+        }) as usize;
+
+    let max_estimation = estimations.iter().map(|x| x.max).min().unwrap_or(total);
+
+    let exp_estimation_prob: f64 = estimations
+        .iter()
+        .map(|x| (x.exp as f64) / (total as f64))
+        .product();
+
+    let exp_estimation = (exp_estimation_prob * (total as f64)).round() as usize;
+
+    let clauses = estimations
+        .iter()
+        .filter(|x| !x.primary_clauses.is_empty())
+        .min_by_key(|x| x.exp)
+        .map(|x| x.primary_clauses.clone())
+        .unwrap_or_default();
+
+    CardinalityEstimation {
+        primary_clauses: clauses,
+        min: min_estimation,
+        exp: exp_estimation,
+        max: max_estimation,
+    }
+}
+
+fn invert_estimation(estimation: &CardinalityEstimation, total: usize) -> CardinalityEstimation {
+    CardinalityEstimation {
+        primary_clauses: vec![],
+        min: total.saturating_sub(estimation.max),
+        exp: total.saturating_sub(estimation.exp),
+        max: total.saturating_sub(estimation.min),
+    }
+}
+
+fn estimate_condition<F>(
+    estimator: &F,
+    condition: &Condition,
+    total: usize,
+) -> CardinalityEstimation
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    match condition {
+        Condition::Filter(filter) => estimate_filter(estimator, filter, total),
+        _ => estimator(condition),
+    }
+}
+
+fn estimate_should<F>(
+    estimator: &F,
+    conditions: &[Condition],
+    total: usize,
+) -> CardinalityEstimation
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    let estimations = conditions
+        .iter()
+        .map(|condition| estimate_condition(estimator, condition, total))
+        .collect_vec();
+    combine_should_estimations(&estimations, total)
+}
+
+fn estimate_must<F>(estimator: &F, conditions: &[Condition], total: usize) -> CardinalityEstimation
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    let estimations = conditions
+        .iter()
+        .map(|condition| estimate_condition(estimator, condition, total))
+        .collect_vec();
+    combine_must_estimations(&estimations, total)
+}
+
+fn estimate_must_not<F>(
+    estimator: &F,
+    conditions: &[Condition],
+    total: usize,
+) -> CardinalityEstimation
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    let estimations = conditions
+        .iter()
+        .map(|condition| invert_estimation(&estimate_condition(estimator, condition, total), total))
+        .collect_vec();
+    combine_must_estimations(&estimations, total)
+}
+
+/// Estimate amount of points, which satisfy the filter,
+/// using estimations of each separate condition
+pub fn estimate_filter<F>(estimator: &F, filter: &Filter, total: usize) -> CardinalityEstimation
+where
+    F: Fn(&Condition) -> CardinalityEstimation,
+{
+    if total == 0 {
+        return CardinalityEstimation {
+            primary_clauses: vec![],
+            min: 0,
+            exp: 0,
+            max: 0,
+        };
+    }
+
+    let mut filter_estimations: Vec<CardinalityEstimation> = vec![];
+
+    if let Some(conditions) = &filter.must {
+        if !conditions.is_empty() {
+            filter_estimations.push(estimate_must(estimator, conditions, total));
+        }
+    }
+    if let Some(conditions) = &filter.should {
+        if !conditions.is_empty() {
+            filter_estimations.push(estimate_should(estimator, conditions, total));
+        }
+    }
+    if let Some(conditions) = &filter.must_not {
+        if !conditions.is_empty() {
+            filter_estimations.push(estimate_must_not(estimator, conditions, total));
+        }
+    }
+
+    combine_must_estimations(&filter_estimations, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FieldCondition, HasIdCondition, PointIdType, PointOffsetType};
+    use std::collections::HashSet;
+
+    const TOTAL: usize = 1000;
+
+    fn test_condition(key: &str) -> Condition {
+        Condition::Field(FieldCondition {
+            key: key.to_string(),
+            r#match: None,
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        })
+    }
+
+    fn test_estimator(condition: &Condition) -> CardinalityEstimation {
+        match condition {
+            Condition::Filter(_) => panic!("unexpected Filter"),
+            Condition::Field(field) => match field.key.as_str() {
+                "color" => CardinalityEstimation {
+                    primary_clauses: vec![PrimaryCondition::Condition(field.clone())],
+                    min: 100,
+                    exp: 200,
+                    max: 300,
+                },
+                "size" => CardinalityEstimation {
+                    primary_clauses: vec![PrimaryCondition::Condition(field.clone())],
+                    min: 100,
+                    exp: 100,
+                    max: 100,
+                },
+                "price" => CardinalityEstimation {
+                    primary_clauses: vec![PrimaryCondition::Condition(field.clone())],
+                    min: 10,
+                    exp: 15,
+                    max: 20,
+                },
+                _ => CardinalityEstimation::unknown(TOTAL),
+            },
+            Condition::HasId(has_id) => CardinalityEstimation {
+                primary_clauses: vec![PrimaryCondition::Ids(
+                    (0..has_id.has_id.len() as PointOffsetType).collect(),
+                )],
+                min: has_id.has_id.len(),
+                exp: has_id.has_id.len(),
+                max: has_id.has_id.len(),
+            },
+            Condition::IsEmpty(_) | Condition::IsNull(_) => CardinalityEstimation::unknown(TOTAL),
+        }
+    }
+
+    #[test]
+    fn simple_query_estimation_test() {
+        let query = Filter::new_must(test_condition("color"));
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert_eq!(estimation.exp, 200);
+        assert!(!estimation.primary_clauses.is_empty());
+    }
+
+    #[test]
+    fn must_estimation_query_test() {
+        let query = Filter {
+            should: None,
+            must: Some(vec![
+                test_condition("color"),
+                test_condition("size"),
+                test_condition("un-indexed"),
+            ]),
+            must_not: None,
+        };
+
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert_eq!(estimation.primary_clauses.len(), 1);
+        match &estimation.primary_clauses[0] {
+            PrimaryCondition::Condition(field) => assert_eq!(&field.key, "size"),
+            _ => panic!(),
+        }
+        assert!(estimation.max <= TOTAL);
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.min <= estimation.exp);
+    }
+
+    #[test]
+    fn should_estimation_query_test() {
+        let query = Filter {
+            should: Some(vec![test_condition("color"), test_condition("size")]),
+            must: None,
+            must_not: None,
+        };
+
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert_eq!(estimation.primary_clauses.len(), 2);
+        assert!(estimation.max <= TOTAL);
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.min <= estimation.exp);
+
+        let query = Filter {
+            should: Some(vec![test_condition("color"), test_condition("un-indexed")]),
+            must: None,
+            must_not: None,
+        };
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert!(estimation.primary_clauses.is_empty());
+    }
+
+    #[test]
+    fn another_complex_query_estimation_test() {
+        let query = Filter {
+            should: None,
+            must: Some(vec![
+                Condition::Filter(Filter {
+                    should: None,
+                    must: Some(vec![test_condition("color"), test_condition("un-indexed")]),
+                    must_not: None,
+                }),
+                Condition::HasId(HasIdCondition::from(
+                    (1..=5u64).map(PointIdType::from).collect::<HashSet<_>>(),
+                )),
+            ]),
+            must_not: Some(vec![test_condition("price")]),
+        };
+        let estimation = estimate_filter(&test_estimator, &query, TOTAL);
+        assert_eq!(estimation.primary_clauses.len(), 1);
+        match &estimation.primary_clauses[0] {
+            PrimaryCondition::Ids(ids) => assert_eq!(ids.len(), 5),
+            _ => panic!(),
+        }
+        assert!(estimation.max <= TOTAL);
+        assert!(estimation.exp <= estimation.max);
+        assert!(estimation.min <= estimation.exp);
+    }
+}
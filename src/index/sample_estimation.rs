@@ -62,11 +62,41 @@ pub fn sample_check_cardinality(
     exp > threshold as i64
 }
 
+/// Estimates amount of points satisfying the `checker` by checking a random sample of points
+pub fn sample_estimate_cardinality(
+    sample_points: impl Iterator<Item = PointOffsetType>,
+    checker: impl Fn(PointOffsetType) -> bool,
+    total_points: usize,
+) -> usize {
+    let mut matched_points = 0;
+    let mut total_checked = 0;
+    for idx in sample_points.take(MAX_ESTIMATED_POINTS) {
+        matched_points += checker(idx) as usize;
+        total_checked += 1;
+    }
+    if total_checked == 0 {
+        return 0;
+    }
+    matched_points * total_points / total_checked
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rand::Rng;
 
+    #[test]
+    fn test_sample_estimate_cardinality() {
+        let total = 100_000;
+        let mut rng = rand::thread_rng();
+        let sample = (0..).map(|_| rng.gen_range(0..total));
+
+        let estimation = sample_estimate_cardinality(sample, |idx| idx % 4 == 0, total as usize);
+        assert!((20_000..30_000).contains(&estimation));
+
+        assert_eq!(sample_estimate_cardinality(0..0, |_| true, 0), 0);
+    }
+
     #[test]
     fn test_confidence_interval() {
         let total = 100_000;
@@ -0,0 +1,406 @@
+use crate::common::file_operations::{atomic_save_bin, read_bin};
+use crate::entry::entry_point::OperationResult;
+use crate::id_tracker::IdTracker;
+use crate::index::field_index::index_selector::index_selector;
+use crate::index::field_index::map_index::MapIndex;
+use crate::index::field_index::{
+    CardinalityEstimation, FieldIndex, PayloadBlockCondition, PayloadFieldIndex, PrimaryCondition,
+};
+use crate::index::payload_config::PayloadConfig;
+use crate::index::query_estimator::estimate_filter;
+use crate::index::PayloadIndex;
+use crate::payload_storage::{ConditionChecker, PayloadStorage};
+use crate::types::{
+    Condition, FacetValue, FieldCondition, Filter, PayloadKeyType, PayloadKeyTypeRef,
+    PointOffsetType,
+};
+use crate::vector_storage::VectorStorage;
+use atomic_refcell::AtomicRefCell;
+use itertools::Itertools;
+use log::{debug, warn};
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, remove_file};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub const PAYLOAD_FIELD_INDEX_PATH: &str = "fields";
+
+type IndexesMap = HashMap<PayloadKeyType, Vec<FieldIndex>>;
+
+/// `PayloadIndex` implementation, which actually uses index structures for providing faster search
+pub struct StructPayloadIndex {
+    condition_checker: Arc<dyn ConditionChecker>,
+    vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+    payload: Arc<AtomicRefCell<dyn PayloadStorage>>,
+    id_tracker: Arc<AtomicRefCell<dyn IdTracker>>,
+    field_indexes: IndexesMap,
+    config: PayloadConfig,
+    path: PathBuf,
+}
+
+impl StructPayloadIndex {
+    pub fn estimate_field_condition(
+        &self,
+        condition: &FieldCondition,
+    ) -> Option<CardinalityEstimation> {
+        self.field_indexes.get(&condition.key).and_then(|indexes| {
+            indexes
+                .iter()
+                .find_map(|index| index.estimate_cardinality(condition))
+        })
+    }
+
+    fn query_field(
+        &self,
+        field_condition: &FieldCondition,
+    ) -> Option<Box<dyn Iterator<Item = PointOffsetType> + '_>> {
+        self.field_indexes
+            .get(&field_condition.key)
+            .and_then(|indexes| {
+                indexes
+                    .iter()
+                    .find_map(|index| index.filter(field_condition))
+            })
+    }
+
+    fn config_path(&self) -> PathBuf {
+        PayloadConfig::get_config_path(&self.path)
+    }
+
+    fn save_config(&self) -> OperationResult<()> {
+        let config_path = self.config_path();
+        self.config.save(&config_path)
+    }
+
+    fn get_field_index_dir(path: &Path) -> PathBuf {
+        path.join(PAYLOAD_FIELD_INDEX_PATH)
+    }
+
+    /// Field names may contain any characters, so they are escaped to stay within the index directory:
+    /// bytes other than ASCII letters, digits, `_` and `-` are written as `%XX`
+    fn get_field_index_path(path: &Path, field: &PayloadKeyType) -> PathBuf {
+        let file_name: String = field
+            .bytes()
+            .map(|byte| match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' => (byte as char).to_string(),
+                _ => format!("%{:02X}", byte),
+            })
+            .collect();
+        Self::get_field_index_dir(path).join(format!("{}.idx", file_name))
+    }
+
+    fn save_field_index(&self, field: &PayloadKeyType) -> OperationResult<()> {
+        let field_index_dir = Self::get_field_index_dir(&self.path);
+        let field_index_path = Self::get_field_index_path(&self.path, field);
+        create_dir_all(field_index_dir)?;
+
+        match self.field_indexes.get(field) {
+            None => {}
+            Some(indexes) => atomic_save_bin(&field_index_path, indexes)?,
+        }
+        Ok(())
+    }
+
+    fn load_or_build_field_index(
+        &self,
+        field: &PayloadKeyType,
+    ) -> OperationResult<Vec<FieldIndex>> {
+        let field_index_path = Self::get_field_index_path(&self.path, field);
+        if field_index_path.exists() {
+            debug!(
+                "Loading field `{}` index from {}",
+                field,
+                field_index_path.to_str().unwrap()
+            );
+            match read_bin(&field_index_path) {
+                Ok(res) => return Ok(res),
+                // Index could be saved in a format of the previous version
+                Err(err) => warn!(
+                    "Failed to load field `{}` index from {}: {}, rebuilding",
+                    field,
+                    field_index_path.to_str().unwrap(),
+                    err
+                ),
+            }
+        } else {
+            debug!(
+                "Index for field `{}` not found in {}, building now",
+                field,
+                field_index_path.to_str().unwrap()
+            );
+        }
+        let res = self.build_field_index(field)?;
+        self.save_field_index(field)?;
+        Ok(res)
+    }
+
+    fn load_all_fields(&mut self) -> OperationResult<()> {
+        let mut field_indexes: IndexesMap = Default::default();
+        for field in self.config.indexed_fields.iter() {
+            let field_index = self.load_or_build_field_index(field)?;
+            field_indexes.insert(field.clone(), field_index);
+        }
+        self.field_indexes = field_indexes;
+        Ok(())
+    }
+
+    pub fn open(
+        condition_checker: Arc<dyn ConditionChecker>,
+        vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+        payload: Arc<AtomicRefCell<dyn PayloadStorage>>,
+        id_tracker: Arc<AtomicRefCell<dyn IdTracker>>,
+        path: &Path,
+    ) -> OperationResult<Self> {
+        create_dir_all(path)?;
+        let config_path = PayloadConfig::get_config_path(path);
+        let config = if config_path.exists() {
+            PayloadConfig::load(&config_path)?
+        } else {
+            PayloadConfig::default()
+        };
+
+        let mut index = StructPayloadIndex {
+            condition_checker,
+            vector_storage,
+            payload,
+            id_tracker,
+            field_indexes: Default::default(),
+            config,
+            path: path.to_owned(),
+        };
+
+        if !index.config_path().exists() {
+            // Save default config
+            index.save_config()?
+        }
+
+        index.load_all_fields()?;
+
+        Ok(index)
+    }
+
+    /// Build index structures for the field using all currently stored payloads.
+    /// Type of the index is selected according to the payload schema
+    pub fn build_field_index(&self, field: &PayloadKeyType) -> OperationResult<Vec<FieldIndex>> {
+        let payload_ref = self.payload.borrow();
+        let schema = payload_ref.schema();
+
+        let field_type = match schema.get(field) {
+            None => return Ok(vec![]),
+            Some(field_type) => field_type,
+        };
+
+        let mut field_indexes = index_selector(field_type);
+        for point_id in payload_ref.iter_ids() {
+            let point_payload = payload_ref.payload(point_id);
+            if let Some(field_value) = point_payload.get(field) {
+                for index in field_indexes.iter_mut() {
+                    index.add_point_on_build(point_id, field_value)
+                }
+            }
+        }
+        for index in field_indexes.iter_mut() {
+            index.finish_build()
+        }
+        Ok(field_indexes)
+    }
+
+    fn build_and_save(&mut self, field: &PayloadKeyType) -> OperationResult<()> {
+        let field_indexes = self.build_field_index(field)?;
+        self.field_indexes.insert(field.clone(), field_indexes);
+        self.save_field_index(field)
+    }
+
+    fn map_index_facet<N, F>(
+        &self,
+        index: &MapIndex<N>,
+        filter: Option<&Filter>,
+        to_facet_value: F,
+    ) -> HashMap<FacetValue, usize>
+    where
+        N: Hash + Eq + Clone,
+        F: Fn(&N) -> FacetValue,
+    {
+        match filter {
+            None => index
+                .values_cardinality()
+                .map(|(value, count)| (to_facet_value(value), count))
+                .collect(),
+            Some(filter) => {
+                let mut counts: HashMap<FacetValue, usize> = HashMap::new();
+                for point_id in self.query_points(filter) {
+                    for value in index.get_values(point_id).into_iter().flatten().unique() {
+                        *counts.entry(to_facet_value(value)).or_insert(0) += 1;
+                    }
+                }
+                counts
+            }
+        }
+    }
+
+    pub fn total_points(&self) -> usize {
+        self.vector_storage.borrow().vector_count()
+    }
+}
+
+impl PayloadIndex for StructPayloadIndex {
+    fn indexed_fields(&self) -> Vec<PayloadKeyType> {
+        self.config.indexed_fields.clone()
+    }
+
+    fn set_indexed(&mut self, field: &PayloadKeyType) -> OperationResult<()> {
+        if !self.config.indexed_fields.contains(field) {
+            self.config.indexed_fields.push(field.clone());
+            self.save_config()?;
+        }
+        self.build_and_save(field)
+    }
+
+    fn drop_index(&mut self, field: &PayloadKeyType) -> OperationResult<()> {
+        self.config.indexed_fields.retain(|x| x != field);
+        self.save_config()?;
+        self.field_indexes.remove(field);
+
+        let field_index_path = Self::get_field_index_path(&self.path, field);
+
+        if field_index_path.exists() {
+            remove_file(&field_index_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn update_point(&mut self, point_id: PointOffsetType) -> OperationResult<()> {
+        if self.field_indexes.is_empty() {
+            return Ok(());
+        }
+        let payload = self.payload.borrow().payload(point_id);
+        for (field, indexes) in self.field_indexes.iter_mut() {
+            for index in indexes.iter_mut() {
+                index.remove_point(point_id);
+            }
+            if let Some(value) = payload.get(field) {
+                if indexes.is_empty() {
+                    // First value of the field, index type is defined by the schema
+                    if let Some(field_type) = self.payload.borrow().schema().get(field) {
+                        *indexes = index_selector(field_type);
+                    }
+                }
+                for index in indexes.iter_mut() {
+                    index.add_point(point_id, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn estimate_cardinality(&self, query: &Filter) -> CardinalityEstimation {
+        let total_points = self.total_points();
+
+        let estimator = |condition: &Condition| match condition {
+            Condition::Filter(_) => panic!("Unexpected branching"),
+            Condition::HasId(has_id) => {
+                let id_tracker_ref = self.id_tracker.borrow();
+                let mapped_ids: HashSet<PointOffsetType> = has_id
+                    .has_id
+                    .iter()
+                    .filter_map(|external_id| id_tracker_ref.internal_id(external_id))
+                    .collect();
+                let num_ids = mapped_ids.len();
+                CardinalityEstimation {
+                    primary_clauses: vec![PrimaryCondition::Ids(mapped_ids)],
+                    min: num_ids,
+                    exp: num_ids,
+                    max: num_ids,
+                }
+            }
+            Condition::Field(field_condition) => self
+                .estimate_field_condition(field_condition)
+                .unwrap_or_else(|| CardinalityEstimation::unknown(total_points)),
+            Condition::IsEmpty(_) | Condition::IsNull(_) => {
+                CardinalityEstimation::unknown(total_points)
+            }
+        };
+
+        estimate_filter(&estimator, query, total_points)
+    }
+
+    fn query_points<'a>(
+        &'a self,
+        query: &'a Filter,
+    ) -> Box<dyn Iterator<Item = PointOffsetType> + 'a> {
+        let query_cardinality = self.estimate_cardinality(query);
+        let vector_storage_ref = self.vector_storage.borrow();
+
+        let matched_points: Vec<_> = if query_cardinality.primary_clauses.is_empty() {
+            // Nothing to pre-select with, check every point
+            vector_storage_ref
+                .iter_ids()
+                .filter(|point_id| self.condition_checker.check(*point_id, query))
+                .collect()
+        } else {
+            let mut preselected: Vec<PointOffsetType> = query_cardinality
+                .primary_clauses
+                .iter()
+                .flat_map(|clause| match clause {
+                    PrimaryCondition::Condition(field_condition) => self
+                        .query_field(field_condition)
+                        .unwrap_or_else(|| vector_storage_ref.iter_ids()),
+                    PrimaryCondition::Ids(ids) => Box::new(ids.iter().cloned()),
+                })
+                .collect();
+            preselected.sort_unstable();
+            preselected.dedup();
+            preselected
+                .into_iter()
+                .filter(|point_id| !vector_storage_ref.is_deleted(*point_id))
+                .filter(|point_id| self.condition_checker.check(*point_id, query))
+                .collect()
+        };
+
+        Box::new(matched_points.into_iter())
+    }
+
+    fn facet_counts(
+        &self,
+        field: PayloadKeyTypeRef,
+        filter: Option<&Filter>,
+    ) -> Option<HashMap<FacetValue, usize>> {
+        self.field_indexes
+            .get(field)?
+            .iter()
+            .find_map(|index| match index {
+                FieldIndex::KeywordIndex(index) => {
+                    Some(self.map_index_facet(index, filter, |x| FacetValue::Keyword(x.clone())))
+                }
+                FieldIndex::IntMapIndex(index) => {
+                    Some(self.map_index_facet(index, filter, |x| FacetValue::Integer(*x)))
+                }
+                _ => None,
+            })
+    }
+
+    fn payload_blocks(
+        &self,
+        field: &PayloadKeyType,
+        threshold: usize,
+    ) -> Box<dyn Iterator<Item = PayloadBlockCondition> + '_> {
+        match self.field_indexes.get(field) {
+            None => Box::new(vec![].into_iter()),
+            Some(indexes) => {
+                let field_clone = field.clone();
+                Box::new(indexes.iter().flat_map(move |field_index| {
+                    field_index.payload_blocks(threshold, field_clone.clone())
+                }))
+            }
+        }
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        for field in self.field_indexes.keys() {
+            self.save_field_index(field)?;
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ pub mod entry;
 pub mod fixtures;
 mod id_tracker;
 pub mod index;
+pub mod payload_storage;
 pub mod segment;
 pub mod segment_constructor;
 pub mod spaces;
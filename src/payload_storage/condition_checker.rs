@@ -0,0 +1,210 @@
+//! Contains functions for interpreting filter queries and defining if given points pass the conditions
+
+use crate::index::field_index::full_text_index::check_text_match;
+use crate::types::{
+    FloatPayloadType, GeoBoundingBox, GeoPoint, GeoRadius, Match, PayloadType, Range,
+};
+use geo::algorithm::haversine_distance::HaversineDistance;
+use geo::Point;
+
+pub trait ValueChecker {
+    /// Check if any of stored values satisfies the condition
+    fn check(&self, payload: &PayloadType) -> bool;
+}
+
+impl ValueChecker for Match {
+    fn check(&self, payload: &PayloadType) -> bool {
+        match payload {
+            PayloadType::Keyword(payload_kws) => payload_kws.iter().any(|kw| {
+                self.keyword.as_ref().map(|x| x == kw).unwrap_or(false)
+                    || self
+                        .text
+                        .as_ref()
+                        .map(|x| check_text_match(x, kw))
+                        .unwrap_or(false)
+            }),
+            PayloadType::Integer(payload_ints) => payload_ints
+                .iter()
+                .cloned()
+                .any(|i| self.integer.map(|x| x == i).unwrap_or(false)),
+            PayloadType::Bool(payload_bools) => payload_bools
+                .iter()
+                .cloned()
+                .any(|b| self.boolean.map(|x| x == b).unwrap_or(false)),
+            _ => false,
+        }
+    }
+}
+
+impl Range {
+    pub fn check_range(&self, number: FloatPayloadType) -> bool {
+        self.lt.map_or(true, |x| number < x)
+            && self.gt.map_or(true, |x| number > x)
+            && self.lte.map_or(true, |x| number <= x)
+            && self.gte.map_or(true, |x| number >= x)
+    }
+}
+
+impl ValueChecker for Range {
+    fn check(&self, payload: &PayloadType) -> bool {
+        match payload {
+            PayloadType::Float(num) => num.iter().cloned().any(|x| self.check_range(x)),
+            PayloadType::Integer(num) => num
+                .iter()
+                .cloned()
+                .any(|x| self.check_range(x as FloatPayloadType)),
+            PayloadType::Datetime(timestamps) => timestamps
+                .iter()
+                .cloned()
+                .any(|x| self.check_range(x as FloatPayloadType)),
+            _ => false,
+        }
+    }
+}
+
+impl GeoBoundingBox {
+    pub fn check_point(&self, point: &GeoPoint) -> bool {
+        (self.top_left.lon < point.lon)
+            && (point.lon < self.bottom_right.lon)
+            && (self.bottom_right.lat < point.lat)
+            && (point.lat < self.top_left.lat)
+    }
+}
+
+impl ValueChecker for GeoBoundingBox {
+    fn check(&self, payload: &PayloadType) -> bool {
+        match payload {
+            PayloadType::Geo(geo_points) => geo_points.iter().any(|point| self.check_point(point)),
+            _ => false,
+        }
+    }
+}
+
+impl GeoRadius {
+    pub fn check_point(&self, point: &GeoPoint) -> bool {
+        let query_center = Point::new(self.center.lon, self.center.lat);
+        query_center.haversine_distance(&Point::new(point.lon, point.lat)) < self.radius
+    }
+}
+
+impl ValueChecker for GeoRadius {
+    fn check(&self, payload: &PayloadType) -> bool {
+        match payload {
+            PayloadType::Geo(geo_points) => geo_points.iter().any(|point| self.check_point(point)),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_matching() {
+        let berlin_and_moscow = PayloadType::Geo(vec![
+            GeoPoint {
+                lat: 52.52197645,
+                lon: 13.413637435864272,
+            },
+            GeoPoint {
+                lat: 55.7536283,
+                lon: 37.62137960067377,
+            },
+        ]);
+        let near_berlin_query = GeoRadius {
+            center: GeoPoint {
+                lat: 52.511,
+                lon: 13.423637,
+            },
+            radius: 2000.0,
+        };
+        let miss_geo_query = GeoRadius {
+            center: GeoPoint {
+                lat: 52.511,
+                lon: 20.423637,
+            },
+            radius: 2000.0,
+        };
+
+        assert!(near_berlin_query.check(&berlin_and_moscow));
+        assert!(!miss_geo_query.check(&berlin_and_moscow));
+
+        let around_moscow = GeoBoundingBox {
+            top_left: GeoPoint {
+                lat: 56.0,
+                lon: 37.0,
+            },
+            bottom_right: GeoPoint {
+                lat: 55.0,
+                lon: 38.0,
+            },
+        };
+        assert!(around_moscow.check(&berlin_and_moscow));
+        assert!(!around_moscow.check(&PayloadType::Integer(vec![1])));
+    }
+
+    #[test]
+    fn test_range_matching() {
+        let range = Range {
+            lt: None,
+            gt: Some(10.0),
+            gte: None,
+            lte: Some(20.0),
+        };
+
+        assert!(range.check(&PayloadType::Integer(vec![5, 20])));
+        assert!(range.check(&PayloadType::Float(vec![10.5])));
+        assert!(!range.check(&PayloadType::Float(vec![10.0, 20.1])));
+        assert!(!range.check(&PayloadType::Keyword(vec!["15".to_owned()])));
+    }
+
+    #[test]
+    fn test_text_matching() {
+        let paragraphs = PayloadType::Keyword(vec![
+            "Payment terms".to_owned(),
+            "The Invoice is attached, see below.".to_owned(),
+        ]);
+        let text_match = |text: &str| Match {
+            keyword: None,
+            integer: None,
+            text: Some(text.to_owned()),
+            boolean: None,
+        };
+
+        assert!(text_match("invoice").check(&paragraphs));
+        assert!(text_match("attached invoice").check(&paragraphs));
+        assert!(!text_match("invoice terms").check(&paragraphs));
+        assert!(!text_match("invoices").check(&paragraphs));
+        assert!(!text_match("invoice").check(&PayloadType::Integer(vec![1])));
+    }
+
+    #[test]
+    fn test_datetime_and_bool_matching() {
+        let range: Range = serde_json::from_str(
+            r#"{"gte": "2021-01-01T00:00:00Z", "lt": "2021-02-01T00:00:00+01:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(range.gte, Some(1_609_459_200_000.0));
+        assert_eq!(range.lt, Some(1_612_134_000_000.0));
+
+        // 2021-01-15T00:00:00Z and 2021-01-31T23:30:00Z
+        assert!(range.check(&PayloadType::Datetime(vec![1_610_668_800_000])));
+        assert!(!range.check(&PayloadType::Datetime(vec![1_612_135_800_000])));
+
+        // Fractions of a second are compared the same way for bounds and values
+        let range: Range = serde_json::from_str(r#"{"gt": "2021-01-01T00:00:00.500Z"}"#).unwrap();
+        assert!(!range.check(&PayloadType::Datetime(vec![1_609_459_200_250])));
+        assert!(range.check(&PayloadType::Datetime(vec![1_609_459_200_750])));
+
+        let published = Match {
+            keyword: None,
+            integer: None,
+            text: None,
+            boolean: Some(true),
+        };
+        assert!(published.check(&PayloadType::Bool(vec![false, true])));
+        assert!(!published.check(&PayloadType::Bool(vec![false])));
+        assert!(!published.check(&PayloadType::Integer(vec![1])));
+    }
+}
@@ -0,0 +1,239 @@
+//! Conversion of arbitrary JSON objects into a flat payload.
+//!
+//! Nested fields are stored under dotted key paths: `{"source": {"kind": "pdf"}}` is stored as
+//! `source.kind`. Fields of objects inside of arrays are marked with `[]`, so
+//! `{"tags": [{"name": "a"}, {"name": "b"}]}` is stored as `tags[].name` with both values.
+
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::types::{
+    FloatPayloadType, GeoPoint, PayloadKeyType, PayloadSchemaType, PayloadType, TheMap,
+};
+use serde_json::{Map, Value};
+
+/// Separator between keys of nested objects
+pub const NESTED_KEY_SEPARATOR: char = '.';
+/// Suffix of an array key, which contains nested objects
+pub const ARRAY_KEY_SUFFIX: &str = "[]";
+
+/// Check if `key` is a flattened `path` itself or any of its nested fields
+pub fn is_nested_key(path: &str, key: &str) -> bool {
+    match key.strip_prefix(path) {
+        None => false,
+        Some(rest) => {
+            rest.is_empty()
+                || rest.starts_with(NESTED_KEY_SEPARATOR)
+                || rest.starts_with(ARRAY_KEY_SUFFIX)
+        }
+    }
+}
+
+/// Convert JSON object into a payload with flattened keys.
+/// Whole numbers are stored as integers and fractional ones as floats, arrays with both as floats.
+/// Strings are stored as keywords, objects with only `lon` and `lat` fields as geo points,
+/// `null` as a field without values.
+/// Payload storage converts the values further according to the type of the field,
+/// e.g. integers of a float field or strings of a datetime field.
+pub fn payload_from_json(value: &Value) -> OperationResult<TheMap<PayloadKeyType, PayloadType>> {
+    let mut payload = Default::default();
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten_value(key, value, &mut payload)?;
+            }
+            Ok(payload)
+        }
+        _ => Err(OperationError::ValidationError {
+            description: "Payload should be a JSON object".to_string(),
+        }),
+    }
+}
+
+fn nested_path(path: &str, key: &str) -> String {
+    format!("{}{}{}", path, NESTED_KEY_SEPARATOR, key)
+}
+
+fn as_geo_point(object: &Map<String, Value>) -> Option<GeoPoint> {
+    if object.len() != 2 {
+        return None;
+    }
+    Some(GeoPoint {
+        lon: object.get("lon")?.as_f64()?,
+        lat: object.get("lat")?.as_f64()?,
+    })
+}
+
+fn flatten_value(
+    path: &str,
+    value: &Value,
+    payload: &mut TheMap<PayloadKeyType, PayloadType>,
+) -> OperationResult<()> {
+    match value {
+        Value::Null => append_value(path, PayloadType::Keyword(vec![]), payload),
+        Value::Bool(flag) => append_value(path, PayloadType::Bool(vec![*flag]), payload),
+        Value::String(keyword) => {
+            append_value(path, PayloadType::Keyword(vec![keyword.clone()]), payload)
+        }
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => append_value(path, PayloadType::Integer(vec![integer]), payload),
+            None if number.is_u64() => Err(OperationError::ValidationError {
+                description: format!("Number {} of field {} is out of range", number, path),
+            }),
+            None => {
+                let float = number.as_f64().unwrap_or_default();
+                append_value(path, PayloadType::Float(vec![float]), payload)
+            }
+        },
+        Value::Object(object) => match as_geo_point(object) {
+            Some(point) => append_value(path, PayloadType::Geo(vec![point]), payload),
+            None => {
+                for (key, value) in object {
+                    flatten_value(&nested_path(path, key), value, payload)?;
+                }
+                Ok(())
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::Object(object) if as_geo_point(object).is_none() => {
+                        let array_path = format!("{}{}", path, ARRAY_KEY_SUFFIX);
+                        flatten_value(&array_path, item, payload)?
+                    }
+                    _ => flatten_value(path, item, payload)?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Add values to the ones, already stored under the same key.
+/// Empty values are compatible with any type
+fn append_value(
+    key: &str,
+    value: PayloadType,
+    payload: &mut TheMap<PayloadKeyType, PayloadType>,
+) -> OperationResult<()> {
+    let merged = match (payload.remove(key), value) {
+        (None, value) => value,
+        (Some(stored), value) if value.is_empty() => stored,
+        (Some(stored), value) if stored.is_empty() => value,
+        (Some(PayloadType::Keyword(mut stored)), PayloadType::Keyword(values)) => {
+            stored.extend(values);
+            PayloadType::Keyword(stored)
+        }
+        (Some(PayloadType::Integer(mut stored)), PayloadType::Integer(values)) => {
+            stored.extend(values);
+            PayloadType::Integer(stored)
+        }
+        (Some(PayloadType::Float(mut stored)), PayloadType::Float(values)) => {
+            stored.extend(values);
+            PayloadType::Float(stored)
+        }
+        // Whole and fractional numbers of the same field are all stored as floats
+        (Some(PayloadType::Integer(stored)), PayloadType::Float(values)) => PayloadType::Float(
+            stored
+                .into_iter()
+                .map(|x| x as FloatPayloadType)
+                .chain(values)
+                .collect(),
+        ),
+        (Some(PayloadType::Float(mut stored)), PayloadType::Integer(values)) => {
+            stored.extend(values.into_iter().map(|x| x as FloatPayloadType));
+            PayloadType::Float(stored)
+        }
+        (Some(PayloadType::Geo(mut stored)), PayloadType::Geo(values)) => {
+            stored.extend(values);
+            PayloadType::Geo(stored)
+        }
+        (Some(PayloadType::Bool(mut stored)), PayloadType::Bool(values)) => {
+            stored.extend(values);
+            PayloadType::Bool(stored)
+        }
+        (Some(stored), _) => {
+            return Err(OperationError::TypeError {
+                field_name: key.to_owned(),
+                expected_type: format!("{:?}", PayloadSchemaType::from(&stored)),
+            })
+        }
+    };
+    payload.insert(key.to_owned(), merged);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_payload_from_json() {
+        let payload = payload_from_json(&json!({
+            "title": "Annual report",
+            "source": {"kind": "pdf", "page": 3, "location": {"lon": 13.4, "lat": 52.5}},
+            "tags": [{"name": "finance", "score": 1}, {"name": "2021", "score": 0.5}],
+            "authors": ["alice", "bob"],
+            "published": true,
+            "created_at": "2021-01-01T00:00:00.500Z",
+            "reviewed_at": null,
+        }))
+        .unwrap();
+
+        assert_eq!(payload.len(), 10);
+        match payload.get("source.kind") {
+            Some(PayloadType::Keyword(x)) => assert_eq!(x, &vec!["pdf".to_string()]),
+            _ => panic!("source.kind is not a keyword"),
+        }
+        match payload.get("source.page") {
+            Some(PayloadType::Integer(x)) => assert_eq!(x, &vec![3]),
+            _ => panic!("source.page is not an integer"),
+        }
+        match payload.get("source.location") {
+            Some(PayloadType::Geo(x)) => assert_eq!(x.len(), 1),
+            _ => panic!("source.location is not a geo point"),
+        }
+        match payload.get("tags[].name") {
+            Some(PayloadType::Keyword(x)) => assert_eq!(x.len(), 2),
+            _ => panic!("tags[].name is not a keyword"),
+        }
+        match payload.get("tags[].score") {
+            Some(PayloadType::Float(x)) => assert_eq!(x, &vec![1.0, 0.5]),
+            _ => panic!("tags[].score is not a float"),
+        }
+        match payload.get("authors") {
+            Some(PayloadType::Keyword(x)) => assert_eq!(x.len(), 2),
+            _ => panic!("authors is not a keyword"),
+        }
+        match payload.get("published") {
+            Some(PayloadType::Bool(x)) => assert_eq!(x, &vec![true]),
+            _ => panic!("published is not a bool"),
+        }
+        // Type is not guessed from the content of the string
+        match payload.get("created_at") {
+            Some(PayloadType::Keyword(x)) => assert_eq!(x, &vec!["2021-01-01T00:00:00.500Z"]),
+            _ => panic!("created_at is not a keyword"),
+        }
+        assert!(payload.get("reviewed_at").unwrap().is_empty());
+
+        let dates = payload_from_json(&json!({"dates": ["2021-01-01T00:00:00Z", "n/a"]})).unwrap();
+        assert_eq!(dates.get("dates").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_json_payload() {
+        assert!(payload_from_json(&json!(["not", "an", "object"])).is_err());
+        assert!(payload_from_json(&json!({"flag": [true, 1]})).is_err());
+        assert!(payload_from_json(&json!({"mixed": ["text", 1]})).is_err());
+        assert!(payload_from_json(&json!({"big": u64::MAX})).is_err());
+    }
+
+    #[test]
+    fn test_is_nested_key() {
+        assert!(is_nested_key("source", "source"));
+        assert!(is_nested_key("source", "source.kind"));
+        assert!(is_nested_key("tags", "tags[].name"));
+        assert!(is_nested_key("source.kind", "source.kind"));
+        assert!(!is_nested_key("source", "sources"));
+        assert!(!is_nested_key("source.kind", "source"));
+    }
+}
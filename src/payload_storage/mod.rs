@@ -0,0 +1,7 @@
+pub mod condition_checker;
+pub mod json_payload;
+mod payload_storage_base;
+pub mod query_checker;
+pub mod simple_payload_storage;
+
+pub use payload_storage_base::*;
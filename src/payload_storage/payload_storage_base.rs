@@ -0,0 +1,64 @@
+use crate::entry::entry_point::OperationResult;
+use crate::types::{
+    Filter, PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaType, PayloadType, PointOffsetType,
+    TheMap,
+};
+
+/// Trait for payload data storage.
+/// Storage operates with internal IDs (PointOffsetType), same as the vector storage
+pub trait PayloadStorage: Send + Sync {
+    /// Replace whole payload of the point with a given one
+    fn assign_all(
+        &mut self,
+        point_id: PointOffsetType,
+        payload: TheMap<PayloadKeyType, PayloadType>,
+    ) -> OperationResult<()> {
+        self.drop(point_id)?;
+        for (key, value) in payload {
+            self.assign(point_id, &key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Assign payload value to a concrete point under a given key
+    fn assign(
+        &mut self,
+        point_id: PointOffsetType,
+        key: PayloadKeyTypeRef,
+        payload: PayloadType,
+    ) -> OperationResult<()>;
+
+    /// Get payload of the point. Empty map is returned if there is no payload
+    fn payload(&self, point_id: PointOffsetType) -> TheMap<PayloadKeyType, PayloadType>;
+
+    /// Delete payload value by key, returns removed value if any
+    fn delete(
+        &mut self,
+        point_id: PointOffsetType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<Option<PayloadType>>;
+
+    /// Drop all payload of the point, returns removed payload if any
+    fn drop(
+        &mut self,
+        point_id: PointOffsetType,
+    ) -> OperationResult<Option<TheMap<PayloadKeyType, PayloadType>>>;
+
+    /// Completely drop payload of all points
+    fn wipe(&mut self) -> OperationResult<()>;
+
+    /// Iterate over ids of all points which have some payload
+    fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_>;
+
+    /// Return type of each payload field ever stored in this storage
+    fn schema(&self) -> TheMap<PayloadKeyType, PayloadSchemaType>;
+
+    /// Force persistence of current storage state.
+    fn flush(&self) -> OperationResult<()>;
+}
+
+/// Checks if points satisfy filtering conditions
+pub trait ConditionChecker: Send + Sync {
+    /// Check if point satisfies filter condition
+    fn check(&self, point_id: PointOffsetType, query: &Filter) -> bool;
+}
@@ -0,0 +1,332 @@
+use crate::id_tracker::IdTracker;
+use crate::payload_storage::condition_checker::ValueChecker;
+use crate::payload_storage::simple_payload_storage::SimplePayloadStorage;
+use crate::payload_storage::ConditionChecker;
+use crate::types::{
+    Condition, FieldCondition, Filter, IsEmptyCondition, IsNullCondition, PayloadKeyType,
+    PayloadType, PointOffsetType, TheMap,
+};
+use atomic_refcell::AtomicRefCell;
+use std::sync::Arc;
+
+fn check_condition<F>(checker: &F, condition: &Condition) -> bool
+where
+    F: Fn(&Condition) -> bool,
+{
+    match condition {
+        Condition::Filter(filter) => check_filter(checker, filter),
+        _ => checker(condition),
+    }
+}
+
+pub fn check_filter<F>(checker: &F, filter: &Filter) -> bool
+where
+    F: Fn(&Condition) -> bool,
+{
+    check_should(checker, &filter.should)
+        && check_must(checker, &filter.must)
+        && check_must_not(checker, &filter.must_not)
+}
+
+fn check_should<F>(checker: &F, should: &Option<Vec<Condition>>) -> bool
+where
+    F: Fn(&Condition) -> bool,
+{
+    let check = |x| check_condition(checker, x);
+    match should {
+        None => true,
+        Some(conditions) => conditions.iter().any(check),
+    }
+}
+
+fn check_must<F>(checker: &F, must: &Option<Vec<Condition>>) -> bool
+where
+    F: Fn(&Condition) -> bool,
+{
+    let check = |x| check_condition(checker, x);
+    match must {
+        None => true,
+        Some(conditions) => conditions.iter().all(check),
+    }
+}
+
+fn check_must_not<F>(checker: &F, must: &Option<Vec<Condition>>) -> bool
+where
+    F: Fn(&Condition) -> bool,
+{
+    let check = |x| !check_condition(checker, x);
+    match must {
+        None => true,
+        Some(conditions) => conditions.iter().all(check),
+    }
+}
+
+/// Check if any of conditions, defined in `field_condition`, matches the payload
+pub fn check_field_condition(
+    field_condition: &FieldCondition,
+    payload: &TheMap<PayloadKeyType, PayloadType>,
+) -> bool {
+    let field_value = match payload.get(&field_condition.key) {
+        None => return false,
+        Some(value) => value,
+    };
+
+    field_condition
+        .r#match
+        .as_ref()
+        .map_or(false, |condition| condition.check(field_value))
+        || field_condition
+            .range
+            .as_ref()
+            .map_or(false, |condition| condition.check(field_value))
+        || field_condition
+            .geo_bounding_box
+            .as_ref()
+            .map_or(false, |condition| condition.check(field_value))
+        || field_condition
+            .geo_radius
+            .as_ref()
+            .map_or(false, |condition| condition.check(field_value))
+}
+
+/// Checks filter conditions using payload and id tracker of the segment
+pub struct SimpleConditionChecker {
+    payload_storage: Arc<AtomicRefCell<SimplePayloadStorage>>,
+    id_tracker: Arc<AtomicRefCell<dyn IdTracker>>,
+}
+
+impl SimpleConditionChecker {
+    pub fn new(
+        payload_storage: Arc<AtomicRefCell<SimplePayloadStorage>>,
+        id_tracker: Arc<AtomicRefCell<dyn IdTracker>>,
+    ) -> Self {
+        SimpleConditionChecker {
+            payload_storage,
+            id_tracker,
+        }
+    }
+}
+
+impl ConditionChecker for SimpleConditionChecker {
+    fn check(&self, point_id: PointOffsetType, query: &Filter) -> bool {
+        let empty_payload = TheMap::new();
+
+        let payload_storage_guard = self.payload_storage.borrow();
+        let payload = payload_storage_guard
+            .payload_ptr(point_id)
+            .unwrap_or(&empty_payload);
+
+        let checker = |condition: &Condition| match condition {
+            Condition::Field(field_condition) => check_field_condition(field_condition, payload),
+            Condition::HasId(has_id) => self
+                .id_tracker
+                .borrow()
+                .external_id(point_id)
+                .map_or(false, |external_id| has_id.has_id.contains(&external_id)),
+            Condition::IsEmpty(IsEmptyCondition { is_empty: field }) => payload
+                .get(&field.key)
+                .map_or(true, |value| value.is_empty()),
+            Condition::IsNull(IsNullCondition { is_null: field }) => payload
+                .get(&field.key)
+                .map_or(false, |value| value.is_empty()),
+            Condition::Filter(_) => panic!("Unexpected branching!"),
+        };
+
+        check_filter(&checker, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_tracker::simple_id_tracker::SimpleIdTracker;
+    use crate::payload_storage::PayloadStorage;
+    use crate::types::{GeoPoint, Match, PayloadField, PointIdType, Range};
+    use std::collections::HashSet;
+    use tempdir::TempDir;
+
+    fn keyword_condition(key: &str, keyword: &str) -> Condition {
+        Condition::Field(FieldCondition {
+            key: key.to_owned(),
+            r#match: Some(Match {
+                keyword: Some(keyword.to_owned()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        })
+    }
+
+    #[test]
+    fn test_condition_checker() {
+        let dir = TempDir::new("payload_dir").unwrap();
+        let dir_id_tracker = TempDir::new("id_tracker_dir").unwrap();
+
+        let mut payload_storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        let mut id_tracker = SimpleIdTracker::open(dir_id_tracker.path()).unwrap();
+
+        id_tracker.set_link(0.into(), 0).unwrap();
+        id_tracker.set_link(1.into(), 1).unwrap();
+        id_tracker.set_link(2.into(), 2).unwrap();
+        id_tracker.set_link(10.into(), 10).unwrap();
+
+        payload_storage
+            .assign(
+                0,
+                "location",
+                PayloadType::Geo(vec![GeoPoint {
+                    lon: 13.404954,
+                    lat: 52.520008,
+                }]),
+            )
+            .unwrap();
+        payload_storage
+            .assign(0, "price", PayloadType::Float(vec![499.90, 12.0]))
+            .unwrap();
+        payload_storage
+            .assign(0, "amount", PayloadType::Integer(vec![10]))
+            .unwrap();
+        payload_storage
+            .assign(0, "rating", PayloadType::Integer(vec![3, 7, 9, 9]))
+            .unwrap();
+        payload_storage
+            .assign(0, "color", PayloadType::Keyword(vec!["red".to_owned()]))
+            .unwrap();
+        payload_storage
+            .assign(0, "has_delivery", PayloadType::Integer(vec![1]))
+            .unwrap();
+
+        let payload_checker = SimpleConditionChecker::new(
+            Arc::new(AtomicRefCell::new(payload_storage)),
+            Arc::new(AtomicRefCell::new(id_tracker)),
+        );
+
+        let match_red = keyword_condition("color", "red");
+        let match_blue = keyword_condition("color", "blue");
+        let with_delivery = Condition::Field(FieldCondition {
+            key: "has_delivery".to_owned(),
+            r#match: Some(Match {
+                keyword: None,
+                integer: Some(1),
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        });
+        let in_berlin = Condition::Field(FieldCondition {
+            key: "location".to_owned(),
+            r#match: None,
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: Some(crate::types::GeoRadius {
+                center: GeoPoint {
+                    lon: 13.41,
+                    lat: 52.52,
+                },
+                radius: 10_000.0,
+            }),
+        });
+        let cheap = Condition::Field(FieldCondition {
+            key: "price".to_owned(),
+            r#match: None,
+            range: Some(Range {
+                lt: Some(20.0),
+                gt: None,
+                gte: None,
+                lte: None,
+            }),
+            geo_bounding_box: None,
+            geo_radius: None,
+        });
+
+        let query = Filter {
+            should: None,
+            must: Some(vec![match_red.clone(), with_delivery.clone(), in_berlin]),
+            must_not: None,
+        };
+        assert!(payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: None,
+            must: Some(vec![match_red.clone()]),
+            must_not: Some(vec![cheap.clone()]),
+        };
+        assert!(!payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: Some(vec![match_blue.clone(), cheap]),
+            must: None,
+            must_not: None,
+        };
+        assert!(payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: Some(vec![match_blue.clone()]),
+            must: None,
+            must_not: None,
+        };
+        assert!(!payload_checker.check(0, &query));
+
+        let query = Filter {
+            should: Some(vec![Condition::Filter(Filter {
+                should: None,
+                must: Some(vec![match_blue]),
+                must_not: None,
+            })]),
+            must: None,
+            must_not: Some(vec![Condition::Filter(Filter::new_must(match_red))]),
+        };
+        assert!(!payload_checker.check(0, &query));
+
+        let ids: HashSet<_> = (1..=3u64).map(PointIdType::from).collect();
+        let query = Filter::new_must_not(Condition::HasId(ids.into()));
+        assert!(!payload_checker.check(2, &query));
+        assert!(payload_checker.check(10, &query));
+
+        // Points without payload do not match any field condition
+        let query = Filter::new_must(with_delivery);
+        assert!(!payload_checker.check(10, &query));
+    }
+
+    #[test]
+    fn test_empty_and_null_conditions() {
+        let dir = TempDir::new("payload_dir").unwrap();
+        let dir_id_tracker = TempDir::new("id_tracker_dir").unwrap();
+
+        let mut payload_storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        let id_tracker = SimpleIdTracker::open(dir_id_tracker.path()).unwrap();
+
+        payload_storage
+            .assign(0, "published_at", PayloadType::Datetime(vec![1_600_000_000]))
+            .unwrap();
+        payload_storage
+            .assign(1, "published_at", PayloadType::Keyword(vec![]))
+            .unwrap();
+
+        let payload_checker = SimpleConditionChecker::new(
+            Arc::new(AtomicRefCell::new(payload_storage)),
+            Arc::new(AtomicRefCell::new(id_tracker)),
+        );
+
+        let field = PayloadField {
+            key: "published_at".to_owned(),
+        };
+        let is_empty = Filter::new_must(Condition::IsEmpty(IsEmptyCondition {
+            is_empty: field.clone(),
+        }));
+        let is_null = Filter::new_must(Condition::IsNull(IsNullCondition { is_null: field }));
+
+        assert!(!payload_checker.check(0, &is_empty));
+        assert!(payload_checker.check(1, &is_empty));
+        assert!(payload_checker.check(2, &is_empty));
+
+        assert!(!payload_checker.check(0, &is_null));
+        assert!(payload_checker.check(1, &is_null));
+        assert!(!payload_checker.check(2, &is_null));
+    }
+}
@@ -0,0 +1,415 @@
+use crate::entry::entry_point::{OperationError, OperationResult};
+use crate::payload_storage::PayloadStorage;
+use crate::types::{
+    PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaType, PayloadType, PointOffsetType, TheMap,
+};
+use rocksdb::{IteratorMode, Options, DB};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Since sled is used for reading only during the initialization, large read cache is not required
+const DB_CACHE_SIZE: usize = 10 * 1024 * 1024; // 10 mb
+
+/// Column family with the type of each field, so that the type outlives the values of the field
+const SCHEMA_CF: &str = "schema";
+
+/// In-memory implementation of `PayloadStorage`.
+/// Persists all changes to disk using `store`, but only uses this storage during the initial load
+pub struct SimplePayloadStorage {
+    payload: HashMap<PointOffsetType, TheMap<PayloadKeyType, PayloadType>>,
+    schema: TheMap<PayloadKeyType, PayloadSchemaType>,
+    store: DB,
+}
+
+impl SimplePayloadStorage {
+    pub fn open(path: &Path, read_only: bool) -> OperationResult<Self> {
+        let mut options: Options = Options::default();
+        options.set_write_buffer_size(DB_CACHE_SIZE);
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let store = match read_only {
+            true => {
+                // Storages created before the schema was persisted have no schema column family,
+                // and read-only mode can not create it
+                let column_families: Vec<_> = DB::list_cf(&options, path)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|name| name == SCHEMA_CF)
+                    .collect();
+                DB::open_cf_for_read_only(&options, path, column_families, false)?
+            }
+            false => DB::open_cf(&options, path, [SCHEMA_CF])?,
+        };
+
+        let mut schema: TheMap<PayloadKeyType, PayloadSchemaType> = Default::default();
+        if let Some(schema_cf) = store.cf_handle(SCHEMA_CF) {
+            for (key, val) in store.iterator_cf(schema_cf, IteratorMode::Start) {
+                let field: PayloadKeyType = serde_cbor::from_slice(&key).unwrap();
+                let schema_type: PayloadSchemaType = serde_cbor::from_slice(&val).unwrap();
+                schema.insert(field, schema_type);
+            }
+        }
+
+        let mut payload_map: HashMap<PointOffsetType, TheMap<PayloadKeyType, PayloadType>> =
+            Default::default();
+        let mut restored_schema: TheMap<PayloadKeyType, PayloadSchemaType> = Default::default();
+
+        for (key, val) in store.iterator(IteratorMode::Start) {
+            let point_id: PointOffsetType = serde_cbor::from_slice(&key).unwrap();
+            let payload: TheMap<PayloadKeyType, PayloadType> =
+                serde_cbor::from_slice(&val).unwrap();
+            for (field, value) in payload.iter().filter(|(_, value)| !value.is_empty()) {
+                if !schema.contains_key(field) {
+                    restored_schema
+                        .entry(field.to_owned())
+                        .or_insert_with(|| value.into());
+                }
+            }
+            payload_map.insert(point_id, payload);
+        }
+
+        let mut storage = SimplePayloadStorage {
+            payload: payload_map,
+            schema,
+            store,
+        };
+
+        // Fields of storages created before the schema was persisted are restored from values
+        for (field, schema_type) in restored_schema {
+            if !read_only {
+                storage.store_schema(&field, &schema_type)?;
+            }
+            storage.schema.insert(field, schema_type);
+        }
+
+        Ok(storage)
+    }
+
+    fn store_schema(
+        &self,
+        key: PayloadKeyTypeRef,
+        schema_type: &PayloadSchemaType,
+    ) -> OperationResult<()> {
+        self.store.put_cf(
+            self.store.cf_handle(SCHEMA_CF).unwrap(),
+            serde_cbor::to_vec(&key).unwrap(),
+            serde_cbor::to_vec(schema_type).unwrap(),
+        )?;
+        Ok(())
+    }
+
+    /// Convert the value into the type of previously stored values of the field,
+    /// fail if it conflicts with them. Empty values are allowed for any field
+    fn check_schema(
+        &self,
+        key: PayloadKeyTypeRef,
+        value: PayloadType,
+    ) -> OperationResult<PayloadType> {
+        match self.schema.get(key) {
+            Some(schema_type) if !value.is_empty() => value
+                .into_schema_type(*schema_type)
+                .ok_or_else(|| OperationError::TypeError {
+                    field_name: key.to_owned(),
+                    expected_type: format!("{:?}", schema_type),
+                }),
+            _ => Ok(value),
+        }
+    }
+
+    fn update_schema(
+        &mut self,
+        key: PayloadKeyTypeRef,
+        value: PayloadType,
+    ) -> OperationResult<PayloadType> {
+        let value = self.check_schema(key, value)?;
+        if !value.is_empty() && !self.schema.contains_key(key) {
+            let schema_type = (&value).into();
+            self.store_schema(key, &schema_type)?;
+            self.schema.insert(key.to_owned(), schema_type);
+        }
+        Ok(value)
+    }
+
+    fn update_storage(&self, point_id: PointOffsetType) -> OperationResult<()> {
+        match self.payload.get(&point_id) {
+            None => self.store.delete(serde_cbor::to_vec(&point_id).unwrap())?,
+            Some(payload) => self.store.put(
+                serde_cbor::to_vec(&point_id).unwrap(),
+                serde_cbor::to_vec(payload).unwrap(),
+            )?,
+        };
+        Ok(())
+    }
+
+    /// Get payload of the point without copying it
+    pub fn payload_ptr(
+        &self,
+        point_id: PointOffsetType,
+    ) -> Option<&TheMap<PayloadKeyType, PayloadType>> {
+        self.payload.get(&point_id)
+    }
+}
+
+impl PayloadStorage for SimplePayloadStorage {
+    fn assign_all(
+        &mut self,
+        point_id: PointOffsetType,
+        payload: TheMap<PayloadKeyType, PayloadType>,
+    ) -> OperationResult<()> {
+        // Validate the whole payload first, so the point is never left half-updated
+        let payload = payload
+            .into_iter()
+            .map(|(key, value)| Ok((key.clone(), self.check_schema(&key, value)?)))
+            .collect::<OperationResult<TheMap<_, _>>>()?;
+        self.drop(point_id)?;
+        for (key, value) in payload {
+            self.assign(point_id, &key, value)?;
+        }
+        Ok(())
+    }
+
+    fn assign(
+        &mut self,
+        point_id: PointOffsetType,
+        key: PayloadKeyTypeRef,
+        payload: PayloadType,
+    ) -> OperationResult<()> {
+        let payload = self.update_schema(key, payload)?;
+        self.payload
+            .entry(point_id)
+            .or_insert_with(Default::default)
+            .insert(key.to_owned(), payload);
+        self.update_storage(point_id)?;
+        Ok(())
+    }
+
+    fn payload(&self, point_id: PointOffsetType) -> TheMap<PayloadKeyType, PayloadType> {
+        match self.payload.get(&point_id) {
+            Some(payload) => payload.clone(),
+            None => TheMap::new(),
+        }
+    }
+
+    fn delete(
+        &mut self,
+        point_id: PointOffsetType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<Option<PayloadType>> {
+        match self.payload.get_mut(&point_id) {
+            Some(payload) => {
+                let res = payload.remove(key);
+                if res.is_some() {
+                    self.update_storage(point_id)?;
+                }
+                Ok(res)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn drop(
+        &mut self,
+        point_id: PointOffsetType,
+    ) -> OperationResult<Option<TheMap<PayloadKeyType, PayloadType>>> {
+        let res = self.payload.remove(&point_id);
+        if res.is_some() {
+            self.update_storage(point_id)?;
+        }
+        Ok(res)
+    }
+
+    fn wipe(&mut self) -> OperationResult<()> {
+        for point_id in self.payload.keys() {
+            self.store.delete(serde_cbor::to_vec(point_id).unwrap())?;
+        }
+        let schema_cf = self.store.cf_handle(SCHEMA_CF).unwrap();
+        for field in self.schema.keys() {
+            self.store
+                .delete_cf(schema_cf, serde_cbor::to_vec(field).unwrap())?;
+        }
+        self.payload = HashMap::new();
+        self.schema = TheMap::new();
+        Ok(())
+    }
+
+    fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        Box::new(self.payload.keys().cloned())
+    }
+
+    fn schema(&self) -> TheMap<PayloadKeyType, PayloadSchemaType> {
+        self.schema.clone()
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        Ok(self.store.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_wipe() {
+        let dir = TempDir::new("storage_dir").unwrap();
+
+        let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        let payload = PayloadType::Integer(vec![1, 2, 3]);
+        let key = "key".to_owned();
+        storage.assign(100, &key, payload.clone()).unwrap();
+        storage.wipe().unwrap();
+        storage.assign(100, &key, payload.clone()).unwrap();
+        storage.wipe().unwrap();
+        storage.assign(100, &key, payload).unwrap();
+        assert!(!storage.payload(100).is_empty());
+        storage.wipe().unwrap();
+        assert!(storage.payload(100).is_empty());
+        assert_eq!(storage.iter_ids().count(), 0);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let key = "kwd".to_owned();
+        {
+            let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+            storage
+                .assign(1, &key, PayloadType::Keyword(vec!["hello".to_owned()]))
+                .unwrap();
+            storage
+                .assign(2, &key, PayloadType::Keyword(vec!["world".to_owned()]))
+                .unwrap();
+            storage
+                .assign(2, "int", PayloadType::Integer(vec![42]))
+                .unwrap();
+            storage.delete(2, &key).unwrap();
+            storage.drop(1).unwrap();
+            storage.flush().unwrap();
+        }
+
+        let storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        assert!(storage.payload(1).is_empty());
+        let payload = storage.payload(2);
+        assert_eq!(payload.len(), 1);
+        match payload.get("int") {
+            Some(PayloadType::Integer(values)) => assert_eq!(values, &vec![42]),
+            _ => panic!("Wrong payload restored"),
+        }
+    }
+
+    #[test]
+    fn test_schema_conflict() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        {
+            let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+            storage
+                .assign(1, "size", PayloadType::Integer(vec![42]))
+                .unwrap();
+
+            let res = storage.assign(2, "size", PayloadType::Keyword(vec!["big".to_owned()]));
+            match res {
+                Err(OperationError::TypeError { field_name, .. }) => assert_eq!(field_name, "size"),
+                _ => panic!("Type conflict is not detected"),
+            }
+            assert!(storage.payload(2).is_empty());
+
+            let mut full_payload: TheMap<PayloadKeyType, PayloadType> = Default::default();
+            full_payload.insert(
+                "color".to_owned(),
+                PayloadType::Keyword(vec!["red".to_owned()]),
+            );
+            full_payload.insert("size".to_owned(), PayloadType::Float(vec![1.5]));
+            assert!(storage.assign_all(1, full_payload).is_err());
+            // Failed assignment does not change the payload
+            assert_eq!(storage.payload(1).len(), 1);
+            assert!(!storage.schema().contains_key("color"));
+            storage.flush().unwrap();
+        }
+
+        let storage = SimplePayloadStorage::open(dir.path(), true).unwrap();
+        assert_eq!(
+            storage.schema().get("size"),
+            Some(&PayloadSchemaType::Integer)
+        );
+    }
+
+    #[test]
+    fn test_schema_persistence() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        {
+            let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+            storage
+                .assign(1, "size", PayloadType::Integer(vec![42]))
+                .unwrap();
+            storage.delete(1, "size").unwrap();
+            storage.flush().unwrap();
+        }
+
+        // Field is type-locked even though none of the points has it anymore
+        let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        assert_eq!(
+            storage.schema().get("size"),
+            Some(&PayloadSchemaType::Integer)
+        );
+        assert!(storage
+            .assign(2, "size", PayloadType::Keyword(vec!["big".to_owned()]))
+            .is_err());
+
+        storage.wipe().unwrap();
+        storage.flush().unwrap();
+        drop(storage);
+        let storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+        assert!(storage.schema().is_empty());
+    }
+
+    #[test]
+    fn test_schema_conversion() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let mut storage = SimplePayloadStorage::open(dir.path(), false).unwrap();
+
+        storage
+            .assign(1, "score", PayloadType::Float(vec![0.5]))
+            .unwrap();
+        storage
+            .assign(2, "score", PayloadType::Integer(vec![1]))
+            .unwrap();
+        match storage.payload(2).get("score") {
+            Some(PayloadType::Float(values)) => assert_eq!(values, &vec![1.0]),
+            _ => panic!("Integer is not converted into float"),
+        }
+
+        // Strings are datetimes only if the field is a datetime one
+        storage
+            .assign(1, "created_at", PayloadType::Datetime(vec![0]))
+            .unwrap();
+        let date = "2021-01-01T00:00:00.500Z".to_owned();
+        storage
+            .assign(2, "created_at", PayloadType::Keyword(vec![date.clone()]))
+            .unwrap();
+        match storage.payload(2).get("created_at") {
+            Some(PayloadType::Datetime(values)) => assert_eq!(values, &vec![1_609_459_200_500]),
+            _ => panic!("String is not converted into datetime"),
+        }
+        let mixed = PayloadType::Keyword(vec![date.clone(), "n/a".to_owned()]);
+        assert!(storage.assign(3, "created_at", mixed.clone()).is_err());
+
+        storage
+            .assign(1, "comment", PayloadType::Keyword(vec![date]))
+            .unwrap();
+        storage.assign(2, "comment", mixed).unwrap();
+        assert_eq!(
+            storage.schema().get("comment"),
+            Some(&PayloadSchemaType::Keyword)
+        );
+
+        // Fractional values do not fit integer field
+        storage
+            .assign(1, "size", PayloadType::Integer(vec![42]))
+            .unwrap();
+        assert!(storage
+            .assign(2, "size", PayloadType::Float(vec![1.5]))
+            .is_err());
+    }
+}
@@ -2,16 +2,22 @@ use crate::entry::entry_point::{
     get_service_error, OperationError, OperationResult, SegmentEntry, SegmentFailedState,
 };
 use crate::id_tracker::IdTracker;
-use crate::index::{VectorIndex};
+use crate::index::sample_estimation::sample_estimate_cardinality;
+use crate::index::{PayloadIndex, VectorIndex};
+use crate::payload_storage::{ConditionChecker, PayloadStorage};
 use crate::spaces::tools::mertic_object;
 use crate::types::{
-    PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaInfo, PayloadType, PointIdType,
-    PointOffsetType, ScoredPoint, SearchParams, SegmentConfig, SegmentInfo, SegmentState,
-    SegmentType, SeqNumberType, TheMap, VectorElementType, WithPayload,
+    FacetValue, FacetValueHit, Filter, PayloadKeyType, PayloadKeyTypeRef, PayloadSchemaInfo,
+    PayloadType, PointIdType, PointOffsetType, Record, ScoredPoint, ScrollResult, SearchParams,
+    SegmentConfig, SegmentInfo, SegmentState, SegmentType, SeqNumberType, TheMap,
+    VectorElementType, WithPayload,
 };
 use crate::vector_storage::VectorStorage;
 use atomic_refcell::AtomicRefCell;
 use atomicwrites::{AllowOverwrite, AtomicFile};
+use itertools::Itertools;
+use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::fs::{remove_dir_all, rename};
 use std::io::Write;
 use std::path::PathBuf;
@@ -19,6 +25,23 @@ use std::sync::{Arc, Mutex};
 
 pub const SEGMENT_STATE_FILE: &str = "segment.json";
 
+/// Distinct values of the payload field, which could be used for faceting
+fn facet_values(payload: &PayloadType) -> Vec<FacetValue> {
+    match payload {
+        PayloadType::Keyword(keywords) => keywords
+            .iter()
+            .unique()
+            .map(|x| FacetValue::Keyword(x.clone()))
+            .collect(),
+        PayloadType::Integer(numbers) => numbers
+            .iter()
+            .unique()
+            .map(|x| FacetValue::Integer(*x))
+            .collect(),
+        _ => vec![],
+    }
+}
+
 /// Simple segment implementation
 pub struct Segment {
     pub version: SeqNumberType,
@@ -26,7 +49,10 @@ pub struct Segment {
     pub current_path: PathBuf,
     pub id_tracker: Arc<AtomicRefCell<dyn IdTracker>>,
     pub vector_storage: Arc<AtomicRefCell<dyn VectorStorage>>,
+    pub payload_storage: Arc<AtomicRefCell<dyn PayloadStorage>>,
+    pub payload_index: Arc<AtomicRefCell<dyn PayloadIndex>>,
     pub vector_index: Arc<AtomicRefCell<dyn VectorIndex>>,
+    pub condition_checker: Arc<dyn ConditionChecker>,
     pub appendable_flag: bool,
     pub segment_type: SegmentType,
     pub segment_config: SegmentConfig,
@@ -47,15 +73,8 @@ impl Segment {
         Ok(new_internal_index)
     }
 
-    fn handle_version_and_failure<F>(
-        &mut self,
-        op_num: SeqNumberType,
-        op_point_id: Option<PointIdType>,
-        operation: F,
-    ) -> OperationResult<bool>
-    where
-        F: FnOnce(&mut Segment) -> OperationResult<bool>,
-    {
+    /// Fail if segment is not recovered from an error of an older operation
+    fn check_failed_state(&self, op_num: SeqNumberType) -> OperationResult<()> {
         if let Some(SegmentFailedState {
             version: failed_version,
             point_id: _failed_point_id,
@@ -70,16 +89,22 @@ impl Segment {
                 });
             } // else: Re-try operation
         }
+        Ok(())
+    }
 
-        let res = self.handle_version(op_num, op_point_id, operation);
-
-        match get_service_error(&res) {
+    fn update_failed_state<T>(
+        &mut self,
+        op_num: SeqNumberType,
+        op_point_id: Option<&PointIdType>,
+        res: &OperationResult<T>,
+    ) {
+        match get_service_error(res) {
             None => {
                 // Recover error state
                 match &self.error_status {
                     None => {} // all good
                     Some(error) => {
-                        if error.point_id == op_point_id {
+                        if error.point_id.as_ref() == op_point_id {
                             // Fixed
                             self.error_status = None;
                         }
@@ -90,11 +115,65 @@ impl Segment {
                 // ToDo: Recover previous segment state
                 self.error_status = Some(SegmentFailedState {
                     version: op_num,
-                    point_id: op_point_id,
+                    point_id: op_point_id.cloned(),
                     error,
                 })
             }
         }
+    }
+
+    fn handle_version_and_failure<F>(
+        &mut self,
+        op_num: SeqNumberType,
+        op_point_id: Option<&PointIdType>,
+        operation: F,
+    ) -> OperationResult<bool>
+    where
+        F: FnOnce(&mut Segment) -> OperationResult<bool>,
+    {
+        self.check_failed_state(op_num)?;
+        let res = self.handle_version(op_num, op_point_id, operation);
+        self.update_failed_state(op_num, op_point_id, &res);
+        res
+    }
+
+    /// Version checking for a batch of point operations under a single operation number.
+    /// Points, which already have a newer version, are skipped and not passed to the `operation`
+    fn handle_batch_version_and_failure<F>(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: Vec<PointIdType>,
+        operation: F,
+    ) -> OperationResult<usize>
+    where
+        F: FnOnce(&mut Segment, &[PointIdType]) -> OperationResult<usize>,
+    {
+        self.check_failed_state(op_num)?;
+
+        let point_ids: Vec<PointIdType> = {
+            let id_tracker = self.id_tracker.borrow();
+            point_ids
+                .into_iter()
+                .filter(|point_id| {
+                    id_tracker
+                        .version(point_id)
+                        .map(|current_version| current_version <= op_num)
+                        .unwrap_or(true)
+                })
+                .collect()
+        };
+        if point_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let res = operation(self, &point_ids).and_then(|applied| {
+            self.version = op_num;
+            self.id_tracker
+                .borrow_mut()
+                .set_versions(&point_ids, op_num)?;
+            Ok(applied)
+        });
+        self.update_failed_state(op_num, None, &res);
         res
     }
 
@@ -104,7 +183,7 @@ impl Segment {
     fn handle_version<F>(
         &mut self,
         op_num: SeqNumberType,
-        op_point_id: Option<PointIdType>,
+        op_point_id: Option<&PointIdType>,
         operation: F,
     ) -> OperationResult<bool>
     where
@@ -136,18 +215,20 @@ impl Segment {
         if res.is_ok() {
             self.version = op_num;
             if let Some(point_id) = op_point_id {
-                self.id_tracker.borrow_mut().set_version(point_id, op_num)?;
+                self.id_tracker
+                    .borrow_mut()
+                    .set_version(point_id.clone(), op_num)?;
             }
         }
         res
     }
 
-    fn lookup_internal_id(&self, point_id: PointIdType) -> OperationResult<PointOffsetType> {
+    fn lookup_internal_id(&self, point_id: &PointIdType) -> OperationResult<PointOffsetType> {
         let internal_id_opt = self.id_tracker.borrow().internal_id(point_id);
         match internal_id_opt {
             Some(internal_id) => Ok(internal_id),
             None => Err(OperationError::PointIdError {
-                missed_point_id: point_id,
+                missed_point_id: point_id.clone(),
             }),
         }
     }
@@ -167,6 +248,22 @@ impl Segment {
         Ok(())
     }
 
+    /// Payload of the point, reduced according to `with_payload` settings
+    fn selected_payload(
+        &self,
+        internal_id: PointOffsetType,
+        with_payload: &WithPayload,
+    ) -> Option<TheMap<PayloadKeyType, PayloadType>> {
+        if !with_payload.enable {
+            return None;
+        }
+        let payload = self.payload_storage.borrow().payload(internal_id);
+        Some(match &with_payload.payload_selector {
+            Some(selector) => selector.process(payload),
+            None => payload,
+        })
+    }
+
     pub fn save_current_state(&self) -> OperationResult<()> {
         self.save_state(&self.get_state())
     }
@@ -178,13 +275,15 @@ impl SegmentEntry for Segment {
     }
 
     fn point_version(&self, point_id: PointIdType) -> Option<SeqNumberType> {
-        self.id_tracker.borrow().version(point_id)
+        self.id_tracker.borrow().version(&point_id)
     }
 
     fn search(
         &self,
         vector: &[VectorElementType],
         with_payload: &WithPayload,
+        with_vector: bool,
+        filter: Option<&Filter>,
         top: usize,
         params: Option<&SearchParams>,
     ) -> OperationResult<Vec<ScoredPoint>> {
@@ -199,7 +298,7 @@ impl SegmentEntry for Segment {
         let internal_result = self
             .vector_index
             .borrow()
-            .search(vector, top, params);
+            .search(vector, filter, top, params);
 
         let id_tracker = self.id_tracker.borrow();
 
@@ -216,7 +315,7 @@ impl SegmentEntry for Segment {
                 )?;
                 let point_version =
                     id_tracker
-                        .version(point_id)
+                        .version(&point_id)
                         .ok_or(OperationError::ServiceError {
                             description: format!(
                                 "Corrupter id_tracker, no version for point {}",
@@ -226,20 +325,67 @@ impl SegmentEntry for Segment {
                 Ok(ScoredPoint {
                     id: point_id,
                     version: point_version,
-                    score: scored_point_offset.score
+                    score: scored_point_offset.score,
+                    payload: self.selected_payload(scored_point_offset.idx, with_payload),
+                    vector: if with_vector {
+                        self.vector_storage
+                            .borrow()
+                            .get_vector(scored_point_offset.idx)
+                    } else {
+                        None
+                    },
                 })
             })
             .collect();
         res
     }
 
+    fn facet(
+        &self,
+        key: PayloadKeyTypeRef,
+        filter: Option<&Filter>,
+        limit: usize,
+    ) -> OperationResult<Vec<FacetValueHit>> {
+        let payload_index = self.payload_index.borrow();
+        let counts = match payload_index.facet_counts(key, filter) {
+            Some(counts) => counts,
+            None => {
+                // Field is not indexed, read values of every matched point
+                let vector_storage = self.vector_storage.borrow();
+                let payload_storage = self.payload_storage.borrow();
+                let points = match filter {
+                    None => vector_storage.iter_ids(),
+                    Some(filter) => payload_index.query_points(filter),
+                };
+                let mut counts: HashMap<FacetValue, usize> = HashMap::new();
+                for point_id in points {
+                    if let Some(value) = payload_storage.payload(point_id).get(key) {
+                        for facet_value in facet_values(value) {
+                            *counts.entry(facet_value).or_insert(0) += 1;
+                        }
+                    }
+                }
+                counts
+            }
+        };
+
+        Ok(counts
+            .into_iter()
+            .sorted_by(|(value1, count1), (value2, count2)| {
+                count2.cmp(count1).then_with(|| value1.cmp(value2))
+            })
+            .take(limit)
+            .map(|(value, count)| FacetValueHit { value, count })
+            .collect())
+    }
+
     fn upsert_point(
         &mut self,
         op_num: SeqNumberType,
         point_id: PointIdType,
         vector: &[VectorElementType],
     ) -> OperationResult<bool> {
-        self.handle_version_and_failure(op_num, Some(point_id), |segment| {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
             let vector_dim = segment.vector_storage.borrow().vector_dim();
             if vector_dim != vector.len() {
                 return Err(OperationError::WrongVector {
@@ -253,17 +399,16 @@ impl SegmentEntry for Segment {
                 .preprocess(vector)
                 .unwrap_or_else(|| vector.to_owned());
 
-            let stored_internal_point = segment.id_tracker.borrow().internal_id(point_id);
+            let stored_internal_point = segment.id_tracker.borrow().internal_id(&point_id);
 
             let was_replaced = match stored_internal_point {
                 Some(existing_internal_id) => {
-                    let new_index =
-                        segment.update_vector(existing_internal_id, processed_vector)?;
-                    if new_index != existing_internal_id {
-                        let mut id_tracker = segment.id_tracker.borrow_mut();
-                        id_tracker.drop(point_id)?;
-                        id_tracker.set_link(point_id, new_index)?;
-                    }
+                    // Vectors are updated in place, so the point keeps its internal id
+                    segment.update_vector(existing_internal_id, processed_vector)?;
+                    segment
+                        .vector_index
+                        .borrow_mut()
+                        .update_vector(existing_internal_id)?;
                     true
                 }
                 None => {
@@ -274,7 +419,8 @@ impl SegmentEntry for Segment {
                     segment
                         .id_tracker
                         .borrow_mut()
-                        .set_link(point_id, new_index)?;
+                        .set_link(point_id.clone(), new_index)?;
+                    segment.vector_index.borrow_mut().update_vector(new_index)?;
                     false
                 }
             };
@@ -288,13 +434,16 @@ impl SegmentEntry for Segment {
         op_num: SeqNumberType,
         point_id: PointIdType,
     ) -> OperationResult<bool> {
-        self.handle_version_and_failure(op_num, Some(point_id), |segment| {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
             let mut id_tracker = segment.id_tracker.borrow_mut();
-            let internal_id = id_tracker.internal_id(point_id);
+            let internal_id = id_tracker.internal_id(&point_id);
             match internal_id {
                 Some(internal_id) => {
+                    segment.vector_index.borrow_mut().delete_vector(internal_id)?;
                     segment.vector_storage.borrow_mut().delete(internal_id)?;
-                    id_tracker.drop(point_id)?;
+                    segment.payload_storage.borrow_mut().drop(internal_id)?;
+                    segment.payload_index.borrow_mut().update_point(internal_id)?;
+                    id_tracker.drop(&point_id)?;
                     Ok(true)
                 }
                 None => Ok(false),
@@ -302,8 +451,223 @@ impl SegmentEntry for Segment {
         })
     }
 
+    fn upsert_points(
+        &mut self,
+        op_num: SeqNumberType,
+        points: &[(PointIdType, Vec<VectorElementType>)],
+    ) -> OperationResult<usize> {
+        let vector_dim = self.vector_storage.borrow().vector_dim();
+        if let Some((_, vector)) = points.iter().find(|(_, vector)| vector.len() != vector_dim) {
+            return Err(OperationError::WrongVector {
+                expected_dim: vector_dim,
+                received_dim: vector.len(),
+            });
+        }
+
+        // If the same point is listed several times, the last vector wins
+        let vectors: HashMap<PointIdType, &[VectorElementType]> = points
+            .iter()
+            .map(|(point_id, vector)| (point_id.clone(), vector.as_slice()))
+            .collect();
+        let point_ids = points
+            .iter()
+            .map(|(point_id, _)| point_id.clone())
+            .unique()
+            .collect();
+
+        self.handle_batch_version_and_failure(op_num, point_ids, |segment, point_ids| {
+            let metric = mertic_object(&segment.segment_config.distance);
+
+            let mut updated_vectors = vec![];
+            let mut inserted_points = vec![];
+            let mut inserted_vectors = vec![];
+            {
+                let id_tracker = segment.id_tracker.borrow();
+                for point_id in point_ids.iter().cloned() {
+                    let vector = vectors[&point_id];
+                    let processed_vector = metric
+                        .preprocess(vector)
+                        .unwrap_or_else(|| vector.to_owned());
+                    match id_tracker.internal_id(&point_id) {
+                        Some(internal_id) => {
+                            updated_vectors.push((internal_id, processed_vector));
+                        }
+                        None => {
+                            inserted_points.push(point_id);
+                            inserted_vectors.push(processed_vector);
+                        }
+                    }
+                }
+            }
+
+            // Vectors are updated in place, so the points keep their internal ids
+            let mut changed_indexes = segment
+                .vector_storage
+                .borrow_mut()
+                .update_vectors(updated_vectors)?;
+
+            let new_indexes = segment
+                .vector_storage
+                .borrow_mut()
+                .put_vectors(inserted_vectors)?;
+            changed_indexes.extend(new_indexes.clone());
+            let links: Vec<_> = inserted_points.into_iter().zip(new_indexes).collect();
+            segment.id_tracker.borrow_mut().set_links(&links)?;
+
+            let mut vector_index = segment.vector_index.borrow_mut();
+            for internal_id in changed_indexes {
+                vector_index.update_vector(internal_id)?;
+            }
+
+            Ok(point_ids.len())
+        })
+    }
+
+    fn delete_points(
+        &mut self,
+        op_num: SeqNumberType,
+        point_ids: &[PointIdType],
+    ) -> OperationResult<usize> {
+        let point_ids = point_ids.iter().cloned().unique().collect();
+
+        self.handle_batch_version_and_failure(op_num, point_ids, |segment, point_ids| {
+            let (stored_points, internal_ids): (Vec<_>, Vec<_>) = {
+                let id_tracker = segment.id_tracker.borrow();
+                point_ids
+                    .iter()
+                    .filter_map(|point_id| {
+                        id_tracker
+                            .internal_id(point_id)
+                            .map(|internal_id| (point_id.clone(), internal_id))
+                    })
+                    .unzip()
+            };
+
+            {
+                let mut vector_index = segment.vector_index.borrow_mut();
+                for internal_id in internal_ids.iter().cloned() {
+                    vector_index.delete_vector(internal_id)?;
+                }
+            }
+            segment
+                .vector_storage
+                .borrow_mut()
+                .delete_vectors(&internal_ids)?;
+            {
+                let mut payload_storage = segment.payload_storage.borrow_mut();
+                for internal_id in internal_ids.iter().cloned() {
+                    payload_storage.drop(internal_id)?;
+                }
+            }
+            {
+                let mut payload_index = segment.payload_index.borrow_mut();
+                for internal_id in internal_ids.iter().cloned() {
+                    payload_index.update_point(internal_id)?;
+                }
+            }
+            segment.id_tracker.borrow_mut().drop_many(&stored_points)?;
+
+            Ok(stored_points.len())
+        })
+    }
+
+    fn delete_by_filter(
+        &mut self,
+        op_num: SeqNumberType,
+        filter: &Filter,
+    ) -> OperationResult<usize> {
+        let point_ids: Vec<PointIdType> = {
+            let payload_index = self.payload_index.borrow();
+            let id_tracker = self.id_tracker.borrow();
+            payload_index
+                .query_points(filter)
+                .filter_map(|internal_id| id_tracker.external_id(internal_id))
+                .collect()
+        };
+
+        // Each point is deleted with its own version check
+        self.delete_points(op_num, &point_ids)
+    }
+
+    fn set_full_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        full_payload: TheMap<PayloadKeyType, PayloadType>,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
+            let internal_id = segment.lookup_internal_id(&point_id)?;
+            segment
+                .payload_storage
+                .borrow_mut()
+                .assign_all(internal_id, full_payload)?;
+            segment
+                .payload_index
+                .borrow_mut()
+                .update_point(internal_id)?;
+            Ok(true)
+        })
+    }
+
+    fn set_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        key: PayloadKeyTypeRef,
+        payload: PayloadType,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
+            let internal_id = segment.lookup_internal_id(&point_id)?;
+            segment
+                .payload_storage
+                .borrow_mut()
+                .assign(internal_id, key, payload)?;
+            segment
+                .payload_index
+                .borrow_mut()
+                .update_point(internal_id)?;
+            Ok(true)
+        })
+    }
+
+    fn delete_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
+            let internal_id = segment.lookup_internal_id(&point_id)?;
+            segment
+                .payload_storage
+                .borrow_mut()
+                .delete(internal_id, key)?;
+            segment
+                .payload_index
+                .borrow_mut()
+                .update_point(internal_id)?;
+            Ok(true)
+        })
+    }
+
+    fn clear_payload(
+        &mut self,
+        op_num: SeqNumberType,
+        point_id: PointIdType,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, Some(&point_id), |segment| {
+            let internal_id = segment.lookup_internal_id(&point_id)?;
+            segment.payload_storage.borrow_mut().drop(internal_id)?;
+            segment
+                .payload_index
+                .borrow_mut()
+                .update_point(internal_id)?;
+            Ok(true)
+        })
+    }
+
     fn vector(&self, point_id: PointIdType) -> OperationResult<Vec<VectorElementType>> {
-        let internal_id = self.lookup_internal_id(point_id)?;
+        let internal_id = self.lookup_internal_id(&point_id)?;
         Ok(self
             .vector_storage
             .borrow()
@@ -311,6 +675,14 @@ impl SegmentEntry for Segment {
             .unwrap())
     }
 
+    fn payload(
+        &self,
+        point_id: PointIdType,
+    ) -> OperationResult<TheMap<PayloadKeyType, PayloadType>> {
+        let internal_id = self.lookup_internal_id(&point_id)?;
+        Ok(self.payload_storage.borrow().payload(internal_id))
+    }
+
     fn iter_points(&self) -> Box<dyn Iterator<Item = PointIdType> + '_> {
         // Sorry for that, but I didn't find any way easier.
         // If you try simply return iterator - it won't work because AtomicRef should exist
@@ -319,23 +691,54 @@ impl SegmentEntry for Segment {
         unsafe { self.id_tracker.as_ptr().as_ref().unwrap().iter_external() }
     }
 
-    fn read_filtered<'a>(
-        &'a self,
+    fn read_filtered(
+        &self,
         offset: PointIdType,
         limit: usize,
-    ) -> Vec<PointIdType> {
-        let storage = self.vector_storage.borrow();
-        self
-            .id_tracker
-            .borrow()
+        filter: Option<&Filter>,
+        with_payload: &WithPayload,
+        with_vector: bool,
+    ) -> OperationResult<ScrollResult> {
+        let id_tracker = self.id_tracker.borrow();
+        let vector_storage = self.vector_storage.borrow();
+
+        // Take one extra point to find out the offset of the next page
+        let mut page: Vec<_> = id_tracker
             .iter_from(offset)
-            .map(|x| x.0)
-            .take(limit)
-            .collect()
+            .filter(|(_, internal_id)| match filter {
+                None => true,
+                Some(filter) => self.condition_checker.check(*internal_id, filter),
+            })
+            .take(limit + 1)
+            .collect();
+
+        let next_page_offset = if page.len() > limit {
+            page.pop().map(|(external_id, _)| external_id)
+        } else {
+            None
+        };
+
+        let points = page
+            .into_iter()
+            .map(|(external_id, internal_id)| Record {
+                id: external_id,
+                payload: self.selected_payload(internal_id, with_payload),
+                vector: if with_vector {
+                    vector_storage.get_vector(internal_id)
+                } else {
+                    None
+                },
+            })
+            .collect();
+
+        Ok(ScrollResult {
+            points,
+            next_page_offset,
+        })
     }
 
     fn has_point(&self, point_id: PointIdType) -> bool {
-        self.id_tracker.borrow().internal_id(point_id).is_some()
+        self.id_tracker.borrow().internal_id(&point_id).is_some()
     }
 
     fn vectors_count(&self) -> usize {
@@ -346,11 +749,45 @@ impl SegmentEntry for Segment {
         self.vector_storage.borrow().deleted_count()
     }
 
+    fn count(&self, filter: Option<&Filter>, exact: bool) -> OperationResult<usize> {
+        let filter = match filter {
+            None => return Ok(self.vectors_count()),
+            Some(filter) => filter,
+        };
+        let payload_index = self.payload_index.borrow();
+        if exact {
+            return Ok(payload_index.query_points(filter).count());
+        }
+
+        let estimation = payload_index.estimate_cardinality(filter);
+        if estimation.min == estimation.max {
+            return Ok(estimation.min);
+        }
+        let vector_storage = self.vector_storage.borrow();
+        let sampled = sample_estimate_cardinality(
+            vector_storage.sample_ids(),
+            |idx| self.condition_checker.check(idx, filter),
+            vector_storage.vector_count(),
+        );
+        Ok(min(max(sampled, estimation.min), estimation.max))
+    }
+
     fn segment_type(&self) -> SegmentType {
         self.segment_type
     }
 
     fn info(&self) -> SegmentInfo {
+        let indexed_fields = self.get_indexed_fields();
+        let schema = self
+            .payload_storage
+            .borrow()
+            .schema()
+            .into_iter()
+            .map(|(key, data_type)| {
+                let indexed = indexed_fields.contains(&key);
+                (key, PayloadSchemaInfo { data_type, indexed })
+            })
+            .collect();
 
         SegmentInfo {
             segment_type: self.segment_type,
@@ -359,6 +796,7 @@ impl SegmentEntry for Segment {
             ram_usage_bytes: 0,  // ToDo: Implement
             disk_usage_bytes: 0, // ToDo: Implement
             is_appendable: self.appendable_flag,
+            schema,
         }
     }
 
@@ -380,6 +818,9 @@ impl SegmentEntry for Segment {
 
         self.id_tracker.borrow().flush()?;
         self.vector_storage.borrow().flush()?;
+        self.payload_storage.borrow().flush()?;
+        self.payload_index.borrow().flush()?;
+        self.vector_index.borrow().flush()?;
         self.save_state(&state)?;
 
         *persisted_version = state.version;
@@ -394,6 +835,38 @@ impl SegmentEntry for Segment {
         Ok(remove_dir_all(&deleted_path)?)
     }
 
+    fn create_field_index(
+        &mut self,
+        op_num: SeqNumberType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, None, |segment| {
+            segment
+                .payload_index
+                .borrow_mut()
+                .set_indexed(&key.to_owned())?;
+            Ok(true)
+        })
+    }
+
+    fn delete_field_index(
+        &mut self,
+        op_num: SeqNumberType,
+        key: PayloadKeyTypeRef,
+    ) -> OperationResult<bool> {
+        self.handle_version_and_failure(op_num, None, |segment| {
+            segment
+                .payload_index
+                .borrow_mut()
+                .drop_index(&key.to_owned())?;
+            Ok(true)
+        })
+    }
+
+    fn get_indexed_fields(&self) -> Vec<PayloadKeyType> {
+        self.payload_index.borrow().indexed_fields()
+    }
+
     fn check_error(&self) -> Option<SegmentFailedState> {
         self.error_status.clone()
     }
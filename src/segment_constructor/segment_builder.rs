@@ -42,6 +42,8 @@ impl SegmentBuilder {
     /// Update current segment builder with all (not deleted) vectors and payload form `other` segment
     /// Perform index building at the end of update
     pub fn update_from(&mut self, other: &Segment) -> OperationResult<()> {
+        self.indexed_fields.extend(other.get_indexed_fields());
+
         match &mut self.segment {
             None => Err(OperationError::ServiceError {
                 description: "Segment building error: created segment not found".to_owned(),
@@ -51,9 +53,11 @@ impl SegmentBuilder {
 
                 let other_id_tracker = other.id_tracker.borrow();
                 let other_vector_storage = other.vector_storage.borrow();
+                let other_payload_storage = other.payload_storage.borrow();
 
                 let mut id_tracker = self_segment.id_tracker.borrow_mut();
                 let mut vector_storage = self_segment.vector_storage.borrow_mut();
+                let mut payload_storage = self_segment.payload_storage.borrow_mut();
 
                 let new_internal_range = vector_storage.update_from(&*other_vector_storage)?;
 
@@ -61,23 +65,32 @@ impl SegmentBuilder {
                     new_internal_range.zip(other_vector_storage.iter_ids())
                 {
                     let external_id = other_id_tracker.external_id(old_internal_id).unwrap();
-                    let other_version = other_id_tracker.version(external_id).unwrap();
+                    let other_version = other_id_tracker.version(&external_id).unwrap();
 
-                    match id_tracker.version(external_id) {
+                    match id_tracker.version(&external_id) {
                         None => {
                             // New point, just insert
-                            id_tracker.set_link(external_id, new_internal_id)?;
+                            id_tracker.set_link(external_id.clone(), new_internal_id)?;
                             id_tracker.set_version(external_id, other_version)?;
+                            payload_storage.assign_all(
+                                new_internal_id,
+                                other_payload_storage.payload(old_internal_id),
+                            )?;
                         }
                         Some(existing_version) => {
                             if existing_version < other_version {
                                 // Other version is the newest, remove the existing one and replace
                                 let existing_internal_id =
-                                    id_tracker.internal_id(external_id).unwrap();
+                                    id_tracker.internal_id(&external_id).unwrap();
                                 vector_storage.delete(existing_internal_id)?;
-                                id_tracker.drop(external_id)?;
-                                id_tracker.set_link(external_id, new_internal_id)?;
+                                payload_storage.drop(existing_internal_id)?;
+                                id_tracker.drop(&external_id)?;
+                                id_tracker.set_link(external_id.clone(), new_internal_id)?;
                                 id_tracker.set_version(external_id, other_version)?;
+                                payload_storage.assign_all(
+                                    new_internal_id,
+                                    other_payload_storage.payload(old_internal_id),
+                                )?;
                             } else {
                                 // Old version is still good, do not move anything else
                                 // Mark newly added vector as removed
@@ -103,6 +116,10 @@ impl TryInto<Segment> for SegmentBuilder {
             })?;
             self.segment = None;
 
+            for field in self.indexed_fields.iter() {
+                segment.payload_index.borrow_mut().set_indexed(field)?;
+            }
+
             segment.vector_index.borrow_mut().build_index()?;
 
             segment.flush()?;
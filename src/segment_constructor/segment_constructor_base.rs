@@ -1,14 +1,21 @@
 use crate::entry::entry_point::{OperationError, OperationResult};
 use crate::id_tracker::simple_id_tracker::SimpleIdTracker;
+use crate::id_tracker::IdTracker;
 use crate::index::hnsw_index::hnsw::HNSWIndex;
 use crate::index::plain_index::PlainIndex;
-use crate::index::{VectorIndex};
+use crate::index::plain_payload_index::PlainPayloadIndex;
+use crate::index::struct_payload_index::StructPayloadIndex;
+use crate::index::{PayloadIndex, VectorIndex};
+use crate::payload_storage::query_checker::SimpleConditionChecker;
+use crate::payload_storage::simple_payload_storage::SimplePayloadStorage;
+use crate::payload_storage::{ConditionChecker, PayloadStorage};
 use crate::segment::{Segment, SEGMENT_STATE_FILE};
 use crate::types::{
     Indexes, PayloadIndexType, SegmentConfig, SegmentState, SegmentType, SeqNumberType, StorageType,
 };
 use crate::vector_storage::drive_vector_storage::DriveVectorStorage;
 use crate::vector_storage::memmap_vector_storage::MemmapVectorStorage;
+use crate::vector_storage::quantized_vector_storage::QuantizedVectorStorage;
 use crate::vector_storage::simple_vector_storage::SimpleVectorStorage;
 use crate::vector_storage::VectorStorage;
 use atomic_refcell::AtomicRefCell;
@@ -22,6 +29,23 @@ fn sp<T>(t: T) -> Arc<AtomicRefCell<T>> {
     Arc::new(AtomicRefCell::new(t))
 }
 
+/// Wrap vector storage with quantized copy of vectors, if it is enabled in config
+fn vector_storage_sp<S: VectorStorage + 'static>(
+    path: &Path,
+    storage: S,
+    config: &SegmentConfig,
+) -> OperationResult<Arc<AtomicRefCell<dyn VectorStorage>>> {
+    Ok(match config.quantization {
+        None => sp(storage),
+        Some(quantization) => sp(QuantizedVectorStorage::open(
+            path,
+            storage,
+            config.distance,
+            quantization,
+        )?),
+    })
+}
+
 fn create_segment(
     version: SeqNumberType,
     segment_path: &Path,
@@ -30,9 +54,19 @@ fn create_segment(
 ) -> OperationResult<Segment> {
     let tracker_path = segment_path.join("id_tracker");
     let vector_storage_path = segment_path.join("vector_storage");
+    let payload_storage_path = segment_path.join("payload_storage");
+    let payload_index_path = segment_path.join("payload_index");
     let vector_index_path = segment_path.join("vector_index");
 
-    let id_tracker = sp(SimpleIdTracker::open(&tracker_path)?);
+    if config.quantization.is_some() && config.storage_type == StorageType::InMemory {
+        // Quantization is meant to keep original vectors out of RAM
+        return Err(OperationError::ValidationError {
+            description: "Quantization requires Mmap or Drive storage type".to_owned(),
+        });
+    }
+
+    let id_tracker: Arc<AtomicRefCell<dyn IdTracker>> =
+        sp(SimpleIdTracker::open(&tracker_path)?);
 
     let vector_storage: Arc<AtomicRefCell<dyn VectorStorage>> = match config.storage_type {
         StorageType::InMemory => sp(SimpleVectorStorage::open(
@@ -41,26 +75,59 @@ fn create_segment(
             config.distance,
             read_only
         )?),
-        StorageType::Mmap => sp(MemmapVectorStorage::open(
+        StorageType::Mmap => vector_storage_sp(
             &vector_storage_path,
-            config.vector_size,
-            config.distance,
-        )?),
-        StorageType::Drive => sp(DriveVectorStorage::open(
-            &vector_storage_path, 
-            config.vector_size, 
-            config.distance, 
-            read_only
-        )?)
+            MemmapVectorStorage::open(&vector_storage_path, config.vector_size, config.distance)?,
+            config,
+        )?,
+        StorageType::Drive => vector_storage_sp(
+            &vector_storage_path,
+            DriveVectorStorage::open(
+                &vector_storage_path,
+                config.vector_size,
+                config.distance,
+                read_only,
+            )?,
+            config,
+        )?,
     };
 
+    let simple_payload_storage =
+        sp(SimplePayloadStorage::open(&payload_storage_path, read_only)?);
+
+    let condition_checker: Arc<dyn ConditionChecker> = Arc::new(SimpleConditionChecker::new(
+        simple_payload_storage.clone(),
+        id_tracker.clone(),
+    ));
+
+    let payload_storage: Arc<AtomicRefCell<dyn PayloadStorage>> = simple_payload_storage;
+
+    let payload_index: Arc<AtomicRefCell<dyn PayloadIndex>> =
+        match config.payload_index.unwrap_or_default() {
+            PayloadIndexType::Plain => sp(PlainPayloadIndex::open(
+                condition_checker.clone(),
+                vector_storage.clone(),
+                &payload_index_path,
+            )?),
+            PayloadIndexType::Struct => sp(StructPayloadIndex::open(
+                condition_checker.clone(),
+                vector_storage.clone(),
+                payload_storage.clone(),
+                id_tracker.clone(),
+                &payload_index_path,
+            )?),
+        };
+
     let vector_index: Arc<AtomicRefCell<dyn VectorIndex>> = match config.index {
         Indexes::Plain { .. } => sp(PlainIndex::new(
             vector_storage.clone(),
+            payload_index.clone(),
         )),
         Indexes::Hnsw(hnsw_config) => sp(HNSWIndex::open(
             &vector_index_path,
             vector_storage.clone(),
+            condition_checker.clone(),
+            payload_index.clone(),
             hnsw_config,
         )?),
     };
@@ -70,8 +137,8 @@ fn create_segment(
         Indexes::Hnsw { .. } => SegmentType::Indexed,
     };
 
-    let appendable_flag =
-        segment_type == SegmentType::Plain {} && config.storage_type == StorageType::InMemory;
+    // HNSW graph is able to link new points, so only storage matters
+    let appendable_flag = config.storage_type == StorageType::InMemory;
 
     Ok(Segment {
         version,
@@ -79,7 +146,10 @@ fn create_segment(
         current_path: segment_path.to_owned(),
         id_tracker,
         vector_storage,
+        payload_storage,
+        payload_index,
         vector_index,
+        condition_checker,
         appendable_flag,
         segment_type,
         segment_config: config.clone(),
@@ -26,6 +26,7 @@ pub fn build_simple_segment(
             payload_index: None,
             distance,
             storage_type: StorageType::Drive,
+            quantization: None,
         },
         read_only
     )
@@ -58,7 +59,7 @@ mod tests {
         let vec4 = vec![1.0, 1.0, 0.0, 1.0];
         let vec5 = vec![1.0, 0.0, 0.0, 0.0];
 
-        match segment.upsert_point(1, 120, &wrong_vec) {
+        match segment.upsert_point(1, 120.into(), &wrong_vec) {
             Err(err) => match err {
                 OperationError::WrongVector { .. } => (),
                 _ => assert!(false, "Wrong error"),
@@ -66,22 +67,22 @@ mod tests {
             Ok(_) => assert!(false, "Operation with wrong vector should fail"),
         };
 
-        segment.upsert_point(2, 1, &vec1).unwrap();
-        segment.upsert_point(2, 2, &vec2).unwrap();
-        segment.upsert_point(2, 3, &vec3).unwrap();
-        segment.upsert_point(2, 4, &vec4).unwrap();
-        segment.upsert_point(2, 5, &vec5).unwrap();
+        segment.upsert_point(2, 1.into(), &vec1).unwrap();
+        segment.upsert_point(2, 2.into(), &vec2).unwrap();
+        segment.upsert_point(2, 3.into(), &vec3).unwrap();
+        segment.upsert_point(2, 4.into(), &vec4).unwrap();
+        segment.upsert_point(2, 5.into(), &vec5).unwrap();
 
         // Replace vectors
-        segment.upsert_point(4, 1, &vec1).unwrap();
-        segment.upsert_point(5, 2, &vec2).unwrap();
-        segment.upsert_point(6, 3, &vec3).unwrap();
-        segment.upsert_point(7, 4, &vec4).unwrap();
-        segment.upsert_point(8, 5, &vec5).unwrap();
+        segment.upsert_point(4, 1.into(), &vec1).unwrap();
+        segment.upsert_point(5, 2.into(), &vec2).unwrap();
+        segment.upsert_point(6, 3.into(), &vec3).unwrap();
+        segment.upsert_point(7, 4.into(), &vec4).unwrap();
+        segment.upsert_point(8, 5.into(), &vec5).unwrap();
 
         assert_eq!(segment.version(), 8);
 
-        let declined = segment.upsert_point(3, 5, &vec5).unwrap();
+        let declined = segment.upsert_point(3, 5.into(), &vec5).unwrap();
         // Should not be processed due to operation number
         assert!(!declined);
     }
@@ -1,7 +1,7 @@
 use crate::types::{Distance, ScoreType, VectorElementType};
 use ndarray::Array1;
 
-pub trait Metric {
+pub trait Metric: Send + Sync {
     fn distance(&self) -> Distance;
 
     /// Greater the value - closer the vectors
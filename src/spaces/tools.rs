@@ -57,6 +57,15 @@ impl<T: Ord> FixedLengthPriorityQueue<T> {
         self.heap.peek().map(|x| &x.0)
     }
 
+    /// Keep only elements, which satisfy the predicate
+    pub fn retain<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let heap = std::mem::take(&mut self.heap);
+        self.heap = heap.into_iter().filter(|x| predicate(&x.0)).collect();
+    }
+
     /// Returns actual length of the queue
     pub fn len(&self) -> usize {
         self.heap.len()
@@ -1,10 +1,15 @@
+use crate::payload_storage::json_payload::is_nested_key;
+use chrono::{DateTime, Utc};
 use ordered_float::OrderedFloat;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use uuid::Uuid;
 
-pub type PointIdType = u64;
+/// Type of point id, defined by user
+pub type PointIdType = ExtendedPointId;
 /// Type of point index across all segments
 pub type PointOffsetType = u32;
 /// Type of point index inside a segment
@@ -21,6 +26,8 @@ pub type VectorElementType = f32;
 pub type FloatPayloadType = f64;
 /// Type of integer point payload
 pub type IntPayloadType = i64;
+/// Type of datetime point payload: unix timestamp in milliseconds
+pub type DateTimePayloadType = i64;
 
 /// Type of internal tags, build from payload
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, FromPrimitive)]
@@ -34,6 +41,51 @@ pub enum Distance {
     Dot,
 }
 
+/// External point id: a number, an UUID or an arbitrary string
+#[derive(
+    Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+#[serde(untagged)]
+pub enum ExtendedPointId {
+    NumId(u64),
+    Uuid(Uuid),
+    String(String),
+}
+
+impl fmt::Display for ExtendedPointId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtendedPointId::NumId(id) => id.fmt(f),
+            ExtendedPointId::Uuid(id) => id.fmt(f),
+            ExtendedPointId::String(id) => id.fmt(f),
+        }
+    }
+}
+
+impl From<u64> for ExtendedPointId {
+    fn from(id: u64) -> Self {
+        ExtendedPointId::NumId(id)
+    }
+}
+
+impl From<Uuid> for ExtendedPointId {
+    fn from(id: Uuid) -> Self {
+        ExtendedPointId::Uuid(id)
+    }
+}
+
+impl From<String> for ExtendedPointId {
+    fn from(id: String) -> Self {
+        ExtendedPointId::String(id)
+    }
+}
+
+impl From<&str> for ExtendedPointId {
+    fn from(id: &str) -> Self {
+        ExtendedPointId::String(id.to_string())
+    }
+}
+
 pub enum Order {
     LargeBetter,
     SmallBetter,
@@ -47,7 +99,45 @@ pub struct ScoredPoint {
     pub version: SeqNumberType,
     /// Points vector distance to the query vector
     pub score: ScoreType,
+    /// Payload of the point, if requested
+    pub payload: Option<TheMap<PayloadKeyType, PayloadType>>,
+    /// Vector of the point, if requested
+    pub vector: Option<Vec<VectorElementType>>,
+}
+
+/// Point data, returned by paginated reading
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+pub struct Record {
+    /// Point id
+    pub id: PointIdType,
+    /// Payload of the point, if requested
+    pub payload: Option<TheMap<PayloadKeyType, PayloadType>>,
+    /// Vector of the point, if requested
+    pub vector: Option<Vec<VectorElementType>>,
+}
+
+/// Single page of points, which satisfy the filter
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+pub struct ScrollResult {
+    /// Points of the page, ordered by id
+    pub points: Vec<Record>,
+    /// Offset of the next page, `None` if there are no more points
+    pub next_page_offset: Option<PointIdType>,
+}
+
+/// Value of a payload field, which could be used for faceting
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum FacetValue {
+    Keyword(String),
+    Integer(IntPayloadType),
+}
 
+/// Value of a payload field together with the amount of points, which have this value
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct FacetValueHit {
+    pub value: FacetValue,
+    pub count: usize,
 }
 
 impl Eq for ScoredPoint {}
@@ -66,7 +156,7 @@ impl PartialOrd for ScoredPoint {
 
 impl PartialEq for ScoredPoint {
     fn eq(&self, other: &Self) -> bool {
-        (self.id, &self.score) == (other.id, &other.score)
+        (&self.id, &self.score) == (&other.id, &other.score)
     }
 }
 
@@ -97,6 +187,8 @@ pub struct SegmentInfo {
     pub ram_usage_bytes: usize,
     pub disk_usage_bytes: usize,
     pub is_appendable: bool,
+    /// Type and index status of each payload field stored in the segment
+    pub schema: TheMap<PayloadKeyType, PayloadSchemaInfo>,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq)]
@@ -140,6 +232,13 @@ pub struct HnswConfig {
     /// If payload chunk is smaller than `full_scan_threshold` additional indexing won't be used -
     /// in this case full-scan search should be preferred by query planner and additional indexing is not required.
     pub full_scan_threshold: usize,
+    /// Number of parallel threads used for the index building. If 0 - use all available CPUs.
+    #[serde(default)]
+    pub max_indexing_threads: usize,
+    /// Store links of the built graph as sorted delta-encoded lists. Reduces memory usage of the graph,
+    /// but links are decoded on each search step. Changed graph is unpacked back into plain lists.
+    #[serde(default)]
+    pub compress_links: bool,
 }
 
 impl Default for HnswConfig {
@@ -148,6 +247,8 @@ impl Default for HnswConfig {
             m: 16,
             ef_construct: 100,
             full_scan_threshold: DEFAULT_FULL_SCAN_THRESHOLD,
+            max_indexing_threads: 0,
+            compress_links: false,
         }
     }
 }
@@ -213,11 +314,82 @@ pub struct SegmentConfig {
     pub payload_index: Option<PayloadIndexType>,
     /// Type of vector storage
     pub storage_type: StorageType,
+    /// Keep compressed copy of vectors in memory and use it for the index search.
+    /// Original vectors stay in the `storage_type` storage, which should be `Mmap` or `Drive`
+    #[serde(default)]
+    pub quantization: Option<QuantizationConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type", content = "options")]
+/// Type of vectors compression
+pub enum QuantizationConfig {
+    /// Map each vector component into `u8` range, requires 4 times less memory
+    Scalar(ScalarQuantizationConfig),
+    /// Replace each part of the vector with the id of the closest k-means centroid.
+    /// Requires `4 * subvector_size` times less memory
+    Product(ProductQuantizationConfig),
+    /// Keep a single bit of each vector component, requires 32 times less memory.
+    /// Candidates are selected by Hamming distance and always rescored with original vectors
+    Binary(BinaryQuantizationConfig),
+}
+
+impl QuantizationConfig {
+    pub fn rescore(&self) -> bool {
+        match self {
+            QuantizationConfig::Scalar(config) => config.rescore,
+            QuantizationConfig::Product(config) => config.rescore,
+            QuantizationConfig::Binary(_) => true,
+        }
+    }
+
+    /// Number of candidates per requested result, which are rescored with original vectors
+    pub fn oversampling(&self) -> usize {
+        match self {
+            QuantizationConfig::Binary(config) if config.oversampling > 0 => config.oversampling,
+            _ => DEFAULT_RESCORE_OVERSAMPLING,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct ScalarQuantizationConfig {
+    /// Calibrate value range of each dimension separately.
+    /// Otherwise a single range is used for all dimensions
+    #[serde(default)]
+    pub per_dimension: bool,
+    /// Rescore top candidates of the index search with original vectors
+    #[serde(default)]
+    pub rescore: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ProductQuantizationConfig {
+    /// Number of dimensions, encoded with a single byte. Last subvector could be shorter
+    pub subvector_size: usize,
+    /// Rescore top candidates of the search with original vectors
+    #[serde(default)]
+    pub rescore: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct BinaryQuantizationConfig {
+    /// Number of candidates per requested result, which are rescored with original vectors.
+    /// If 0 - use default
+    #[serde(default)]
+    pub oversampling: usize,
 }
 
 /// Default value based on https://github.com/google-research/google-research/blob/master/scann/docs/algorithms.md
 pub const DEFAULT_FULL_SCAN_THRESHOLD: usize = 20_000;
 
+/// Number of candidates per requested result, selected with quantized vectors for rescoring
+pub const DEFAULT_RESCORE_OVERSAMPLING: usize = 4;
+
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct SegmentState {
@@ -240,6 +412,49 @@ pub enum PayloadType {
     Integer(Vec<IntPayloadType>),
     Float(Vec<FloatPayloadType>),
     Geo(Vec<GeoPoint>),
+    Datetime(Vec<DateTimePayloadType>),
+    Bool(Vec<bool>),
+}
+
+impl PayloadType {
+    /// Number of values stored in the field
+    pub fn len(&self) -> usize {
+        match self {
+            PayloadType::Keyword(x) => x.len(),
+            PayloadType::Integer(x) => x.len(),
+            PayloadType::Float(x) => x.len(),
+            PayloadType::Geo(x) => x.len(),
+            PayloadType::Datetime(x) => x.len(),
+            PayloadType::Bool(x) => x.len(),
+        }
+    }
+
+    /// Field without values is considered to be `null`.
+    /// Such fields do not define a type of the field in the payload schema
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert values into the type of the field, if it is possible without losing information:
+    /// integers are accepted by float fields and RFC3339 strings by datetime fields
+    pub fn into_schema_type(self, schema_type: PayloadSchemaType) -> Option<PayloadType> {
+        match (self, schema_type) {
+            (value, schema_type) if PayloadSchemaType::from(&value) == schema_type => Some(value),
+            (PayloadType::Integer(values), PayloadSchemaType::Float) => Some(PayloadType::Float(
+                values.into_iter().map(|x| x as FloatPayloadType).collect(),
+            )),
+            (PayloadType::Keyword(values), PayloadSchemaType::Datetime) => values
+                .iter()
+                .map(|x| {
+                    x.parse::<DateTime<Utc>>()
+                        .ok()
+                        .map(|x| datetime_timestamp(&x))
+                })
+                .collect::<Option<_>>()
+                .map(PayloadType::Datetime),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq)]
@@ -250,6 +465,8 @@ pub enum PayloadSchemaType {
     Integer,
     Float,
     Geo,
+    Datetime,
+    Bool,
 }
 
 impl From<&PayloadType> for PayloadSchemaType {
@@ -259,6 +476,8 @@ impl From<&PayloadType> for PayloadSchemaType {
             PayloadType::Integer(_) => PayloadSchemaType::Integer,
             PayloadType::Float(_) => PayloadSchemaType::Float,
             PayloadType::Geo(_) => PayloadSchemaType::Geo,
+            PayloadType::Datetime(_) => PayloadSchemaType::Datetime,
+            PayloadType::Bool(_) => PayloadSchemaType::Bool,
         }
     }
 }
@@ -287,6 +506,7 @@ pub enum PayloadInterface {
     KeywordShortcut(PayloadVariant<String>),
     IntShortcut(PayloadVariant<i64>),
     FloatShortcut(PayloadVariant<f64>),
+    BoolShortcut(PayloadVariant<bool>),
     Payload(PayloadInterfaceStrict),
 }
 
@@ -298,6 +518,9 @@ pub enum PayloadInterfaceStrict {
     Integer(PayloadVariant<i64>),
     Float(PayloadVariant<f64>),
     Geo(PayloadVariant<GeoPoint>),
+    /// Datetime in RFC3339 format, e.g. `2021-06-01T12:00:00Z`
+    Datetime(PayloadVariant<DateTime<Utc>>),
+    Bool(PayloadVariant<bool>),
 }
 
 // For tests
@@ -314,6 +537,10 @@ impl From<&PayloadInterfaceStrict> for PayloadType {
             PayloadInterfaceStrict::Integer(x) => PayloadType::Integer(x.to_list()),
             PayloadInterfaceStrict::Float(x) => PayloadType::Float(x.to_list()),
             PayloadInterfaceStrict::Geo(x) => PayloadType::Geo(x.to_list()),
+            PayloadInterfaceStrict::Datetime(x) => PayloadType::Datetime(
+                x.to_list().iter().map(datetime_timestamp).collect(),
+            ),
+            PayloadInterfaceStrict::Bool(x) => PayloadType::Bool(x.to_list()),
         }
     }
 }
@@ -325,33 +552,68 @@ impl From<&PayloadInterface> for PayloadType {
             PayloadInterface::KeywordShortcut(x) => PayloadType::Keyword(x.to_list()),
             PayloadInterface::FloatShortcut(x) => PayloadType::Float(x.to_list()),
             PayloadInterface::IntShortcut(x) => PayloadType::Integer(x.to_list()),
+            PayloadInterface::BoolShortcut(x) => PayloadType::Bool(x.to_list()),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct Match {
     /// Keyword value to match
     pub keyword: Option<String>,
     /// Integer value to match
     pub integer: Option<IntPayloadType>,
+    /// Full-text match: all words of the text should be present in the keyword value.
+    /// Comparison is case-insensitive
+    pub text: Option<String>,
+    /// Boolean value to match
+    pub boolean: Option<bool>,
+}
+
+/// Timestamp of the datetime, stored in `Datetime` payload
+pub fn datetime_timestamp(datetime: &DateTime<Utc>) -> DateTimePayloadType {
+    datetime.timestamp_millis()
+}
+
+/// Range bound, given either as a number or as a RFC3339 datetime
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RangeBound {
+    Number(FloatPayloadType),
+    Datetime(DateTime<Utc>),
+}
+
+/// Datetime bounds are converted into timestamps, comparable with `Datetime` payload
+fn deserialize_range_bound<'de, D>(deserializer: D) -> Result<Option<FloatPayloadType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let bound: Option<RangeBound> = Option::deserialize(deserializer)?;
+    Ok(bound.map(|bound| match bound {
+        RangeBound::Number(number) => number,
+        RangeBound::Datetime(datetime) => datetime_timestamp(&datetime) as FloatPayloadType,
+    }))
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Copy, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct Range {
     /// point.key < range.lt
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
     pub lt: Option<FloatPayloadType>,
     /// point.key > range.gt
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
     pub gt: Option<FloatPayloadType>,
     /// point.key >= range.gte
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
     pub gte: Option<FloatPayloadType>,
     /// point.key <= range.lte
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
     pub lte: Option<FloatPayloadType>,
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct GeoBoundingBox {
     /// Coordinates of the top left point of the area rectangle
@@ -360,7 +622,7 @@ pub struct GeoBoundingBox {
     pub bottom_right: GeoPoint,
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct GeoRadius {
     /// Coordinates of the top left point of the area rectangle
@@ -369,7 +631,7 @@ pub struct GeoRadius {
     pub radius: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct FieldCondition {
     pub key: PayloadKeyType,
@@ -394,6 +656,27 @@ impl From<HashSet<PointIdType>> for HasIdCondition {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct PayloadField {
+    /// Payload field name
+    pub key: PayloadKeyType,
+}
+
+/// Select points without values of the field: field is absent or `null`
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct IsEmptyCondition {
+    pub is_empty: PayloadField,
+}
+
+/// Select points where the field is present, but is `null`
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct IsNullCondition {
+    pub is_null: PayloadField,
+}
+
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(untagged)]
 pub enum Condition {
@@ -401,6 +684,50 @@ pub enum Condition {
     Field(FieldCondition),
     /// Check if points id is in a given set
     HasId(HasIdCondition),
+    /// Check if field has no values
+    IsEmpty(IsEmptyCondition),
+    /// Check if field is explicitly set to `null`
+    IsNull(IsNullCondition),
+    /// Nested filter
+    Filter(Filter),
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub struct Filter {
+    /// At least one of those conditions should match
+    pub should: Option<Vec<Condition>>,
+    /// All conditions must match
+    pub must: Option<Vec<Condition>>,
+    /// All conditions must NOT match
+    pub must_not: Option<Vec<Condition>>,
+}
+
+impl Filter {
+    pub fn new_should(condition: Condition) -> Self {
+        Filter {
+            should: Some(vec![condition]),
+            must: None,
+            must_not: None,
+        }
+    }
+
+    pub fn new_must(condition: Condition) -> Self {
+        Filter {
+            should: None,
+            must: Some(vec![condition]),
+            must_not: None,
+        }
+    }
+
+    pub fn new_must_not(condition: Condition) -> Self {
+        Filter {
+            should: None,
+            must: None,
+            must_not: Some(vec![condition]),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
@@ -464,12 +791,17 @@ impl PayloadSelector {
         PayloadSelector { include, exclude }
     }
 
+    /// Select payload fields. Nested fields are selected together with the parent key,
+    /// e.g. `source` selects `source.kind` and `source.page`
     pub fn process(
         &self,
         x: TheMap<PayloadKeyType, PayloadType>,
     ) -> TheMap<PayloadKeyType, PayloadType> {
+        let selected = |paths: &[PayloadKeyType], key: &str| {
+            paths.iter().any(|path| is_nested_key(path, key))
+        };
         x.into_iter()
-            .filter(|(key, _)| self.include.contains(key) && !self.exclude.contains(key))
+            .filter(|(key, _)| selected(&self.include, key) && !selected(&self.exclude, key))
             .collect()
     }
 }
@@ -613,6 +945,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_datetime_and_bool_parse() {
+        let datetime_query_strict =
+            r#"{"type": "datetime", "value": ["2021-01-01T00:00:00Z", "2021-01-01T02:00:00+02:00"]}"#;
+        let payload_interface: PayloadInterface =
+            serde_json::from_str(datetime_query_strict).unwrap();
+        let payload: PayloadType = (&payload_interface).into();
+        match &payload {
+            PayloadType::Datetime(x) => assert_eq!(x, &vec![1_609_459_200_000, 1_609_459_200_000]),
+            _ => panic!("Datetime payload expected"),
+        }
+
+        let invalid_datetime = r#"{"type": "datetime", "value": "yesterday"}"#;
+        assert!(serde_json::from_str::<PayloadInterface>(invalid_datetime).is_err());
+
+        let payload_interface: PayloadInterface = serde_json::from_str("true").unwrap();
+        let payload: PayloadType = (&payload_interface).into();
+        match &payload {
+            PayloadType::Bool(x) => assert_eq!(x, &vec![true]),
+            _ => panic!("Bool payload expected"),
+        }
+    }
+
     #[allow(dead_code)]
     fn check_rms_serialization<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(
         record: T,
@@ -713,6 +1068,35 @@ mod tests {
         check_json_serialization(payload);
     }
 
+    #[test]
+    fn test_filter_parse() {
+        let query = r#"
+        {
+            "must": [
+                { "key": "city", "match": { "keyword": "Berlin" } },
+                { "has_id": [1, 2, 3] },
+                { "must_not": [ { "key": "price", "range": { "gte": 100.0 } } ] }
+            ]
+        }
+        "#;
+
+        let filter: Filter = serde_json::from_str(query).unwrap();
+        let must = filter.must.unwrap();
+        assert_eq!(must.len(), 3);
+        match &must[0] {
+            Condition::Field(condition) => assert_eq!(condition.key, "city"),
+            _ => panic!("Field condition expected"),
+        }
+        match &must[1] {
+            Condition::HasId(condition) => assert_eq!(condition.has_id.len(), 3),
+            _ => panic!("HasId condition expected"),
+        }
+        match &must[2] {
+            Condition::Filter(nested) => assert!(nested.must_not.is_some()),
+            _ => panic!("Nested filter expected"),
+        }
+    }
+
     #[test]
     fn test_name() {
         let label = PayloadType::Keyword(vec!["Hello".to_owned()]);
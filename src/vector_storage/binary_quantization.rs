@@ -0,0 +1,165 @@
+use crate::entry::entry_point::OperationResult;
+use crate::types::{PointOffsetType, ScoreType, VectorElementType};
+use crate::vector_storage::quantized_vector_storage::{save_quantized, QuantizedVectors};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Vectors with each component replaced by a single bit: whether it is above the mean value of the dimension.
+/// Vectors are compared by Hamming distance, so scores are only good for candidate selection
+#[derive(Deserialize, Serialize)]
+pub struct BinaryQuantizedVectors {
+    dim: usize,
+    /// Mean value of each dimension
+    thresholds: Vec<VectorElementType>,
+    /// Packed bits of all vectors, `dim` bits rounded up to the whole word per vector
+    codes: Vec<u64>,
+}
+
+impl BinaryQuantizedVectors {
+    pub fn new(dim: usize) -> Self {
+        BinaryQuantizedVectors {
+            dim,
+            thresholds: vec![0.0; dim],
+            codes: vec![],
+        }
+    }
+
+    fn words_per_vector(&self) -> usize {
+        self.dim.div_ceil(WORD_BITS)
+    }
+
+    fn codes(&self, point_id: PointOffsetType) -> &[u64] {
+        let words = self.words_per_vector();
+        let offset = point_id as usize * words;
+        &self.codes[offset..offset + words]
+    }
+
+    fn pack(&self, vector: &[VectorElementType]) -> Vec<u64> {
+        let mut bits = vec![0u64; self.words_per_vector()];
+        for (i, (value, threshold)) in vector.iter().zip(&self.thresholds).enumerate() {
+            if value > threshold {
+                bits[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+            }
+        }
+        bits
+    }
+
+    /// Greater the value - fewer differing bits
+    fn similarity(&self, bits_a: &[u64], bits_b: &[u64]) -> ScoreType {
+        let hamming_distance: u32 = bits_a
+            .iter()
+            .zip(bits_b)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        self.dim as ScoreType - 2.0 * hamming_distance as ScoreType
+    }
+}
+
+impl QuantizedVectors for BinaryQuantizedVectors {
+    fn train(&mut self, sample: &[Vec<VectorElementType>]) {
+        if sample.is_empty() {
+            return;
+        }
+        let mut sums = vec![0.0; self.dim];
+        for vector in sample {
+            sums.iter_mut()
+                .zip(vector)
+                .for_each(|(sum, value)| *sum += value);
+        }
+        self.thresholds = sums
+            .into_iter()
+            .map(|sum| sum / sample.len() as VectorElementType)
+            .collect();
+    }
+
+    fn encode(&mut self, point_id: PointOffsetType, vector: &[VectorElementType]) {
+        let words = self.words_per_vector();
+        let offset = point_id as usize * words;
+        if self.codes.len() < offset + words {
+            self.codes.resize(offset + words, 0);
+        }
+        let bits = self.pack(vector);
+        self.codes[offset..offset + words].copy_from_slice(&bits);
+    }
+
+    fn num_vectors(&self) -> usize {
+        self.codes.len() / self.words_per_vector()
+    }
+
+    fn decode(&self, point_id: PointOffsetType) -> Vec<VectorElementType> {
+        let bits = self.codes(point_id);
+        self.thresholds
+            .iter()
+            .enumerate()
+            .map(|(i, threshold)| {
+                if bits[i / WORD_BITS] & (1 << (i % WORD_BITS)) != 0 {
+                    threshold + 1.0
+                } else {
+                    threshold - 1.0
+                }
+            })
+            .collect()
+    }
+
+    fn query_scorer<'a>(
+        &'a self,
+        query: &[VectorElementType],
+    ) -> Box<dyn Fn(PointOffsetType) -> ScoreType + Send + Sync + 'a> {
+        let query_bits = self.pack(query);
+        Box::new(move |point_id| self.similarity(&query_bits, self.codes(point_id)))
+    }
+
+    fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType {
+        self.similarity(self.codes(point_a), self.codes(point_b))
+    }
+
+    fn save(&self, path: &Path, trained_count: usize) -> OperationResult<()> {
+        save_quantized(path, trained_count, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_quantization() {
+        let dim = 70;
+        let vectors: Vec<Vec<VectorElementType>> = vec![
+            (0..dim)
+                .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+                .collect(),
+            (0..dim)
+                .map(|i| if i % 2 == 0 { -1.0 } else { 1.0 })
+                .collect(),
+            (0..dim).map(|i| if i < 36 { 1.0 } else { -1.0 }).collect(),
+        ];
+        let mut quantized = BinaryQuantizedVectors::new(dim);
+        quantized.train(&vectors);
+        for (point_id, vector) in vectors.iter().enumerate() {
+            quantized.encode(point_id as PointOffsetType, vector);
+        }
+        assert_eq!(quantized.num_vectors(), 3);
+        assert_eq!(quantized.codes(0).len(), 2);
+
+        // Opposite vectors differ in all bits, the third one differs from both in half of bits
+        assert_eq!(quantized.score_internal(0, 0), dim as ScoreType);
+        assert_eq!(quantized.score_internal(0, 1), -(dim as ScoreType));
+        assert_eq!(quantized.score_internal(0, 2), 0.0);
+
+        let query_scorer = quantized.query_scorer(&vectors[2]);
+        assert_eq!(query_scorer(2), dim as ScoreType);
+        assert_eq!(query_scorer(1), 0.0);
+        drop(query_scorer);
+
+        let decoded = quantized.decode(1);
+        assert_eq!(quantized.pack(&decoded), quantized.codes(1));
+
+        // Thresholds stay valid, if there is nothing to train on
+        quantized.train(&[]);
+        assert!(quantized.thresholds.iter().all(|x| x.is_finite()));
+        assert_eq!(quantized.score_internal(0, 1), -(dim as ScoreType));
+    }
+}
@@ -2,7 +2,7 @@ use std::ops::Range;
 use std::path::Path;
 
 use log::debug;
-use rocksdb::{IteratorMode, Options, DB};
+use rocksdb::{IteratorMode, Options, WriteBatch, DB};
 use serde::{Deserialize, Serialize};
 
 use crate::entry::entry_point::OperationResult;
@@ -124,6 +124,24 @@ impl DriveVectorStorage {
         Ok(())
     }
 
+    /// Write multiple vectors with a single batch
+    pub fn upsert_batch(
+        &self,
+        points: impl Iterator<Item = (PointOffsetType, Vec<VectorElementType>)>,
+    ) -> OperationResult<()> {
+        let mut batch = WriteBatch::default();
+        for (point_id, vector) in points {
+            let record = StoredRecord { vector };
+            batch.put(
+                bincode::serialize(&point_id).unwrap(),
+                bincode::serialize(&record).unwrap(),
+            );
+        }
+        self.store.write(batch)?;
+
+        Ok(())
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -202,10 +220,43 @@ impl VectorStorage for DriveVectorStorage {
         Ok(start_index..end_index)
     }
 
+    fn put_vectors(
+        &mut self,
+        vectors: Vec<Vec<VectorElementType>>,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let dim = self.dim;
+        let start_index = self.len as PointOffsetType;
+        let end_index = start_index + vectors.len() as PointOffsetType;
+        let points = vectors.into_iter().enumerate().map(|(offset, vector)| {
+            assert_eq!(dim, vector.len());
+            (start_index + offset as PointOffsetType, vector)
+        });
+        self.upsert_batch(points)?;
+        self.len = end_index as usize;
+        Ok(start_index..end_index)
+    }
+
+    fn update_vectors(
+        &mut self,
+        vectors: Vec<(PointOffsetType, Vec<VectorElementType>)>,
+    ) -> OperationResult<Vec<PointOffsetType>> {
+        let keys = vectors.iter().map(|(key, _)| *key).collect();
+        self.upsert_batch(vectors.into_iter())?;
+        Ok(keys)
+    }
+
     fn delete(&mut self, key: PointOffsetType) -> OperationResult<()> {
         Ok(self.store.delete(bincode::serialize(&key).unwrap())?)
     }
 
+    fn delete_vectors(&mut self, keys: &[PointOffsetType]) -> OperationResult<()> {
+        let mut batch = WriteBatch::default();
+        for key in keys {
+            batch.delete(bincode::serialize(key).unwrap());
+        }
+        Ok(self.store.write(batch)?)
+    }
+
     fn is_deleted(&self, _: PointOffsetType) -> bool {
         false
     }
@@ -37,6 +37,22 @@ impl RawScorer for MemmapRawScorer<'_> {
         Box::new(res_iter)
     }
 
+    fn score_points_with_deleted<'a>(
+        &'a self,
+        points: &'a mut dyn Iterator<Item = PointOffsetType>,
+    ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a> {
+        let res_iter = points
+            .filter(move |point| *point < self.mmap_store.num_vectors as PointOffsetType)
+            .filter_map(move |point| {
+                let other_vector = self.mmap_store.raw_vector(point)?;
+                Some(ScoredPointOffset {
+                    idx: point,
+                    score: self.metric.similarity(&self.query, other_vector),
+                })
+            });
+        Box::new(res_iter)
+    }
+
     fn check_point(&self, point: PointOffsetType) -> bool {
         (point < self.mmap_store.num_vectors as PointOffsetType)
             && !self.mmap_store.deleted(point).unwrap_or(true)
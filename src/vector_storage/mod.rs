@@ -1,7 +1,11 @@
 pub mod memmap_vector_storage;
 mod mmap_vectors;
 pub mod simple_vector_storage;
+pub mod binary_quantization;
 pub mod drive_vector_storage;
+pub mod product_quantization;
+pub mod quantized_vector_storage;
+pub mod scalar_quantization;
 mod vector_storage_base;
 
 pub use vector_storage_base::*;
@@ -0,0 +1,263 @@
+use crate::entry::entry_point::OperationResult;
+use crate::types::{
+    Distance, PointOffsetType, ProductQuantizationConfig, ScoreType, VectorElementType,
+};
+use crate::vector_storage::quantized_vector_storage::{
+    pairwise_similarity, save_quantized, QuantizedVectors,
+};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::ops::Range;
+use std::path::Path;
+
+/// Max number of centroids of each subvector, so that centroid id fits into `u8`
+const MAX_CENTROIDS: usize = 256;
+
+/// Number of k-means iterations, used to fit codebooks
+const KMEANS_ITERATIONS: usize = 16;
+
+fn squared_distance(v1: &[VectorElementType], v2: &[VectorElementType]) -> ScoreType {
+    v1.iter().zip(v2).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+/// Vectors split into subvectors of fixed size, each subvector is replaced with the id of the closest centroid.
+/// Centroids of each subvector are fitted with k-means
+#[derive(Deserialize, Serialize)]
+pub struct ProductQuantizedVectors {
+    dim: usize,
+    distance: Distance,
+    subvector_size: usize,
+    /// Centroids of each subvector, stored one after another
+    codebooks: Vec<Vec<VectorElementType>>,
+    codes: Vec<u8>,
+}
+
+impl ProductQuantizedVectors {
+    pub fn new(dim: usize, distance: Distance, config: ProductQuantizationConfig) -> Self {
+        ProductQuantizedVectors {
+            dim,
+            distance,
+            subvector_size: max(1, config.subvector_size),
+            codebooks: vec![],
+            codes: vec![],
+        }
+    }
+
+    fn num_subvectors(&self) -> usize {
+        self.dim.div_ceil(self.subvector_size)
+    }
+
+    fn subvector_range(&self, subvector: usize) -> Range<usize> {
+        let start = subvector * self.subvector_size;
+        start..min(start + self.subvector_size, self.dim)
+    }
+
+    fn centroid(&self, subvector: usize, centroid: u8) -> &[VectorElementType] {
+        let size = self.subvector_range(subvector).len();
+        let offset = centroid as usize * size;
+        &self.codebooks[subvector][offset..offset + size]
+    }
+
+    fn codes(&self, point_id: PointOffsetType) -> &[u8] {
+        let num_subvectors = self.num_subvectors();
+        let offset = point_id as usize * num_subvectors;
+        &self.codes[offset..offset + num_subvectors]
+    }
+
+    /// Id of the centroid, closest to the given part of the vector
+    fn closest_centroid(&self, subvector: usize, values: &[VectorElementType]) -> u8 {
+        let size = values.len();
+        let mut best_centroid = 0;
+        let mut best_distance = ScoreType::MAX;
+        for (centroid, centroid_values) in self.codebooks[subvector].chunks(size).enumerate() {
+            let distance = squared_distance(values, centroid_values);
+            if distance < best_distance {
+                best_distance = distance;
+                best_centroid = centroid;
+            }
+        }
+        best_centroid as u8
+    }
+}
+
+/// Fit centroids to the given subvectors, returns centroids stored one after another
+fn kmeans(points: &[&[VectorElementType]], num_centroids: usize) -> Vec<VectorElementType> {
+    let size = points[0].len();
+    let mut centroids: Vec<VectorElementType> = points
+        .choose_multiple(&mut rand::thread_rng(), num_centroids)
+        .flat_map(|point| point.iter().cloned())
+        .collect();
+
+    let mut assignments = vec![0; points.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+            *assignment = centroids
+                .chunks(size)
+                .map(|centroid| squared_distance(point, centroid))
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(centroid, _)| centroid)
+                .unwrap_or(0);
+        }
+
+        let mut sums = vec![0.0; num_centroids * size];
+        let mut counts = vec![0usize; num_centroids];
+        for (point, &assignment) in points.iter().zip(&assignments) {
+            counts[assignment] += 1;
+            let sum = &mut sums[assignment * size..(assignment + 1) * size];
+            sum.iter_mut().zip(point.iter()).for_each(|(s, x)| *s += x);
+        }
+        // Centroids without assigned points stay in place
+        for (centroid, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            let range = centroid * size..(centroid + 1) * size;
+            for (value, sum) in centroids[range.clone()].iter_mut().zip(&sums[range]) {
+                *value = sum / count as VectorElementType;
+            }
+        }
+    }
+    centroids
+}
+
+impl QuantizedVectors for ProductQuantizedVectors {
+    fn train(&mut self, sample: &[Vec<VectorElementType>]) {
+        if sample.is_empty() {
+            return;
+        }
+        let num_centroids = min(MAX_CENTROIDS, sample.len());
+        self.codebooks = (0..self.num_subvectors())
+            .map(|subvector| {
+                let range = self.subvector_range(subvector);
+                let points: Vec<_> = sample.iter().map(|v| &v[range.clone()]).collect();
+                kmeans(&points, num_centroids)
+            })
+            .collect();
+    }
+
+    fn encode(&mut self, point_id: PointOffsetType, vector: &[VectorElementType]) {
+        let num_subvectors = self.num_subvectors();
+        let offset = point_id as usize * num_subvectors;
+        if self.codes.len() < offset + num_subvectors {
+            self.codes.resize(offset + num_subvectors, 0);
+        }
+        for subvector in 0..num_subvectors {
+            let range = self.subvector_range(subvector);
+            self.codes[offset + subvector] = self.closest_centroid(subvector, &vector[range]);
+        }
+    }
+
+    fn num_vectors(&self) -> usize {
+        self.codes.len() / self.num_subvectors()
+    }
+
+    fn decode(&self, point_id: PointOffsetType) -> Vec<VectorElementType> {
+        self.codes(point_id)
+            .iter()
+            .enumerate()
+            .flat_map(|(subvector, &centroid)| self.centroid(subvector, centroid))
+            .cloned()
+            .collect()
+    }
+
+    fn query_scorer<'a>(
+        &'a self,
+        query: &[VectorElementType],
+    ) -> Box<dyn Fn(PointOffsetType) -> ScoreType + Send + Sync + 'a> {
+        // Asymmetric distance: partial scores of the query with each centroid are computed once per query
+        let tables: Vec<Vec<ScoreType>> = self
+            .codebooks
+            .iter()
+            .enumerate()
+            .map(|(subvector, codebook)| {
+                let query_values = &query[self.subvector_range(subvector)];
+                codebook
+                    .chunks(query_values.len())
+                    .map(|centroid| match self.distance {
+                        Distance::Cosine | Distance::Dot => {
+                            query_values.iter().zip(centroid).map(|(a, b)| a * b).sum()
+                        }
+                        Distance::Euclid => squared_distance(query_values, centroid),
+                    })
+                    .collect()
+            })
+            .collect();
+        Box::new(move |point_id| {
+            let partial_sum: ScoreType = self
+                .codes(point_id)
+                .iter()
+                .zip(&tables)
+                .map(|(&centroid, table)| table[centroid as usize])
+                .sum();
+            match self.distance {
+                Distance::Cosine | Distance::Dot => partial_sum,
+                Distance::Euclid => -partial_sum.sqrt(),
+            }
+        })
+    }
+
+    fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType {
+        let pairs = self
+            .codes(point_a)
+            .iter()
+            .zip(self.codes(point_b))
+            .enumerate()
+            .flat_map(|(subvector, (&centroid_a, &centroid_b))| {
+                let values_a = self.centroid(subvector, centroid_a).iter().cloned();
+                values_a.zip(self.centroid(subvector, centroid_b).iter().cloned())
+            });
+        pairwise_similarity(self.distance, pairs)
+    }
+
+    fn save(&self, path: &Path, trained_count: usize) -> OperationResult<()> {
+        save_quantized(path, trained_count, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::payload_fixtures::random_vector;
+
+    #[test]
+    fn test_product_quantization() {
+        let dim = 10;
+        let mut rng = rand::thread_rng();
+        let vectors: Vec<_> = (0..50).map(|_| random_vector(&mut rng, dim)).collect();
+
+        for &distance in &[Distance::Dot, Distance::Euclid] {
+            let config = ProductQuantizationConfig {
+                subvector_size: 4,
+                rescore: false,
+            };
+            let mut quantized = ProductQuantizedVectors::new(dim, distance, config);
+            assert_eq!(quantized.num_subvectors(), 3);
+            quantized.train(&vectors);
+            for (point_id, vector) in vectors.iter().enumerate() {
+                quantized.encode(point_id as PointOffsetType, vector);
+            }
+            assert_eq!(quantized.num_vectors(), vectors.len());
+
+            // There are fewer points than centroids, so each point is a centroid itself
+            for (point_id, vector) in vectors.iter().enumerate() {
+                let decoded = quantized.decode(point_id as PointOffsetType);
+                assert_eq!(&decoded, vector);
+            }
+
+            let query = random_vector(&mut rng, dim);
+            let query_scorer = quantized.query_scorer(&query);
+            for (point_id, vector) in vectors.iter().enumerate() {
+                let exact =
+                    pairwise_similarity(distance, query.iter().cloned().zip(vector.clone()));
+                assert!((query_scorer(point_id as PointOffsetType) - exact).abs() < 1e-4);
+            }
+            drop(query_scorer);
+            let exact =
+                pairwise_similarity(distance, vectors[0].iter().cloned().zip(vectors[1].clone()));
+            assert!((quantized.score_internal(0, 1) - exact).abs() < 1e-4);
+
+            // Codebooks stay in place, if there is nothing to train on
+            quantized.train(&[]);
+            assert!((quantized.score_internal(0, 1) - exact).abs() < 1e-4);
+        }
+    }
+}
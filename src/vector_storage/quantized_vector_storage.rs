@@ -0,0 +1,576 @@
+use crate::common::file_operations::{atomic_save_bin, read_bin};
+use crate::entry::entry_point::OperationResult;
+use crate::spaces::metric::Metric;
+use crate::spaces::tools::mertic_object;
+use crate::types::{Distance, PointOffsetType, QuantizationConfig, ScoreType, VectorElementType};
+use crate::vector_storage::binary_quantization::BinaryQuantizedVectors;
+use crate::vector_storage::product_quantization::ProductQuantizedVectors;
+use crate::vector_storage::scalar_quantization::ScalarQuantizedVectors;
+use crate::vector_storage::{RawScorer, ScoredPointOffset, VectorStorage};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Max number of vectors, used to fit parameters of the quantization
+const TRAIN_SAMPLE_SIZE: usize = 10_000;
+
+pub const QUANTIZED_VECTORS_FILE: &str = "quantized.bin";
+
+/// Compressed copy of the vectors, used for the approximate scoring
+pub trait QuantizedVectors: Send + Sync {
+    /// Fit encoding parameters to the given vectors. Previously encoded vectors should be encoded again.
+    /// Empty sample leaves parameters unchanged
+    fn train(&mut self, sample: &[Vec<VectorElementType>]);
+
+    /// Encode vector and store it under the given id
+    fn encode(&mut self, point_id: PointOffsetType, vector: &[VectorElementType]);
+
+    /// Number of stored encoded vectors, including deleted ones
+    fn num_vectors(&self) -> usize;
+
+    /// Approximate vector of the point, restored from its code
+    fn decode(&self, point_id: PointOffsetType) -> Vec<VectorElementType>;
+
+    /// Prepare query for scoring of encoded vectors
+    fn query_scorer<'a>(
+        &'a self,
+        query: &[VectorElementType],
+    ) -> Box<dyn Fn(PointOffsetType) -> ScoreType + Send + Sync + 'a>;
+
+    /// Approximate similarity between two encoded vectors
+    fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType;
+
+    /// Save encoding parameters and codes, see `save_quantized`
+    fn save(&self, path: &Path, trained_count: usize) -> OperationResult<()>;
+}
+
+/// Content of the quantized vectors file
+#[derive(Deserialize, Serialize)]
+struct StoredQuantizedVectors<Q> {
+    /// Number of vectors at the moment of the last training
+    trained_count: usize,
+    quantized: Q,
+}
+
+pub fn save_quantized<Q: Serialize>(
+    path: &Path,
+    trained_count: usize,
+    quantized: &Q,
+) -> OperationResult<()> {
+    atomic_save_bin(
+        path,
+        &StoredQuantizedVectors {
+            trained_count,
+            quantized,
+        },
+    )
+}
+
+fn load_quantized<Q>(path: &Path) -> OperationResult<(usize, Box<dyn QuantizedVectors>)>
+where
+    Q: QuantizedVectors + DeserializeOwned + Serialize + 'static,
+{
+    let stored: StoredQuantizedVectors<Q> = read_bin(path)?;
+    Ok((stored.trained_count, Box::new(stored.quantized)))
+}
+
+/// Similarity of two vectors, given as pairs of their components
+pub fn pairwise_similarity(
+    distance: Distance,
+    pairs: impl Iterator<Item = (VectorElementType, VectorElementType)>,
+) -> ScoreType {
+    match distance {
+        Distance::Cosine | Distance::Dot => pairs.map(|(a, b)| a * b).sum(),
+        Distance::Euclid => {
+            let s: ScoreType = pairs.map(|(a, b)| (a - b).powi(2)).sum();
+            -s.sqrt()
+        }
+    }
+}
+
+pub struct QuantizedRawScorer<'a> {
+    query_scorer: Box<dyn Fn(PointOffsetType) -> ScoreType + Send + Sync + 'a>,
+    quantized: &'a dyn QuantizedVectors,
+    storage: &'a dyn VectorStorage,
+}
+
+impl RawScorer for QuantizedRawScorer<'_> {
+    fn score_points<'a>(
+        &'a self,
+        points: &'a mut dyn Iterator<Item = PointOffsetType>,
+    ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a> {
+        let res_iter = points
+            .filter(move |point| self.check_point(*point))
+            .map(move |point| ScoredPointOffset {
+                idx: point,
+                score: (self.query_scorer)(point),
+            });
+        Box::new(res_iter)
+    }
+
+    fn score_points_with_deleted<'a>(
+        &'a self,
+        points: &'a mut dyn Iterator<Item = PointOffsetType>,
+    ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a> {
+        let res_iter = points
+            .filter(move |point| (*point as usize) < self.quantized.num_vectors())
+            .map(move |point| ScoredPointOffset {
+                idx: point,
+                score: (self.query_scorer)(point),
+            });
+        Box::new(res_iter)
+    }
+
+    fn check_point(&self, point: PointOffsetType) -> bool {
+        (point as usize) < self.quantized.num_vectors() && !self.storage.is_deleted(point)
+    }
+
+    fn score_point(&self, point: PointOffsetType) -> ScoreType {
+        (self.query_scorer)(point)
+    }
+
+    fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType {
+        self.quantized.score_internal(point_a, point_b)
+    }
+}
+
+/// Vector storage, which keeps encoded copy of the vectors of the underlying storage.
+/// Encoded vectors are used by `raw_scorer` for the index search, all other requests use original vectors,
+/// including `raw_scorer_internal`, which links points into the graph.
+/// Encoding is fitted again each time the number of vectors doubles, so it follows the data.
+/// Encoding parameters and codes are saved on flush, so the same encoding is used after reopening.
+/// Codes are written only if they were changed since the last flush
+pub struct QuantizedVectorStorage<S: VectorStorage> {
+    path: PathBuf,
+    storage: S,
+    config: QuantizationConfig,
+    metric: Box<dyn Metric>,
+    quantized: Box<dyn QuantizedVectors>,
+    // Number of vectors at the moment of the last training
+    trained_count: usize,
+    // Codes or encoding parameters differ from the saved ones
+    changed: AtomicBool,
+}
+
+impl<S: VectorStorage> QuantizedVectorStorage<S> {
+    /// Wrap `storage`, quantized vectors are stored in the directory `path`
+    pub fn open(
+        path: &Path,
+        storage: S,
+        distance: Distance,
+        config: QuantizationConfig,
+    ) -> OperationResult<Self> {
+        let quantized_path = path.join(QUANTIZED_VECTORS_FILE);
+        if quantized_path.exists() {
+            let (trained_count, quantized) = match config {
+                QuantizationConfig::Scalar(_) => {
+                    load_quantized::<ScalarQuantizedVectors>(&quantized_path)?
+                }
+                QuantizationConfig::Product(_) => {
+                    load_quantized::<ProductQuantizedVectors>(&quantized_path)?
+                }
+                QuantizationConfig::Binary(_) => {
+                    load_quantized::<BinaryQuantizedVectors>(&quantized_path)?
+                }
+            };
+            let mut quantized_storage = QuantizedVectorStorage {
+                path: quantized_path,
+                storage,
+                config,
+                metric: mertic_object(&distance),
+                quantized,
+                trained_count,
+                changed: AtomicBool::new(false),
+            };
+            // Vectors could be added after the codes were saved
+            let encoded_count = quantized_storage.quantized.num_vectors() as PointOffsetType;
+            let total_vectors = quantized_storage.storage.total_vector_count() as PointOffsetType;
+            quantized_storage.update_codes(encoded_count..total_vectors);
+            return Ok(quantized_storage);
+        }
+
+        let dim = storage.vector_dim();
+        let quantized: Box<dyn QuantizedVectors> = match config {
+            QuantizationConfig::Scalar(scalar_config) => {
+                Box::new(ScalarQuantizedVectors::new(dim, distance, scalar_config))
+            }
+            QuantizationConfig::Product(product_config) => {
+                Box::new(ProductQuantizedVectors::new(dim, distance, product_config))
+            }
+            QuantizationConfig::Binary(_) => Box::new(BinaryQuantizedVectors::new(dim)),
+        };
+        let mut quantized_storage = QuantizedVectorStorage {
+            path: quantized_path,
+            storage,
+            config,
+            metric: mertic_object(&distance),
+            quantized,
+            trained_count: 0,
+            changed: AtomicBool::new(false),
+        };
+        quantized_storage.train_and_encode();
+        Ok(quantized_storage)
+    }
+
+    fn train_and_encode(&mut self) {
+        let total_vectors = self.storage.total_vector_count();
+        let vector_count = self.storage.vector_count();
+        if vector_count == 0 {
+            return;
+        }
+        // Small storages are used as a whole, so that no extreme values are missed
+        let sample_ids = if vector_count <= TRAIN_SAMPLE_SIZE {
+            self.storage.iter_ids()
+        } else {
+            Box::new(self.storage.sample_ids().take(TRAIN_SAMPLE_SIZE))
+        };
+        let sample: Vec<_> = sample_ids
+            .filter_map(|point_id| self.storage.get_vector(point_id))
+            .collect();
+        // Vector count of some storages includes deleted vectors, so there could be nothing to train on
+        if sample.is_empty() {
+            return;
+        }
+        self.quantized.train(&sample);
+        for point_id in 0..total_vectors as PointOffsetType {
+            if let Some(vector) = self.storage.get_vector(point_id) {
+                self.quantized.encode(point_id, &vector);
+            }
+        }
+        self.trained_count = total_vectors;
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Encode changed vectors, or all of them if the encoding should be fitted again
+    fn update_codes(&mut self, point_ids: impl Iterator<Item = PointOffsetType>) {
+        if self.storage.total_vector_count() >= 2 * self.trained_count {
+            self.train_and_encode();
+            return;
+        }
+        for point_id in point_ids {
+            if let Some(vector) = self.storage.get_vector(point_id) {
+                self.quantized.encode(point_id, &vector);
+                self.changed.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn quantized_raw_scorer(&self, query: Vec<VectorElementType>) -> Box<dyn RawScorer + '_> {
+        Box::new(QuantizedRawScorer {
+            query_scorer: self.quantized.query_scorer(&query),
+            quantized: self.quantized.as_ref(),
+            storage: &self.storage,
+        })
+    }
+}
+
+impl<S: VectorStorage> VectorStorage for QuantizedVectorStorage<S> {
+    fn vector_dim(&self) -> usize {
+        self.storage.vector_dim()
+    }
+
+    fn vector_count(&self) -> usize {
+        self.storage.vector_count()
+    }
+
+    fn deleted_count(&self) -> usize {
+        self.storage.deleted_count()
+    }
+
+    fn total_vector_count(&self) -> usize {
+        self.storage.total_vector_count()
+    }
+
+    fn get_vector(&self, key: PointOffsetType) -> Option<Vec<VectorElementType>> {
+        self.storage.get_vector(key)
+    }
+
+    fn put_vector(&mut self, vector: Vec<VectorElementType>) -> OperationResult<PointOffsetType> {
+        let point_id = self.storage.put_vector(vector)?;
+        self.update_codes(std::iter::once(point_id));
+        Ok(point_id)
+    }
+
+    fn update_vector(
+        &mut self,
+        key: PointOffsetType,
+        vector: Vec<VectorElementType>,
+    ) -> OperationResult<PointOffsetType> {
+        let point_id = self.storage.update_vector(key, vector)?;
+        self.update_codes(std::iter::once(point_id));
+        Ok(point_id)
+    }
+
+    fn update_from(
+        &mut self,
+        other: &dyn VectorStorage,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let point_ids = self.storage.update_from(other)?;
+        self.update_codes(point_ids.clone());
+        Ok(point_ids)
+    }
+
+    fn put_vectors(
+        &mut self,
+        vectors: Vec<Vec<VectorElementType>>,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let point_ids = self.storage.put_vectors(vectors)?;
+        self.update_codes(point_ids.clone());
+        Ok(point_ids)
+    }
+
+    fn update_vectors(
+        &mut self,
+        vectors: Vec<(PointOffsetType, Vec<VectorElementType>)>,
+    ) -> OperationResult<Vec<PointOffsetType>> {
+        let point_ids = self.storage.update_vectors(vectors)?;
+        self.update_codes(point_ids.iter().cloned());
+        Ok(point_ids)
+    }
+
+    fn delete(&mut self, key: PointOffsetType) -> OperationResult<()> {
+        self.storage.delete(key)
+    }
+
+    fn delete_vectors(&mut self, keys: &[PointOffsetType]) -> OperationResult<()> {
+        self.storage.delete_vectors(keys)
+    }
+
+    fn is_deleted(&self, key: PointOffsetType) -> bool {
+        self.storage.is_deleted(key)
+    }
+
+    fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
+        self.storage.iter_ids()
+    }
+
+    fn flush(&self) -> OperationResult<()> {
+        self.storage.flush()?;
+        if self.changed.swap(false, Ordering::Relaxed) {
+            if let Err(err) = self.quantized.save(&self.path, self.trained_count) {
+                self.changed.store(true, Ordering::Relaxed);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn raw_scorer(&self, vector: Vec<VectorElementType>) -> Box<dyn RawScorer + '_> {
+        let query = self.metric.preprocess(&vector).unwrap_or(vector);
+        self.quantized_raw_scorer(query)
+    }
+
+    fn raw_scorer_internal(&self, point_id: PointOffsetType) -> Box<dyn RawScorer + '_> {
+        // Graph is linked with original vectors, encoded ones are too coarse to select neighbours
+        match self.storage.get_vector(point_id) {
+            Some(vector) => self.storage.raw_scorer(vector),
+            None => self.quantized_raw_scorer(self.quantized.decode(point_id)),
+        }
+    }
+
+    fn score_points(
+        &self,
+        vector: &[VectorElementType],
+        points: &mut dyn Iterator<Item = PointOffsetType>,
+        top: usize,
+    ) -> Vec<ScoredPointOffset> {
+        self.storage.score_points(vector, points, top)
+    }
+
+    fn score_all(&self, vector: &[VectorElementType], top: usize) -> Vec<ScoredPointOffset> {
+        self.storage.score_all(vector, top)
+    }
+
+    fn score_internal(
+        &self,
+        point: PointOffsetType,
+        points: &mut dyn Iterator<Item = PointOffsetType>,
+        top: usize,
+    ) -> Vec<ScoredPointOffset> {
+        self.storage.score_internal(point, points, top)
+    }
+
+    fn is_quantized(&self) -> bool {
+        true
+    }
+
+    fn rescore_required(&self) -> bool {
+        self.config.rescore()
+    }
+
+    fn rescore_limit(&self, top: usize) -> usize {
+        top * self.config.oversampling()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::payload_fixtures::random_vector;
+    use crate::types::{
+        BinaryQuantizationConfig, ProductQuantizationConfig, ScalarQuantizationConfig,
+    };
+    use crate::vector_storage::drive_vector_storage::DriveVectorStorage;
+    use crate::vector_storage::simple_vector_storage::SimpleVectorStorage;
+    use itertools::Itertools;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_quantized_scores() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let dim = 16;
+        let distance = Distance::Cosine;
+        let mut rng = rand::thread_rng();
+
+        let storage = SimpleVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let config = QuantizationConfig::Scalar(ScalarQuantizationConfig {
+            per_dimension: true,
+            rescore: true,
+        });
+        let mut storage =
+            QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        for _ in 0..100 {
+            storage.put_vector(random_vector(&mut rng, dim)).unwrap();
+        }
+        storage.delete(10).unwrap();
+
+        let query = random_vector(&mut rng, dim);
+        let exact = storage.score_all(&query, 100);
+        assert_eq!(exact.len(), 99);
+
+        let raw_scorer = storage.raw_scorer(query);
+        let mut ids = 0..100;
+        let approximate = raw_scorer.score_points(&mut ids).collect_vec();
+        assert_eq!(approximate.len(), 99);
+        assert!(!raw_scorer.check_point(10));
+        for exact_point in &exact {
+            let approximate_score = raw_scorer.score_point(exact_point.idx);
+            assert!((approximate_score - exact_point.score).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_internal_scorer_uses_original_vectors() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let dim = 128;
+        let distance = Distance::Dot;
+        let mut rng = rand::thread_rng();
+
+        let storage = SimpleVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let config = QuantizationConfig::Binary(BinaryQuantizationConfig { oversampling: 1 });
+        let mut storage =
+            QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        for _ in 0..100 {
+            storage.put_vector(random_vector(&mut rng, dim)).unwrap();
+        }
+
+        // Graph is linked by these scores, so they are exact, unlike Hamming scores of `raw_scorer`
+        let exact = storage.score_internal(0, &mut (0..100), 100);
+        let raw_scorer = storage.raw_scorer_internal(0);
+        for exact_point in &exact {
+            assert!((raw_scorer.score_point(exact_point.idx) - exact_point.score).abs() < 1e-4);
+            assert!(
+                (raw_scorer.score_internal(0, exact_point.idx) - exact_point.score).abs() < 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_reopen_quantized_storage() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let dim = 16;
+        let distance = Distance::Dot;
+        let mut rng = rand::thread_rng();
+        let config = QuantizationConfig::Product(ProductQuantizationConfig {
+            subvector_size: 4,
+            rescore: false,
+        });
+
+        let storage = DriveVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let mut storage =
+            QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        let vectors = (0..500).map(|_| random_vector(&mut rng, dim)).collect_vec();
+        storage.put_vectors(vectors).unwrap();
+        storage.flush().unwrap();
+
+        let query = random_vector(&mut rng, dim);
+        let scores = storage
+            .raw_scorer(query.clone())
+            .score_points(&mut (0..500))
+            .collect_vec();
+        drop(storage);
+
+        // Codebooks are loaded, not fitted again, so the scores are the same
+        let storage = DriveVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let storage = QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        assert_eq!(storage.vector_count(), 500);
+        let reopened_scores = storage
+            .raw_scorer(query)
+            .score_points(&mut (0..500))
+            .collect_vec();
+        assert_eq!(scores, reopened_scores);
+    }
+
+    #[test]
+    fn test_flush_changed_codes() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let dim = 16;
+        let distance = Distance::Dot;
+        let mut rng = rand::thread_rng();
+        let config = QuantizationConfig::Scalar(ScalarQuantizationConfig {
+            per_dimension: false,
+            rescore: false,
+        });
+        let quantized_path = dir.path().join(QUANTIZED_VECTORS_FILE);
+
+        let storage = DriveVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let mut storage =
+            QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        let vectors = (0..100).map(|_| random_vector(&mut rng, dim)).collect_vec();
+        storage.put_vectors(vectors).unwrap();
+        storage.flush().unwrap();
+        assert!(quantized_path.exists());
+
+        // Nothing is written, if codes are not changed
+        std::fs::remove_file(&quantized_path).unwrap();
+        storage.flush().unwrap();
+        assert!(!quantized_path.exists());
+
+        storage.put_vector(random_vector(&mut rng, dim)).unwrap();
+        storage.flush().unwrap();
+        assert!(quantized_path.exists());
+        drop(storage);
+
+        // Reopened storage encodes nothing and writes nothing
+        let storage = DriveVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let storage = QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        std::fs::remove_file(&quantized_path).unwrap();
+        storage.flush().unwrap();
+        assert!(!quantized_path.exists());
+    }
+
+    #[test]
+    fn test_train_without_vectors() {
+        let dir = TempDir::new("storage_dir").unwrap();
+        let dim = 16;
+        let distance = Distance::Dot;
+        let mut rng = rand::thread_rng();
+
+        // Drive storage counts deleted vectors until reopening
+        let mut storage = DriveVectorStorage::open(dir.path(), dim, distance, false).unwrap();
+        let vectors = (0..10).map(|_| random_vector(&mut rng, dim)).collect_vec();
+        storage.put_vectors(vectors).unwrap();
+        storage.delete_vectors(&(0..10).collect_vec()).unwrap();
+        assert_eq!(storage.vector_count(), 10);
+        assert_eq!(storage.iter_ids().count(), 0);
+
+        let config = QuantizationConfig::Product(ProductQuantizationConfig {
+            subvector_size: 4,
+            rescore: false,
+        });
+        let storage = QuantizedVectorStorage::open(dir.path(), storage, distance, config).unwrap();
+        assert_eq!(storage.quantized.num_vectors(), 0);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::entry::entry_point::OperationResult;
+use crate::types::{
+    Distance, PointOffsetType, ScalarQuantizationConfig, ScoreType, VectorElementType,
+};
+use crate::vector_storage::quantized_vector_storage::{
+    pairwise_similarity, save_quantized, QuantizedVectors,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Number of distinct values of the encoded vector component
+const LEVELS: VectorElementType = u8::MAX as VectorElementType;
+
+/// Vectors with each component linearly mapped from its calibrated `[min, max]` range into `u8`
+#[derive(Deserialize, Serialize)]
+pub struct ScalarQuantizedVectors {
+    dim: usize,
+    distance: Distance,
+    config: ScalarQuantizationConfig,
+    /// Minimal value of each dimension
+    offsets: Vec<VectorElementType>,
+    /// Size of a single quantization step of each dimension
+    scales: Vec<VectorElementType>,
+    codes: Vec<u8>,
+}
+
+impl ScalarQuantizedVectors {
+    pub fn new(dim: usize, distance: Distance, config: ScalarQuantizationConfig) -> Self {
+        ScalarQuantizedVectors {
+            dim,
+            distance,
+            config,
+            offsets: vec![0.0; dim],
+            scales: vec![0.0; dim],
+            codes: vec![],
+        }
+    }
+
+    fn codes(&self, point_id: PointOffsetType) -> &[u8] {
+        let offset = point_id as usize * self.dim;
+        &self.codes[offset..offset + self.dim]
+    }
+
+    fn decoded_values<'a>(
+        &'a self,
+        codes: &'a [u8],
+    ) -> impl Iterator<Item = VectorElementType> + 'a {
+        codes
+            .iter()
+            .zip(self.offsets.iter().zip(&self.scales))
+            .map(|(&code, (offset, scale))| offset + scale * code as VectorElementType)
+    }
+}
+
+impl QuantizedVectors for ScalarQuantizedVectors {
+    fn train(&mut self, sample: &[Vec<VectorElementType>]) {
+        let mut min_values = vec![VectorElementType::MAX; self.dim];
+        let mut max_values = vec![VectorElementType::MIN; self.dim];
+        for vector in sample {
+            for (i, &value) in vector.iter().enumerate() {
+                min_values[i] = min_values[i].min(value);
+                max_values[i] = max_values[i].max(value);
+            }
+        }
+        if !self.config.per_dimension {
+            let min_value = min_values
+                .iter()
+                .cloned()
+                .fold(VectorElementType::MAX, f32::min);
+            let max_value = max_values
+                .iter()
+                .cloned()
+                .fold(VectorElementType::MIN, f32::max);
+            min_values.iter_mut().for_each(|x| *x = min_value);
+            max_values.iter_mut().for_each(|x| *x = max_value);
+        }
+        self.offsets = min_values;
+        self.scales = max_values
+            .iter()
+            .zip(&self.offsets)
+            .map(|(max_value, min_value)| (max_value - min_value) / LEVELS)
+            .collect();
+    }
+
+    fn encode(&mut self, point_id: PointOffsetType, vector: &[VectorElementType]) {
+        let offset = point_id as usize * self.dim;
+        if self.codes.len() < offset + self.dim {
+            self.codes.resize(offset + self.dim, 0);
+        }
+        for (i, &value) in vector.iter().enumerate() {
+            let scale = self.scales[i];
+            let code = if scale > 0.0 {
+                ((value - self.offsets[i]) / scale)
+                    .round()
+                    .clamp(0.0, LEVELS)
+            } else {
+                0.0
+            };
+            self.codes[offset + i] = code as u8;
+        }
+    }
+
+    fn num_vectors(&self) -> usize {
+        self.codes.len() / self.dim
+    }
+
+    fn decode(&self, point_id: PointOffsetType) -> Vec<VectorElementType> {
+        self.decoded_values(self.codes(point_id)).collect()
+    }
+
+    fn query_scorer<'a>(
+        &'a self,
+        query: &[VectorElementType],
+    ) -> Box<dyn Fn(PointOffsetType) -> ScoreType + Send + Sync + 'a> {
+        let query = query.to_vec();
+        Box::new(move |point_id| {
+            let values = self.decoded_values(self.codes(point_id));
+            pairwise_similarity(self.distance, query.iter().cloned().zip(values))
+        })
+    }
+
+    fn score_internal(&self, point_a: PointOffsetType, point_b: PointOffsetType) -> ScoreType {
+        let values_a = self.decoded_values(self.codes(point_a));
+        let values_b = self.decoded_values(self.codes(point_b));
+        pairwise_similarity(self.distance, values_a.zip(values_b))
+    }
+
+    fn save(&self, path: &Path, trained_count: usize) -> OperationResult<()> {
+        save_quantized(path, trained_count, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_quantization() {
+        let vectors = vec![
+            vec![0.0, -1.0, 10.0],
+            vec![1.0, 1.0, 20.0],
+            vec![0.5, 0.0, 15.0],
+        ];
+        for &per_dimension in &[true, false] {
+            let config = ScalarQuantizationConfig {
+                per_dimension,
+                rescore: false,
+            };
+            let mut quantized = ScalarQuantizedVectors::new(3, Distance::Dot, config);
+            quantized.train(&vectors);
+            for (point_id, vector) in vectors.iter().enumerate() {
+                quantized.encode(point_id as PointOffsetType, vector);
+            }
+            assert_eq!(quantized.num_vectors(), 3);
+
+            let max_error = if per_dimension { 0.05 } else { 0.1 };
+            for (point_id, vector) in vectors.iter().enumerate() {
+                let decoded = quantized.decode(point_id as PointOffsetType);
+                for (value, decoded_value) in vector.iter().zip(decoded) {
+                    assert!((value - decoded_value).abs() < max_error);
+                }
+            }
+
+            let exact: ScoreType = vectors[0].iter().zip(&vectors[1]).map(|(a, b)| a * b).sum();
+            assert!((quantized.score_internal(0, 1) - exact).abs() < 1.0);
+            assert!((quantized.query_scorer(&vectors[0])(1) - exact).abs() < 1.0);
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::ops::Range;
 use std::path::Path;
 
 use log::debug;
-use rocksdb::{IteratorMode, Options, DB};
+use rocksdb::{IteratorMode, Options, WriteBatch, DB};
 use serde::{Deserialize, Serialize};
 
 use crate::entry::entry_point::OperationResult;
@@ -58,6 +58,20 @@ impl RawScorer for SimpleRawScorer<'_> {
         Box::new(res_iter)
     }
 
+    fn score_points_with_deleted<'a>(
+        &'a self,
+        points: &'a mut dyn Iterator<Item = PointOffsetType>,
+    ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a> {
+        let res_iter = points.filter_map(move |point| {
+            let other_vector = self.vectors.get(point as usize)?;
+            Some(ScoredPointOffset {
+                idx: point,
+                score: self.metric.blas_similarity(&self.query, other_vector),
+            })
+        });
+        Box::new(res_iter)
+    }
+
     fn check_point(&self, point: PointOffsetType) -> bool {
         (point < self.vectors.len() as PointOffsetType) && !self.deleted[point as usize]
     }
@@ -126,13 +140,17 @@ impl SimpleVectorStorage {
         })
     }
 
-    fn update_stored(&self, point_id: PointOffsetType) -> OperationResult<()> {
+    fn stored_record(&self, point_id: PointOffsetType) -> StoredRecord {
         let v = self.vectors.get(point_id as usize).unwrap();
 
-        let record = StoredRecord {
+        StoredRecord {
             deleted: self.deleted[point_id as usize],
             vector: v.to_vec(), // ToDo: try to reduce number of vector copies
-        };
+        }
+    }
+
+    fn update_stored(&self, point_id: PointOffsetType) -> OperationResult<()> {
+        let record = self.stored_record(point_id);
         self.store.put(
             bincode::serialize(&point_id).unwrap(),
             bincode::serialize(&record).unwrap(),
@@ -140,6 +158,24 @@ impl SimpleVectorStorage {
 
         Ok(())
     }
+
+    /// Persist multiple points with a single write
+    fn update_stored_batch(
+        &self,
+        point_ids: impl Iterator<Item = PointOffsetType>,
+    ) -> OperationResult<()> {
+        let mut batch = WriteBatch::default();
+        for point_id in point_ids {
+            let record = self.stored_record(point_id);
+            batch.put(
+                bincode::serialize(&point_id).unwrap(),
+                bincode::serialize(&record).unwrap(),
+            );
+        }
+        self.store.write(batch)?;
+
+        Ok(())
+    }
 }
 
 impl VectorStorage for SimpleVectorStorage {
@@ -203,6 +239,33 @@ impl VectorStorage for SimpleVectorStorage {
         Ok(start_index..end_index)
     }
 
+    fn put_vectors(
+        &mut self,
+        vectors: Vec<Vec<VectorElementType>>,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let start_index = self.vectors.len() as PointOffsetType;
+        for vector in vectors {
+            assert_eq!(self.dim, vector.len());
+            self.vectors.push(Array::from(vector));
+            self.deleted.push(false);
+        }
+        let end_index = self.vectors.len() as PointOffsetType;
+        self.update_stored_batch(start_index..end_index)?;
+        Ok(start_index..end_index)
+    }
+
+    fn update_vectors(
+        &mut self,
+        vectors: Vec<(PointOffsetType, Vec<VectorElementType>)>,
+    ) -> OperationResult<Vec<PointOffsetType>> {
+        let keys: Vec<_> = vectors.iter().map(|(key, _)| *key).collect();
+        for (key, vector) in vectors {
+            self.vectors[key as usize].assign(&Array::from(vector));
+        }
+        self.update_stored_batch(keys.iter().cloned())?;
+        Ok(keys)
+    }
+
     fn delete(&mut self, key: PointOffsetType) -> OperationResult<()> {
         if (key as usize) >= self.deleted.len() {
             return Ok(());
@@ -215,6 +278,22 @@ impl VectorStorage for SimpleVectorStorage {
         Ok(())
     }
 
+    fn delete_vectors(&mut self, keys: &[PointOffsetType]) -> OperationResult<()> {
+        let mut deleted_keys = Vec::with_capacity(keys.len());
+        for key in keys.iter().cloned() {
+            if (key as usize) >= self.deleted.len() {
+                continue;
+            }
+            if !self.deleted[key as usize] {
+                self.deleted_count += 1
+            }
+            self.deleted.set(key as usize, true);
+            deleted_keys.push(key);
+        }
+        self.update_stored_batch(deleted_keys.into_iter())?;
+        Ok(())
+    }
+
     fn is_deleted(&self, key: PointOffsetType) -> bool {
         self.deleted[key as usize]
     }
@@ -33,6 +33,14 @@ pub trait RawScorer {
         &'a self,
         points: &'a mut dyn Iterator<Item = PointOffsetType>,
     ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a>;
+    /// Same as `score_points`, but also scores deleted points, if their vectors are still stored.
+    /// Allows to traverse graph through deleted nodes
+    fn score_points_with_deleted<'a>(
+        &'a self,
+        points: &'a mut dyn Iterator<Item = PointOffsetType>,
+    ) -> Box<dyn Iterator<Item = ScoredPointOffset> + 'a> {
+        self.score_points(points)
+    }
     /// Return true if point satisfies current search context (exists and not deleted)
     fn check_point(&self, point: PointOffsetType) -> bool;
     /// Score stored vector with vector under the given index
@@ -46,7 +54,7 @@ pub trait RawScorer {
 /// Trait for vector storage
 /// El - type of vector element, expected numerical type
 /// Storage operates with internal IDs (PointOffsetType), which always starts with zero and have no skips
-pub trait VectorStorage {
+pub trait VectorStorage: Send + Sync {
     fn vector_dim(&self) -> usize;
     fn vector_count(&self) -> usize;
     /// Number of searchable vectors (not deleted)
@@ -63,7 +71,36 @@ pub trait VectorStorage {
     ) -> OperationResult<PointOffsetType>;
     fn update_from(&mut self, other: &dyn VectorStorage)
         -> OperationResult<Range<PointOffsetType>>;
+    /// Put a batch of vectors, returns range of their new ids
+    fn put_vectors(
+        &mut self,
+        vectors: Vec<Vec<VectorElementType>>,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let start_index = self.total_vector_count() as PointOffsetType;
+        let mut end_index = start_index;
+        for vector in vectors {
+            end_index = self.put_vector(vector)? + 1;
+        }
+        Ok(start_index..end_index)
+    }
+    /// Update a batch of vectors, returns new ids in the same order
+    fn update_vectors(
+        &mut self,
+        vectors: Vec<(PointOffsetType, Vec<VectorElementType>)>,
+    ) -> OperationResult<Vec<PointOffsetType>> {
+        vectors
+            .into_iter()
+            .map(|(key, vector)| self.update_vector(key, vector))
+            .collect()
+    }
     fn delete(&mut self, key: PointOffsetType) -> OperationResult<()>;
+    /// Delete a batch of vectors
+    fn delete_vectors(&mut self, keys: &[PointOffsetType]) -> OperationResult<()> {
+        for key in keys {
+            self.delete(*key)?;
+        }
+        Ok(())
+    }
     fn is_deleted(&self, key: PointOffsetType) -> bool;
     fn iter_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_>;
     /// Iterator over not-deleted ids
@@ -71,7 +108,8 @@ pub trait VectorStorage {
 
     /// Generate a RawScorer object which contains all required context for searching similar vector
     fn raw_scorer(&self, vector: Vec<VectorElementType>) -> Box<dyn RawScorer + '_>;
-    /// Same as `raw_scorer` but uses internal vector for search, avoids double pre-processing
+    /// Same as `raw_scorer` but uses internal vector for search, avoids double pre-processing.
+    /// Used to link points into the graph, so it scores original vectors even if storage is quantized
     fn raw_scorer_internal(&self, point_id: PointOffsetType) -> Box<dyn RawScorer + '_>;
 
     fn score_points(
@@ -88,6 +126,21 @@ pub trait VectorStorage {
         top: usize,
     ) -> Vec<ScoredPointOffset>;
 
+    /// Whether `raw_scorer` uses compressed copy of the vectors instead of the original ones
+    fn is_quantized(&self) -> bool {
+        false
+    }
+
+    /// Whether top candidates of the quantized `raw_scorer` should be scored again with `score_points`
+    fn rescore_required(&self) -> bool {
+        false
+    }
+
+    /// Number of candidates of the quantized search, which are rescored to select `top` results
+    fn rescore_limit(&self, top: usize) -> usize {
+        top
+    }
+
     /// Iterator over `n` random ids which are not deleted
     fn sample_ids(&self) -> Box<dyn Iterator<Item = PointOffsetType> + '_> {
         let total = self.total_vector_count() as PointOffsetType;
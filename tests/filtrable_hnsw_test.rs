@@ -1,17 +1,24 @@
 #[cfg(test)]
 mod tests {
     use atomic_refcell::AtomicRefCell;
+    use itertools::Itertools;
+    use rand::rngs::ThreadRng;
     use rand::{thread_rng, Rng};
     use nuclia_vectors::entry::entry_point::SegmentEntry;
     use nuclia_vectors::fixtures::payload_fixtures::{random_int_payload, random_vector};
     use nuclia_vectors::index::hnsw_index::hnsw::HNSWIndex;
     use nuclia_vectors::index::{VectorIndex};
-    use nuclia_vectors::segment_constructor::build_segment;
+    use nuclia_vectors::segment::Segment;
+    use nuclia_vectors::segment_constructor::segment_builder::SegmentBuilder;
+    use nuclia_vectors::segment_constructor::{build_segment, load_segment};
     use nuclia_vectors::types::{
-        Condition, Distance, FieldCondition, HnswConfig, Indexes, PayloadIndexType,
-        PayloadKeyType, PayloadType, PointIdType, Range, SearchParams, SegmentConfig,
-        SeqNumberType, StorageType, TheMap,
+        BinaryQuantizationConfig, Condition, Distance, FieldCondition, Filter, HnswConfig, Indexes,
+        PayloadIndexType, PayloadKeyType, PayloadType, PointIdType, ProductQuantizationConfig,
+        QuantizationConfig, Range, ScalarQuantizationConfig, ScoreType, SearchParams,
+        SegmentConfig, SeqNumberType, StorageType, TheMap, VectorElementType, WithPayload,
     };
+    use std::convert::TryInto;
+    use std::path::Path;
     use std::sync::Arc;
     use tempdir::TempDir;
 
@@ -19,7 +26,7 @@ mod tests {
     fn test_filterable_hnsw() {
         let dim = 8;
         let m = 8;
-        let num_vectors: PointIdType = 5_000;
+        let num_vectors: u64 = 5_000;
         let ef = 32;
         let ef_construct = 16;
         let distance = Distance::Cosine;
@@ -37,6 +44,7 @@ mod tests {
             index: Indexes::Plain {},
             payload_index: Some(PayloadIndexType::Plain),
             storage_type: StorageType::InMemory,
+            quantization: None,
             distance,
         };
 
@@ -52,7 +60,7 @@ mod tests {
             );
 
             segment
-                .upsert_point(idx as SeqNumberType, idx, &vector)
+                .upsert_point(idx as SeqNumberType, idx.into(), &vector)
                 .unwrap();
         }
         // let opnum = num_vectors + 1;
@@ -61,11 +69,15 @@ mod tests {
             m,
             ef_construct,
             full_scan_threshold: indexing_threshold,
+            max_indexing_threads: 0,
+            compress_links: false,
         };
 
         let mut hnsw_index = HNSWIndex::open(
             hnsw_dir.path(),
             segment.vector_storage.clone(),
+            segment.condition_checker.clone(),
+            segment.payload_index.clone(),
             hnsw_config,
         )
         .unwrap();
@@ -81,6 +93,7 @@ mod tests {
 
             let index_result = hnsw_index.search_with_graph(
                 &query,
+                None,
                 top,
                 Some(&SearchParams { hnsw_ef: Some(ef) }),
             );
@@ -89,7 +102,7 @@ mod tests {
                 segment
                     .vector_index
                     .borrow()
-                    .search(&query, top, None);
+                    .search(&query, None, top, None);
 
             if plain_result == index_result {
                 hits += 1;
@@ -98,4 +111,675 @@ mod tests {
         assert!(attempts - hits < 5, "hits: {} of {}", hits, attempts); // Not more than 5% failures
         eprintln!("hits = {:#?} out of {}", hits, attempts);
     }
+
+    const INT_KEY: &str = "int";
+
+    fn graph_test_config(max_indexing_threads: usize, compress_links: bool) -> HnswConfig {
+        HnswConfig {
+            m: 8,
+            ef_construct: 16,
+            full_scan_threshold: 500,
+            max_indexing_threads,
+            compress_links,
+        }
+    }
+
+    /// Build plain segment with random vectors and indexed random integer payload under `INT_KEY`
+    fn build_payload_segment(
+        dir: &Path,
+        dim: usize,
+        num_vectors: u64,
+        rnd: &mut ThreadRng,
+    ) -> Segment {
+        let config = SegmentConfig {
+            vector_size: dim,
+            index: Indexes::Plain {},
+            payload_index: Some(PayloadIndexType::Struct),
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            distance: Distance::Cosine,
+        };
+        let mut segment = build_segment(dir, &config, false).unwrap();
+        for idx in 0..num_vectors {
+            let vector = random_vector(rnd, dim);
+            let mut payload: TheMap<PayloadKeyType, PayloadType> = Default::default();
+            payload.insert(INT_KEY.to_string(), random_int_payload(rnd, 2));
+            segment.upsert_point(idx, idx.into(), &vector).unwrap();
+            segment.set_full_payload(idx, idx.into(), payload).unwrap();
+        }
+        segment.create_field_index(num_vectors, INT_KEY).unwrap();
+        segment
+    }
+
+    fn open_hnsw_index(dir: &Path, segment: &Segment, hnsw_config: HnswConfig) -> HNSWIndex {
+        HNSWIndex::open(
+            dir,
+            segment.vector_storage.clone(),
+            segment.condition_checker.clone(),
+            segment.payload_index.clone(),
+            hnsw_config,
+        )
+        .unwrap()
+    }
+
+    /// Filter by `INT_KEY`, which matches values in `[left, left + width]`
+    fn int_range_filter(left: i64, width: i64) -> Filter {
+        Filter::new_must(Condition::Field(FieldCondition {
+            key: INT_KEY.to_string(),
+            r#match: None,
+            range: Some(Range {
+                lt: None,
+                gt: None,
+                gte: Some(left as f64),
+                lte: Some((left + width) as f64),
+            }),
+            geo_bounding_box: None,
+            geo_radius: None,
+        }))
+    }
+
+    /// Number of random queries, for which the index returns the same result as plain search
+    fn count_hits(
+        segment: &Segment,
+        hnsw_index: &HNSWIndex,
+        filter: Option<&Filter>,
+        top: usize,
+        ef: usize,
+        rnd: &mut ThreadRng,
+    ) -> usize {
+        let params = SearchParams { hnsw_ef: Some(ef) };
+        let dim = segment.segment_config.vector_size;
+        (0..ATTEMPTS)
+            .filter(|_| {
+                let query = random_vector(rnd, dim);
+                let index_result = hnsw_index.search(&query, filter, top, Some(&params));
+                let plain_result = segment
+                    .vector_index
+                    .borrow()
+                    .search(&query, filter, top, None);
+                index_result == plain_result
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_filterable_hnsw_single_thread() {
+        let mut rnd = thread_rng();
+
+        let dir = TempDir::new("segment_dir").unwrap();
+        let hnsw_dir = TempDir::new("hnsw_dir").unwrap();
+        let segment = build_payload_segment(dir.path(), 8, 5_000, &mut rnd);
+
+        let mut hnsw_index =
+            open_hnsw_index(hnsw_dir.path(), &segment, graph_test_config(1, false));
+        hnsw_index.build_index().unwrap();
+
+        let hits = count_hits(&segment, &hnsw_index, None, 3, 32, &mut rnd);
+        assert!(ATTEMPTS - hits < 5, "hits: {} of {}", hits, ATTEMPTS);
+    }
+
+    #[test]
+    fn test_filtered_hnsw_search() {
+        let top = 3;
+        let ef = 32;
+
+        let mut rnd = thread_rng();
+
+        let dir = TempDir::new("segment_dir").unwrap();
+        let hnsw_dir = TempDir::new("hnsw_dir").unwrap();
+        let segment = build_payload_segment(dir.path(), 8, 5_000, &mut rnd);
+
+        let mut hnsw_index =
+            open_hnsw_index(hnsw_dir.path(), &segment, graph_test_config(0, false));
+        hnsw_index.build_index().unwrap();
+
+        let params = SearchParams { hnsw_ef: Some(ef) };
+        let mut filtered_hits = 0;
+        for _i in 0..ATTEMPTS {
+            let query = random_vector(&mut rnd, 8);
+
+            // Very selective filter should be processed with exact plain search
+            let filter = int_range_filter(rnd.gen_range(0..400), 2);
+            let filtered_index_result =
+                hnsw_index.search(&query, Some(&filter), top, Some(&params));
+            let filtered_plain_result =
+                segment
+                    .vector_index
+                    .borrow()
+                    .search(&query, Some(&filter), top, None);
+            assert_eq!(filtered_index_result, filtered_plain_result);
+
+            // Wide filter is processed with graph search, which relies on payload block links
+            let filter = int_range_filter(rnd.gen_range(0..400), 100);
+            let filtered_index_result =
+                hnsw_index.search(&query, Some(&filter), top, Some(&params));
+            let filtered_plain_result =
+                segment
+                    .vector_index
+                    .borrow()
+                    .search(&query, Some(&filter), top, None);
+            if filtered_index_result == filtered_plain_result {
+                filtered_hits += 1;
+            }
+        }
+        assert!(
+            ATTEMPTS - filtered_hits < 5,
+            "filtered hits: {} of {}",
+            filtered_hits,
+            ATTEMPTS
+        );
+    }
+
+    #[test]
+    fn test_compressed_hnsw_links() {
+        let top = 3;
+        let ef = 32;
+
+        let mut rnd = thread_rng();
+
+        let dir = TempDir::new("segment_dir").unwrap();
+        let hnsw_dir = TempDir::new("hnsw_dir").unwrap();
+        let segment = build_payload_segment(dir.path(), 8, 5_000, &mut rnd);
+        let hnsw_config = graph_test_config(0, true);
+
+        let mut hnsw_index = open_hnsw_index(hnsw_dir.path(), &segment, hnsw_config);
+        hnsw_index.build_index().unwrap();
+
+        let hits = count_hits(&segment, &hnsw_index, None, top, ef, &mut rnd);
+        assert!(ATTEMPTS - hits < 5, "hits: {} of {}", hits, ATTEMPTS);
+        let filter = int_range_filter(0, 300);
+        let hits = count_hits(&segment, &hnsw_index, Some(&filter), top, ef, &mut rnd);
+        assert!(
+            ATTEMPTS - hits < 5,
+            "filtered hits: {} of {}",
+            hits,
+            ATTEMPTS
+        );
+    }
+
+    #[test]
+    fn test_reopened_hnsw_index() {
+        let top = 3;
+        let ef = 32;
+
+        let mut rnd = thread_rng();
+
+        let dir = TempDir::new("segment_dir").unwrap();
+        let segment = build_payload_segment(dir.path(), 8, 5_000, &mut rnd);
+
+        for &compress_links in &[false, true] {
+            let hnsw_dir = TempDir::new("hnsw_dir").unwrap();
+            let hnsw_config = graph_test_config(0, compress_links);
+            let mut hnsw_index = open_hnsw_index(hnsw_dir.path(), &segment, hnsw_config);
+            hnsw_index.build_index().unwrap();
+
+            // Links are stored uncompressed and packed again on opening
+            let reopened_index = open_hnsw_index(hnsw_dir.path(), &segment, hnsw_config);
+
+            let params = SearchParams { hnsw_ef: Some(ef) };
+            let filter = int_range_filter(0, 300);
+            for _i in 0..ATTEMPTS {
+                let query = random_vector(&mut rnd, 8);
+                assert_eq!(
+                    hnsw_index.search_with_graph(&query, None, top, Some(&params)),
+                    reopened_index.search_with_graph(&query, None, top, Some(&params)),
+                );
+                assert_eq!(
+                    hnsw_index.search(&query, Some(&filter), top, Some(&params)),
+                    reopened_index.search(&query, Some(&filter), top, Some(&params)),
+                );
+            }
+        }
+    }
+
+    const PAYLOAD_KEY: &str = "num";
+    const ATTEMPTS: usize = 100;
+
+    /// Payload of the test point, see `insert_points`
+    fn payload_value(idx: u64) -> i64 {
+        (idx % 10) as i64
+    }
+
+    /// Filter, which matches points with `payload_value(idx) < 8`
+    fn payload_filter() -> Filter {
+        Filter::new_must(Condition::Field(FieldCondition {
+            key: PAYLOAD_KEY.to_string(),
+            r#match: None,
+            range: Some(Range {
+                lt: Some(8.0),
+                gt: None,
+                gte: None,
+                lte: None,
+            }),
+            geo_bounding_box: None,
+            geo_radius: None,
+        }))
+    }
+
+    /// Insert vectors with ids equal to their positions and payload, given by `payload_value`
+    fn insert_points(segment: &mut Segment, vectors: &[Vec<VectorElementType>]) {
+        let batch: Vec<_> = vectors
+            .iter()
+            .enumerate()
+            .map(|(idx, vector)| ((idx as u64).into(), vector.clone()))
+            .collect();
+        segment.upsert_points(1, &batch).unwrap();
+        for idx in 0..vectors.len() as u64 {
+            segment
+                .set_payload(
+                    2,
+                    idx.into(),
+                    PAYLOAD_KEY,
+                    PayloadType::Integer(vec![payload_value(idx)]),
+                )
+                .unwrap();
+        }
+        segment.create_field_index(3, PAYLOAD_KEY).unwrap();
+    }
+
+    /// Build segment with random vectors, see `insert_points`.
+    /// Mmap storage does not accept single points, so it is filled by the segment builder
+    fn build_test_segment(
+        dir: &Path,
+        config: &SegmentConfig,
+        num_vectors: u64,
+        rnd: &mut ThreadRng,
+    ) -> (Segment, Vec<Vec<VectorElementType>>) {
+        let vectors: Vec<_> = (0..num_vectors)
+            .map(|_| random_vector(rnd, config.vector_size))
+            .collect();
+        if config.storage_type != StorageType::Mmap {
+            let mut segment = build_segment(dir, config, false).unwrap();
+            insert_points(&mut segment, &vectors);
+            return (segment, vectors);
+        }
+
+        let source_dir = TempDir::new("source_segment_dir").unwrap();
+        let temp_dir = TempDir::new("segment_temp_dir").unwrap();
+        let source_config = SegmentConfig {
+            index: Indexes::Plain {},
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            ..config.clone()
+        };
+        let mut source_segment = build_segment(source_dir.path(), &source_config, false).unwrap();
+        insert_points(&mut source_segment, &vectors);
+        let mut builder = SegmentBuilder::new(dir, temp_dir.path(), config, false).unwrap();
+        builder.update_from(&source_segment).unwrap();
+        (builder.try_into().unwrap(), vectors)
+    }
+
+    fn cosine(a: &[VectorElementType], b: &[VectorElementType]) -> ScoreType {
+        let norm = |x: &[VectorElementType]| x.iter().map(|v| v * v).sum::<ScoreType>().sqrt();
+        a.iter().zip(b).map(|(x, y)| x * y).sum::<ScoreType>() / (norm(a) * norm(b))
+    }
+
+    fn search_ids(
+        segment: &Segment,
+        query: &[VectorElementType],
+        filter: Option<&Filter>,
+        top: usize,
+        ef: usize,
+    ) -> Vec<PointIdType> {
+        segment
+            .search(
+                query,
+                &WithPayload::default(),
+                false,
+                filter,
+                top,
+                Some(&SearchParams { hnsw_ef: Some(ef) }),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|x| x.id)
+            .collect()
+    }
+
+    /// Ids of the closest `top` vectors among expected ones, found by full scan
+    fn exact_ids(
+        vectors: &[Vec<VectorElementType>],
+        query: &[VectorElementType],
+        is_expected: impl Fn(u64) -> bool,
+        top: usize,
+    ) -> Vec<PointIdType> {
+        (0..vectors.len() as u64)
+            .filter(|idx| is_expected(*idx))
+            .map(|idx| (idx, cosine(query, &vectors[idx as usize])))
+            .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+            .take(top)
+            .map(|(idx, _)| idx.into())
+            .collect()
+    }
+
+    /// Check that search of random queries mostly returns the exact top of the points,
+    /// selected by `is_expected`. Vector ids are equal to their positions in `vectors`
+    fn assert_exact_hits(
+        segment: &Segment,
+        vectors: &[Vec<VectorElementType>],
+        filter: Option<&Filter>,
+        is_expected: impl Fn(u64) -> bool,
+        top: usize,
+        ef: usize,
+        rnd: &mut ThreadRng,
+    ) {
+        let mut hits = 0;
+        for _i in 0..ATTEMPTS {
+            let query = random_vector(rnd, vectors[0].len());
+            let exact = exact_ids(vectors, &query, &is_expected, top);
+            if search_ids(segment, &query, filter, top, ef) == exact {
+                hits += 1;
+            }
+        }
+        // Not more than 5% failures
+        assert!(ATTEMPTS - hits < 5, "hits: {} of {}", hits, ATTEMPTS);
+    }
+
+    /// Check that at least `min_recall` of the exact `top` results of random queries is found
+    #[allow(clippy::too_many_arguments)]
+    fn assert_recall(
+        segment: &Segment,
+        vectors: &[Vec<VectorElementType>],
+        filter: Option<&Filter>,
+        is_expected: impl Fn(u64) -> bool,
+        top: usize,
+        ef: usize,
+        min_recall: f64,
+        rnd: &mut ThreadRng,
+    ) {
+        let mut found = 0;
+        for _i in 0..ATTEMPTS {
+            let query = random_vector(rnd, vectors[0].len());
+            let result = search_ids(segment, &query, filter, top, ef);
+            found += exact_ids(vectors, &query, &is_expected, top)
+                .iter()
+                .filter(|id| result.contains(id))
+                .count();
+        }
+        let recall = found as f64 / (ATTEMPTS * top) as f64;
+        assert!(recall >= min_recall, "recall: {}", recall);
+    }
+
+    /// Check that reopened segment returns the same results, with and without filter
+    fn assert_same_after_reopen(segment: Segment, top: usize, ef: usize, rnd: &mut ThreadRng) {
+        let filter = payload_filter();
+        let queries: Vec<_> = (0..ATTEMPTS)
+            .map(|_| random_vector(rnd, segment.segment_config.vector_size))
+            .collect();
+        let search_all = |segment: &Segment| {
+            queries
+                .iter()
+                .map(|query| {
+                    (
+                        search_ids(segment, query, None, top, ef),
+                        search_ids(segment, query, Some(&filter), top, ef),
+                    )
+                })
+                .collect_vec()
+        };
+
+        let results = search_all(&segment);
+        segment.flush().unwrap();
+        let path = segment.current_path.clone();
+        drop(segment);
+
+        let segment = load_segment(&path, false).unwrap();
+        assert_eq!(results, search_all(&segment));
+    }
+
+    #[test]
+    fn test_incremental_hnsw() {
+        let dim = 8;
+        let num_vectors: u64 = 2_000;
+        let ef = 32;
+        let top = 3;
+
+        let mut rnd = thread_rng();
+
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let config = SegmentConfig {
+            vector_size: dim,
+            index: Indexes::Hnsw(HnswConfig {
+                m: 8,
+                ef_construct: 16,
+                full_scan_threshold: 500,
+                max_indexing_threads: 0,
+                compress_links: false,
+            }),
+            payload_index: Some(PayloadIndexType::Struct),
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            distance: Distance::Cosine,
+        };
+
+        let mut segment = build_segment(dir.path(), &config, false).unwrap();
+        assert!(segment.is_appendable());
+
+        let vectors: Vec<_> = (0..num_vectors)
+            .map(|_| random_vector(&mut rnd, dim))
+            .collect();
+        for idx in 0..num_vectors / 2 {
+            segment
+                .upsert_point(idx, idx.into(), &vectors[idx as usize])
+                .unwrap();
+        }
+        let batch: Vec<_> = (num_vectors / 2..num_vectors)
+            .map(|idx| (idx.into(), vectors[idx as usize].clone()))
+            .collect();
+        segment.upsert_points(num_vectors, &batch).unwrap();
+
+        assert_exact_hits(&segment, &vectors, None, |_| true, top, ef, &mut rnd);
+
+        // Deleted points are not returned, the rest stays reachable through the graph
+        let deleted: Vec<_> = (0..num_vectors)
+            .filter(|idx| idx % 2 == 0)
+            .map(|idx| idx.into())
+            .collect();
+        segment.delete_points(num_vectors + 1, &deleted).unwrap();
+        for _i in 0..ATTEMPTS {
+            let query = random_vector(&mut rnd, dim);
+            let res = search_ids(&segment, &query, None, top, ef);
+            assert_eq!(res.len(), top);
+            assert!(res.iter().all(|x| !deleted.contains(x)));
+        }
+
+        // Filtered search passes through deleted points, which have no payload anymore.
+        // Points, which do not match the filter, are not traversed, so the search needs wider beam
+        for idx in (0..num_vectors).filter(|idx| idx % 2 == 1) {
+            segment
+                .set_payload(
+                    num_vectors + 2,
+                    idx.into(),
+                    PAYLOAD_KEY,
+                    PayloadType::Integer(vec![payload_value(idx)]),
+                )
+                .unwrap();
+        }
+        assert_exact_hits(
+            &segment,
+            &vectors,
+            Some(&payload_filter()),
+            |idx| idx % 2 == 1 && payload_value(idx) < 8,
+            top,
+            4 * ef,
+            &mut rnd,
+        );
+
+        // Changed vector is re-linked and found by its new value
+        let new_vector = random_vector(&mut rnd, dim);
+        segment
+            .upsert_point(num_vectors + 3, 11.into(), &new_vector)
+            .unwrap();
+        segment.flush().unwrap();
+        drop(segment);
+
+        let segment = load_segment(dir.path(), false).unwrap();
+        let res = search_ids(&segment, &new_vector, None, 1, ef);
+        assert_eq!(res[0], 11.into());
+    }
+
+    #[test]
+    fn test_quantization_requires_disk_storage() {
+        let dir = TempDir::new("segment_dir").unwrap();
+        let config = SegmentConfig {
+            vector_size: 8,
+            index: Indexes::Plain {},
+            payload_index: Some(PayloadIndexType::Struct),
+            storage_type: StorageType::InMemory,
+            quantization: Some(QuantizationConfig::Scalar(Default::default())),
+            distance: Distance::Cosine,
+        };
+        assert!(build_segment(dir.path(), &config, false).is_err());
+    }
+
+    #[test]
+    fn test_quantized_hnsw() {
+        let dim = 8;
+        let num_vectors: u64 = 2_000;
+        let ef = 64;
+        let top = 3;
+
+        let mut rnd = thread_rng();
+
+        for &storage_type in &[StorageType::Drive, StorageType::Mmap] {
+            let dir = TempDir::new("segment_dir").unwrap();
+            let config = SegmentConfig {
+                vector_size: dim,
+                index: Indexes::Hnsw(HnswConfig {
+                    m: 8,
+                    ef_construct: 32,
+                    full_scan_threshold: 500,
+                    max_indexing_threads: 0,
+                    compress_links: false,
+                }),
+                payload_index: Some(PayloadIndexType::Struct),
+                storage_type,
+                quantization: Some(QuantizationConfig::Scalar(ScalarQuantizationConfig {
+                    per_dimension: true,
+                    rescore: true,
+                })),
+                distance: Distance::Cosine,
+            };
+
+            let (segment, vectors) = build_test_segment(dir.path(), &config, num_vectors, &mut rnd);
+
+            // Rescored results have exact scores of the original vectors
+            assert_exact_hits(&segment, &vectors, None, |_| true, top, ef, &mut rnd);
+            assert_exact_hits(
+                &segment,
+                &vectors,
+                Some(&payload_filter()),
+                |idx| payload_value(idx) < 8,
+                top,
+                ef,
+                &mut rnd,
+            );
+            assert_same_after_reopen(segment, top, ef, &mut rnd);
+        }
+    }
+
+    #[test]
+    fn test_product_quantized_search() {
+        let dim = 8;
+        let num_vectors: u64 = 2_000;
+        let ef = 64;
+        let top = 3;
+
+        let mut rnd = thread_rng();
+
+        let hnsw_config = HnswConfig {
+            m: 8,
+            ef_construct: 32,
+            full_scan_threshold: 500,
+            max_indexing_threads: 0,
+            compress_links: false,
+        };
+        let quantization = QuantizationConfig::Product(ProductQuantizationConfig {
+            subvector_size: 2,
+            rescore: true,
+        });
+
+        for index in &[Indexes::Plain {}, Indexes::Hnsw(hnsw_config)] {
+            for &storage_type in &[StorageType::Drive, StorageType::Mmap] {
+                let dir = TempDir::new("segment_dir").unwrap();
+                let config = SegmentConfig {
+                    vector_size: dim,
+                    index: *index,
+                    payload_index: Some(PayloadIndexType::Struct),
+                    storage_type,
+                    quantization: Some(quantization),
+                    distance: Distance::Cosine,
+                };
+
+                let (segment, vectors) =
+                    build_test_segment(dir.path(), &config, num_vectors, &mut rnd);
+
+                assert_exact_hits(&segment, &vectors, None, |_| true, top, ef, &mut rnd);
+                assert_exact_hits(
+                    &segment,
+                    &vectors,
+                    Some(&payload_filter()),
+                    |idx| payload_value(idx) < 8,
+                    top,
+                    ef,
+                    &mut rnd,
+                );
+                // Codebooks are not fitted again on reopening, so the results are the same
+                assert_same_after_reopen(segment, top, ef, &mut rnd);
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_quantized_search() {
+        let dim = 128;
+        let num_vectors: u64 = 1_000;
+        let ef = 64;
+        let top = 10;
+
+        let mut rnd = thread_rng();
+
+        let hnsw_config = HnswConfig {
+            m: 8,
+            ef_construct: 16,
+            full_scan_threshold: 500,
+            max_indexing_threads: 0,
+            compress_links: false,
+        };
+        let quantization =
+            QuantizationConfig::Binary(BinaryQuantizationConfig { oversampling: 20 });
+
+        for index in &[Indexes::Plain {}, Indexes::Hnsw(hnsw_config)] {
+            for &storage_type in &[StorageType::Drive, StorageType::Mmap] {
+                let dir = TempDir::new("segment_dir").unwrap();
+                let config = SegmentConfig {
+                    vector_size: dim,
+                    index: *index,
+                    payload_index: Some(PayloadIndexType::Struct),
+                    storage_type,
+                    quantization: Some(quantization),
+                    distance: Distance::Cosine,
+                };
+
+                let (segment, vectors) =
+                    build_test_segment(dir.path(), &config, num_vectors, &mut rnd);
+
+                // Neighbours of random vectors are almost equally far, so Hamming prefilter may miss
+                // some of them, but rescored candidates must contain most of the exact results
+                assert_recall(&segment, &vectors, None, |_| true, top, ef, 0.8, &mut rnd);
+                assert_recall(
+                    &segment,
+                    &vectors,
+                    Some(&payload_filter()),
+                    |idx| payload_value(idx) < 8,
+                    top,
+                    ef,
+                    0.8,
+                    &mut rnd,
+                );
+                assert_same_after_reopen(segment, top, ef, &mut rnd);
+            }
+        }
+    }
 }
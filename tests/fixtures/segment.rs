@@ -18,11 +18,11 @@ pub fn build_segment_1(path: &Path) -> Segment {
     let vec4 = vec![1.0, 1.0, 0.0, 1.0];
     let vec5 = vec![1.0, 0.0, 0.0, 0.0];
 
-    segment1.upsert_point(1, 1, &vec1).unwrap();
-    segment1.upsert_point(2, 2, &vec2).unwrap();
-    segment1.upsert_point(3, 3, &vec3).unwrap();
-    segment1.upsert_point(4, 4, &vec4).unwrap();
-    segment1.upsert_point(5, 5, &vec5).unwrap();
+    segment1.upsert_point(1, 1.into(), &vec1).unwrap();
+    segment1.upsert_point(2, 2.into(), &vec2).unwrap();
+    segment1.upsert_point(3, 3.into(), &vec3).unwrap();
+    segment1.upsert_point(4, 4.into(), &vec4).unwrap();
+    segment1.upsert_point(5, 5.into(), &vec5).unwrap();
 
     segment1
 }
@@ -37,11 +37,11 @@ pub fn build_segment_2(path: &Path) -> Segment {
     let vec4 = vec![-1.0, 1.0, 0.0, 1.0];
     let vec5 = vec![-1.0, 0.0, 0.0, 0.0];
 
-    segment2.upsert_point(11, 11, &vec1).unwrap();
-    segment2.upsert_point(12, 12, &vec2).unwrap();
-    segment2.upsert_point(13, 13, &vec3).unwrap();
-    segment2.upsert_point(14, 14, &vec4).unwrap();
-    segment2.upsert_point(15, 15, &vec5).unwrap();
+    segment2.upsert_point(11, 11.into(), &vec1).unwrap();
+    segment2.upsert_point(12, 12.into(), &vec2).unwrap();
+    segment2.upsert_point(13, 13.into(), &vec3).unwrap();
+    segment2.upsert_point(14, 14.into(), &vec4).unwrap();
+    segment2.upsert_point(15, 15.into(), &vec5).unwrap();
     
     segment2
 }
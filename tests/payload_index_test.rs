@@ -0,0 +1,597 @@
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use nuclia_vectors::entry::entry_point::SegmentEntry;
+    use nuclia_vectors::fixtures::payload_fixtures::{
+        random_filter, random_geo_condition, random_geo_payload, random_int_payload,
+        random_keyword_payload, random_must_filter, random_text_condition, random_vector,
+    };
+    use nuclia_vectors::segment::Segment;
+    use nuclia_vectors::segment_constructor::segment_builder::SegmentBuilder;
+    use nuclia_vectors::segment_constructor::{build_segment, load_segment};
+    use nuclia_vectors::types::{
+        Condition, Distance, FacetValue, FieldCondition, Filter, Indexes, IsEmptyCondition, Match,
+        PayloadField, PayloadIndexType, PayloadKeyType, PayloadType, Range, SegmentConfig,
+        StorageType, TheMap, WithPayload,
+    };
+    use rand::prelude::ThreadRng;
+    use rand::{thread_rng, Rng};
+    use std::convert::TryInto;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    fn build_test_segments(
+        path_struct: &Path,
+        path_plain: &Path,
+        rnd: &mut ThreadRng,
+    ) -> (Segment, Segment) {
+        let dim = 5;
+
+        let mut config = SegmentConfig {
+            vector_size: dim,
+            index: Indexes::Plain {},
+            payload_index: Some(PayloadIndexType::Plain),
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            distance: Distance::Dot,
+        };
+
+        let mut plain_segment = build_segment(path_plain, &config, false).unwrap();
+        config.payload_index = Some(PayloadIndexType::Struct);
+        let mut struct_segment = build_segment(path_struct, &config, false).unwrap();
+
+        let num_points = 2000;
+        let num_int_values = 2;
+
+        let mut opnum = 0;
+        struct_segment.create_field_index(opnum, "kvd").unwrap();
+
+        for idx in 0..num_points {
+            let vector = random_vector(rnd, dim);
+            let mut payload: TheMap<PayloadKeyType, PayloadType> = Default::default();
+            payload.insert("kvd".to_string(), random_keyword_payload(rnd));
+            payload.insert("int".to_string(), random_int_payload(rnd, num_int_values));
+            payload.insert("geo".to_string(), random_geo_payload(rnd, 1));
+
+            plain_segment
+                .upsert_point(idx, idx.into(), &vector)
+                .unwrap();
+            struct_segment
+                .upsert_point(idx, idx.into(), &vector)
+                .unwrap();
+
+            plain_segment
+                .set_full_payload(idx, idx.into(), payload.clone())
+                .unwrap();
+            struct_segment
+                .set_full_payload(idx, idx.into(), payload)
+                .unwrap();
+
+            opnum += 1;
+        }
+
+        // Index is built for existing points as well as updated for the new ones
+        struct_segment.create_field_index(opnum, "int").unwrap();
+        struct_segment.create_field_index(opnum, "geo").unwrap();
+
+        (struct_segment, plain_segment)
+    }
+
+    #[test]
+    fn test_cardinality_estimation() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, _) = build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        for _ in 0..100 {
+            let filter = random_must_filter(&mut rnd, 1);
+
+            let estimation = struct_segment
+                .payload_index
+                .borrow()
+                .estimate_cardinality(&filter);
+
+            let exact = struct_segment
+                .payload_index
+                .borrow()
+                .query_points(&filter)
+                .count();
+
+            assert!(!estimation.primary_clauses.is_empty());
+            assert!(estimation.min <= exact);
+            assert!(exact <= estimation.max);
+        }
+    }
+
+    #[test]
+    fn test_struct_payload_index() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, plain_segment) =
+            build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        assert_eq!(
+            struct_segment
+                .get_indexed_fields()
+                .into_iter()
+                .sorted()
+                .collect_vec(),
+            vec!["geo".to_string(), "int".to_string(), "kvd".to_string()]
+        );
+
+        let attempts = 100;
+        for _ in 0..attempts {
+            let query_vector = random_vector(&mut rnd, 5);
+            let query_filter = random_filter(&mut rnd);
+
+            let plain_result = plain_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+            let struct_result = struct_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+
+            plain_result
+                .iter()
+                .zip(struct_result.iter())
+                .for_each(|(r1, r2)| {
+                    assert_eq!(r1.id, r2.id);
+                    assert!((r1.score - r2.score) < 0.0001)
+                });
+            assert_eq!(plain_result.len(), struct_result.len());
+        }
+    }
+
+    #[test]
+    fn test_struct_payload_geo_index() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, plain_segment) =
+            build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        for _ in 0..100 {
+            let query_vector = random_vector(&mut rnd, 5);
+            let query_filter = Filter::new_must(random_geo_condition(&mut rnd));
+
+            let estimation = struct_segment
+                .payload_index
+                .borrow()
+                .estimate_cardinality(&query_filter);
+            let exact = plain_segment
+                .payload_index
+                .borrow()
+                .query_points(&query_filter)
+                .count();
+            assert!(!estimation.primary_clauses.is_empty());
+            assert!(estimation.min <= exact);
+            assert!(exact <= estimation.max);
+
+            let plain_result = plain_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+            let struct_result = struct_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+
+            assert_eq!(
+                plain_result.iter().map(|x| x.id.clone()).collect_vec(),
+                struct_result.iter().map(|x| x.id.clone()).collect_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_struct_payload_text_index() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, plain_segment) =
+            build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        for _ in 0..100 {
+            let query_vector = random_vector(&mut rnd, 5);
+            let query_filter = Filter::new_must(random_text_condition(&mut rnd));
+
+            let estimation = struct_segment
+                .payload_index
+                .borrow()
+                .estimate_cardinality(&query_filter);
+            let exact = plain_segment
+                .payload_index
+                .borrow()
+                .query_points(&query_filter)
+                .count();
+            assert!(exact > 0);
+            assert!(!estimation.primary_clauses.is_empty());
+            assert!(estimation.min <= exact);
+            assert!(exact <= estimation.max);
+
+            let plain_result = plain_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+            let struct_result = struct_segment
+                .search(
+                    &query_vector,
+                    &WithPayload::default(),
+                    false,
+                    Some(&query_filter),
+                    5,
+                    None,
+                )
+                .unwrap();
+
+            assert_eq!(
+                plain_result.iter().map(|x| x.id.clone()).collect_vec(),
+                struct_result.iter().map(|x| x.id.clone()).collect_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_datetime_and_bool_index() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let mut config = SegmentConfig {
+            vector_size: 5,
+            index: Indexes::Plain {},
+            payload_index: Some(PayloadIndexType::Plain),
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            distance: Distance::Dot,
+        };
+        let mut plain_segment = build_segment(dir1.path(), &config, false).unwrap();
+        config.payload_index = Some(PayloadIndexType::Struct);
+        let mut struct_segment = build_segment(dir2.path(), &config, false).unwrap();
+
+        struct_segment.create_field_index(0, "created_at").unwrap();
+        struct_segment.create_field_index(0, "published").unwrap();
+
+        let base_timestamp = 1_600_000_000;
+        for idx in 0..1000 {
+            let vector = random_vector(&mut rnd, 5);
+            let mut payload: TheMap<PayloadKeyType, PayloadType> = Default::default();
+            payload.insert(
+                "created_at".to_string(),
+                PayloadType::Datetime(vec![base_timestamp + rnd.gen_range(0..86_400 * 30)]),
+            );
+            if rnd.gen_bool(0.8) {
+                payload.insert("published".to_string(), PayloadType::Bool(vec![rnd.gen()]));
+            }
+            for segment in [&mut plain_segment, &mut struct_segment].iter_mut() {
+                segment.upsert_point(idx + 1, idx.into(), &vector).unwrap();
+                segment
+                    .set_full_payload(idx + 1, idx.into(), payload.clone())
+                    .unwrap();
+            }
+        }
+
+        let published = Condition::Field(FieldCondition {
+            key: "published".to_string(),
+            r#match: Some(Match {
+                keyword: None,
+                integer: None,
+                text: None,
+                boolean: Some(true),
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        });
+        let first_week = Condition::Field(FieldCondition {
+            key: "created_at".to_string(),
+            r#match: None,
+            range: Some(Range {
+                lt: Some((base_timestamp + 86_400 * 7) as f64),
+                gt: None,
+                gte: None,
+                lte: None,
+            }),
+            geo_bounding_box: None,
+            geo_radius: None,
+        });
+        let not_set = Condition::IsEmpty(IsEmptyCondition {
+            is_empty: PayloadField {
+                key: "published".to_string(),
+            },
+        });
+
+        for condition in vec![published.clone(), first_week.clone(), not_set] {
+            let filter = Filter::new_must(condition);
+            let plain_points = plain_segment
+                .payload_index
+                .borrow()
+                .query_points(&filter)
+                .collect_vec();
+            let struct_points = struct_segment
+                .payload_index
+                .borrow()
+                .query_points(&filter)
+                .sorted()
+                .collect_vec();
+            assert!(!plain_points.is_empty());
+            assert_eq!(plain_points, struct_points);
+        }
+
+        let filter = Filter {
+            should: None,
+            must: Some(vec![published, first_week]),
+            must_not: None,
+        };
+        let estimation = struct_segment
+            .payload_index
+            .borrow()
+            .estimate_cardinality(&filter);
+        assert!(!estimation.primary_clauses.is_empty());
+    }
+
+    #[test]
+    fn test_count() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, plain_segment) =
+            build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        let total = struct_segment.vectors_count();
+        assert_eq!(struct_segment.count(None, true).unwrap(), total);
+        assert_eq!(struct_segment.count(None, false).unwrap(), total);
+
+        for _ in 0..50 {
+            let filter = random_filter(&mut rnd);
+
+            let exact = plain_segment.count(Some(&filter), true).unwrap();
+            assert_eq!(struct_segment.count(Some(&filter), true).unwrap(), exact);
+
+            for segment in &[&struct_segment, &plain_segment] {
+                let approximate = segment.count(Some(&filter), false).unwrap();
+                assert!(
+                    (approximate as i64 - exact as i64).abs() < (total / 10) as i64,
+                    "approximate: {}, exact: {}",
+                    approximate,
+                    exact
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_facet() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (struct_segment, plain_segment) =
+            build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        let mut filters = vec![None];
+        filters.extend((0..20).map(|_| Some(random_filter(&mut rnd))));
+
+        for filter in filters.iter() {
+            for key in &["kvd", "int"] {
+                let plain_facet = plain_segment.facet(key, filter.as_ref(), 10).unwrap();
+                let struct_facet = struct_segment.facet(key, filter.as_ref(), 10).unwrap();
+                assert_eq!(plain_facet, struct_facet);
+                assert!(plain_facet.len() <= 10);
+                assert!(plain_facet
+                    .iter()
+                    .tuple_windows()
+                    .all(|(a, b)| a.count >= b.count));
+            }
+        }
+
+        let top_keyword = struct_segment.facet("kvd", None, 1).unwrap().remove(0);
+        let keyword = match &top_keyword.value {
+            FacetValue::Keyword(keyword) => keyword.clone(),
+            _ => panic!("Keyword value expected"),
+        };
+        let filter = Filter::new_must(Condition::Field(FieldCondition {
+            key: "kvd".to_string(),
+            r#match: Some(Match {
+                keyword: Some(keyword),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }));
+        assert_eq!(
+            struct_segment.count(Some(&filter), true).unwrap(),
+            top_keyword.count
+        );
+    }
+
+    #[test]
+    fn test_struct_payload_index_updates() {
+        let mut rnd = thread_rng();
+
+        let dir1 = TempDir::new("segment1_dir").unwrap();
+        let dir2 = TempDir::new("segment2_dir").unwrap();
+
+        let (mut struct_segment, _) = build_test_segments(dir1.path(), dir2.path(), &mut rnd);
+
+        let filter = Filter::new_must(Condition::Field(FieldCondition {
+            key: "kvd".to_string(),
+            r#match: Some(Match {
+                keyword: Some("unique keyword".to_string()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }));
+
+        let count =
+            |segment: &Segment| segment.payload_index.borrow().query_points(&filter).count();
+        assert_eq!(count(&struct_segment), 0);
+
+        let opnum = 10_000;
+        struct_segment
+            .set_payload(
+                opnum,
+                1.into(),
+                "kvd",
+                PayloadType::Keyword(vec!["unique keyword".to_string()]),
+            )
+            .unwrap();
+        struct_segment
+            .set_payload(
+                opnum + 1,
+                2.into(),
+                "kvd",
+                PayloadType::Keyword(vec!["unique keyword".to_string()]),
+            )
+            .unwrap();
+        assert_eq!(count(&struct_segment), 2);
+
+        struct_segment.delete_point(opnum + 2, 1.into()).unwrap();
+        struct_segment.clear_payload(opnum + 3, 3.into()).unwrap();
+        assert_eq!(count(&struct_segment), 1);
+
+        // Index state survives segment re-creation
+        struct_segment.flush().unwrap();
+        let temp_dir = TempDir::new("segment_temp_dir").unwrap();
+        let dest_dir = TempDir::new("segment_dest_dir").unwrap();
+        let mut builder = SegmentBuilder::new(
+            dest_dir.path(),
+            temp_dir.path(),
+            &struct_segment.config(),
+            false,
+        )
+        .unwrap();
+        builder.update_from(&struct_segment).unwrap();
+        let merged_segment: Segment = builder.try_into().unwrap();
+
+        assert_eq!(
+            merged_segment
+                .get_indexed_fields()
+                .into_iter()
+                .sorted()
+                .collect_vec(),
+            vec!["geo".to_string(), "int".to_string(), "kvd".to_string()]
+        );
+        assert_eq!(count(&merged_segment), 1);
+
+        struct_segment.delete_field_index(opnum + 4, "kvd").unwrap();
+        assert_eq!(
+            struct_segment.get_indexed_fields(),
+            vec!["int".to_string(), "geo".to_string()]
+        );
+        assert_eq!(count(&struct_segment), 1);
+    }
+
+    #[test]
+    fn test_field_index_file_names() {
+        let dir = TempDir::new("segment_dir").unwrap();
+        let config = SegmentConfig {
+            vector_size: 2,
+            index: Indexes::Plain {},
+            payload_index: Some(PayloadIndexType::Struct),
+            storage_type: StorageType::InMemory,
+            quantization: None,
+            distance: Distance::Dot,
+        };
+        let mut segment = build_segment(dir.path(), &config, false).unwrap();
+
+        // Field names, which are not valid or safe file names
+        let fields = ["a/b", "..", "../escape", "a%2Fb"];
+        segment.upsert_point(1, 1.into(), &[1.0, 0.0]).unwrap();
+        for (idx, field) in fields.iter().enumerate() {
+            segment
+                .set_payload(2, 1.into(), field, PayloadType::Integer(vec![idx as i64]))
+                .unwrap();
+        }
+        for field in fields.iter() {
+            segment.create_field_index(3, field).unwrap();
+        }
+        segment.flush().unwrap();
+
+        // Each field has its own file inside of the field index directory
+        let fields_dir = dir.path().join("payload_index").join("fields");
+        assert_eq!(
+            std::fs::read_dir(&fields_dir).unwrap().count(),
+            fields.len()
+        );
+        assert!(!dir.path().join("payload_index").join("escape.idx").exists());
+        drop(segment);
+
+        let mut segment = load_segment(dir.path(), false).unwrap();
+        for (idx, field) in fields.iter().enumerate() {
+            let filter = Filter::new_must(Condition::Field(FieldCondition {
+                key: field.to_string(),
+                r#match: Some(Match {
+                    keyword: None,
+                    integer: Some(idx as i64),
+                    text: None,
+                    boolean: None,
+                }),
+                range: None,
+                geo_bounding_box: None,
+                geo_radius: None,
+            }));
+            let points = segment.payload_index.borrow().query_points(&filter).count();
+            assert_eq!(points, 1);
+        }
+
+        segment.delete_field_index(4, "a/b").unwrap();
+        assert_eq!(
+            std::fs::read_dir(&fields_dir).unwrap().count(),
+            fields.len() - 1
+        );
+    }
+}
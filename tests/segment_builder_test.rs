@@ -25,7 +25,7 @@ mod tests {
             SegmentBuilder::new(dir.path(), temp_dir.path(), &segment1.segment_config, false).unwrap();
 
         // Include overlapping with segment1 to check the
-        segment2.upsert_point(100, 3, &[0., 0., 0., 0.]).unwrap();
+        segment2.upsert_point(100, 3.into(), &[0., 0., 0., 0.]).unwrap();
 
         builder.update_from(&segment1).unwrap();
         builder.update_from(&segment2).unwrap();
@@ -58,6 +58,6 @@ mod tests {
                 .count()
         );
 
-        assert_eq!(merged_segment.point_version(3), Some(100));
+        assert_eq!(merged_segment.point_version(3.into()), Some(100));
     }
 }
\ No newline at end of file
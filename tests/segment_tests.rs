@@ -4,8 +4,15 @@ mod fixtures;
 mod tests {
     use crate::fixtures::segment::build_segment_1;
     use nuclia_vectors::entry::entry_point::SegmentEntry;
+    use nuclia_vectors::payload_storage::json_payload::payload_from_json;
     use nuclia_vectors::segment_constructor::build_segment;
-    use nuclia_vectors::types::{Condition, WithPayload, SegmentConfig, Indexes, Distance};
+    use nuclia_vectors::segment_constructor::load_segment;
+    use nuclia_vectors::types::{
+        Condition, Distance, ExtendedPointId, FieldCondition, Filter, Indexes, Match,
+        PayloadSchemaType, PayloadSelector, PayloadType, PointIdType, SegmentConfig, StorageType,
+        WithPayload,
+    };
+    use serde_json::json;
     use std::collections::HashSet;
     use std::path::Path;
     use tempdir::TempDir;
@@ -19,6 +26,7 @@ mod tests {
             payload_index: None,
             distance: Distance::Dot,
             storage_type: Default::default(),
+            quantization: None,
         };
 
         //let dir = TempDir::new().unwrap();
@@ -31,15 +39,15 @@ mod tests {
         let vec4 = vec![1.0, 1.0, 0.0];
         let vec5 = vec![1.0, 0.0, 0.0];
     
-        segment_w.upsert_point(1, 1, &vec1).unwrap();
-        segment_w.upsert_point(2, 2, &vec2).unwrap();
-        segment_w.upsert_point(3, 3, &vec3).unwrap();
-        segment_w.upsert_point(4, 4, &vec4).unwrap();
-        segment_w.upsert_point(5, 5, &vec5).unwrap();
+        segment_w.upsert_point(1, 1.into(), &vec1).unwrap();
+        segment_w.upsert_point(2, 2.into(), &vec2).unwrap();
+        segment_w.upsert_point(3, 3.into(), &vec3).unwrap();
+        segment_w.upsert_point(4, 4.into(), &vec4).unwrap();
+        segment_w.upsert_point(5, 5.into(), &vec5).unwrap();
 
-        segment_w.vector(1).unwrap();
+        segment_w.vector(1.into()).unwrap();
 
-        segment_r.vector(1).unwrap();
+        segment_r.vector(1.into()).unwrap();
     }
 
 
@@ -48,21 +56,21 @@ mod tests {
         let dir = TempDir::new("segment_dir").unwrap();
 
         let segment = build_segment_1(dir.path());
-        assert!(segment.has_point(3));
+        assert!(segment.has_point(3.into()));
 
         let query_vector = vec![1.0, 1.0, 1.0, 1.0];
 
         let res = segment
-            .search(&query_vector, &WithPayload::default(), 1, None)
+            .search(&query_vector, &WithPayload::default(), false, None, 1, None)
             .unwrap();
 
         let res2 = segment
-            .search(&query_vector, &WithPayload::default(), 3, None)
+            .search(&query_vector, &WithPayload::default(), false, None, 3, None)
             .unwrap();
         dbg!(res2);
 
         let best_match = res.get(0).expect("Non-empty result");
-        assert_eq!(best_match.id, 3);
+        assert_eq!(best_match.id, 3.into());
 
 
         let point_ids1: Vec<_> = segment.iter_points().collect();
@@ -73,4 +81,518 @@ mod tests {
 
         assert_eq!(&point_ids1, &point_ids2)
     }
+
+    #[test]
+    fn test_payload_operations() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        segment
+            .set_payload(6, 1.into(), "color", PayloadType::Keyword(vec!["red".to_owned()]))
+            .unwrap();
+        segment
+            .set_payload(7, 1.into(), "size", PayloadType::Integer(vec![10]))
+            .unwrap();
+        segment
+            .set_payload(8, 2.into(), "size", PayloadType::Integer(vec![20]))
+            .unwrap();
+
+        assert_eq!(segment.payload(1.into()).unwrap().len(), 2);
+        assert_eq!(segment.point_version(1.into()), Some(7));
+
+        // Outdated operation should not be applied
+        let applied = segment.delete_payload(6, 1.into(), "color").unwrap();
+        assert!(!applied);
+        assert_eq!(segment.payload(1.into()).unwrap().len(), 2);
+
+        segment.delete_payload(9, 1.into(), "color").unwrap();
+        assert!(segment.payload(1.into()).unwrap().get("color").is_none());
+
+        segment.clear_payload(10, 2.into()).unwrap();
+        assert!(segment.payload(2.into()).unwrap().is_empty());
+
+        assert!(segment
+            .set_payload(11, 100.into(), "size", PayloadType::Integer(vec![1]))
+            .is_err());
+
+        segment.delete_point(12, 1.into()).unwrap();
+        segment
+            .upsert_point(13, 1.into(), &[1.0, 1.0, 1.0, 1.0])
+            .unwrap();
+        assert!(segment.payload(1.into()).unwrap().is_empty());
+
+        segment
+            .set_payload(14, 3.into(), "size", PayloadType::Integer(vec![30]))
+            .unwrap();
+        segment.flush().unwrap();
+        drop(segment);
+
+        let segment = load_segment(dir.path(), false).unwrap();
+        match segment.payload(3.into()).unwrap().get("size") {
+            Some(PayloadType::Integer(values)) => assert_eq!(values, &vec![30]),
+            _ => panic!("Payload was not persisted"),
+        }
+    }
+
+    #[test]
+    fn test_filtered_search() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        for (op_num, point_id, color) in &[(6, 1, "red"), (7, 2, "blue"), (8, 3, "blue")] {
+            segment
+                .set_payload(
+                    *op_num,
+                    (*point_id).into(),
+                    "color",
+                    PayloadType::Keyword(vec![color.to_string()]),
+                )
+                .unwrap();
+        }
+
+        let query_vector = vec![1.0, 1.0, 0.0, 1.0];
+
+        let is_blue = Condition::Field(FieldCondition {
+            key: "color".to_string(),
+            r#match: Some(Match {
+                keyword: Some("blue".to_string()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        });
+
+        let blue_filter = Filter::new_must(is_blue.clone());
+        let res = segment
+            .search(&query_vector, &WithPayload::default(), false, Some(&blue_filter), 10, None)
+            .unwrap();
+        let mut ids: Vec<_> = res.iter().map(|x| x.id.clone()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![2.into(), 3.into()]);
+
+        let not_blue_filter = Filter::new_must_not(is_blue);
+        let res = segment
+            .search(&query_vector, &WithPayload::default(), false, Some(&not_blue_filter), 1, None)
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].id, 4.into());
+
+        let ids: HashSet<PointIdType> = vec![1.into(), 5.into(), 100.into()].into_iter().collect();
+        let has_id_filter = Filter::new_must(Condition::HasId(ids.into()));
+        let res = segment
+            .search(&query_vector, &WithPayload::default(), false, Some(&has_id_filter), 10, None)
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].id, 1.into());
+    }
+
+    #[test]
+    fn test_search_with_payload() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        segment
+            .set_payload(
+                6,
+                1.into(),
+                "color",
+                PayloadType::Keyword(vec!["red".to_string()]),
+            )
+            .unwrap();
+        segment
+            .set_payload(7, 1.into(), "size", PayloadType::Integer(vec![10]))
+            .unwrap();
+
+        let query_vector = vec![1.0, 0.0, 1.0, 1.0];
+
+        let res = segment
+            .search(&query_vector, &WithPayload::default(), false, None, 1, None)
+            .unwrap();
+        assert_eq!(res[0].id, 1.into());
+        assert!(res[0].payload.is_none());
+        assert!(res[0].vector.is_none());
+
+        let res = segment
+            .search(&query_vector, &WithPayload::from(true), true, None, 1, None)
+            .unwrap();
+        let payload = res[0].payload.as_ref().unwrap();
+        assert_eq!(payload.len(), 2);
+        assert_eq!(res[0].vector.as_ref().unwrap().len(), 4);
+
+        let with_payload = WithPayload {
+            enable: true,
+            payload_selector: Some(PayloadSelector::new_include_and_exclude(
+                vec!["color".to_string(), "size".to_string()],
+                vec!["size".to_string()],
+            )),
+        };
+        let res = segment
+            .search(&query_vector, &with_payload, false, None, 1, None)
+            .unwrap();
+        let payload = res[0].payload.as_ref().unwrap();
+        assert_eq!(payload.len(), 1);
+        assert!(payload.contains_key("color"));
+    }
+
+    #[test]
+    fn test_read_filtered() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        for (op_num, point_id, color) in &[
+            (6, 1, "red"),
+            (7, 2, "blue"),
+            (8, 3, "blue"),
+            (9, 4, "red"),
+            (10, 5, "blue"),
+        ] {
+            segment
+                .set_payload(
+                    *op_num,
+                    (*point_id).into(),
+                    "color",
+                    PayloadType::Keyword(vec![color.to_string()]),
+                )
+                .unwrap();
+        }
+
+        let page = segment
+            .read_filtered(0.into(), 3, None, &WithPayload::default(), false)
+            .unwrap();
+        assert_eq!(
+            page.points.iter().map(|x| x.id.clone()).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        assert_eq!(page.next_page_offset, Some(4.into()));
+        assert!(page.points[0].payload.is_none());
+
+        let blue_filter = Filter::new_must(Condition::Field(FieldCondition {
+            key: "color".to_string(),
+            r#match: Some(Match {
+                keyword: Some("blue".to_string()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }));
+
+        let mut offset = Some(0.into());
+        let mut blue_ids = vec![];
+        while let Some(page_offset) = offset {
+            let page = segment
+                .read_filtered(page_offset, 2, Some(&blue_filter), &WithPayload::from(true), true)
+                .unwrap();
+            for record in page.points.iter() {
+                assert!(record.payload.as_ref().unwrap().contains_key("color"));
+                assert_eq!(record.vector.as_ref().unwrap().len(), 4);
+            }
+            blue_ids.extend(page.points.into_iter().map(|x| x.id));
+            offset = page.next_page_offset;
+        }
+        assert_eq!(blue_ids, vec![2.into(), 3.into(), 5.into()]);
+    }
+
+    #[test]
+    fn test_payload_schema() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        segment
+            .set_payload(6, 1.into(), "size", PayloadType::Integer(vec![10]))
+            .unwrap();
+        segment
+            .create_field_index(7, &"size".to_string())
+            .unwrap();
+
+        let conflict = segment.set_payload(8, 2.into(), "size", PayloadType::Float(vec![0.5]));
+        assert!(conflict.is_err());
+        assert!(segment.payload(2.into()).unwrap().get("size").is_none());
+
+        segment
+            .set_payload(
+                9,
+                2.into(),
+                "color",
+                PayloadType::Keyword(vec!["red".to_string()]),
+            )
+            .unwrap();
+
+        let schema = segment.info().schema;
+        assert_eq!(schema.len(), 2);
+        assert_eq!(schema["size"].data_type, PayloadSchemaType::Integer);
+        assert!(schema["size"].indexed);
+        assert_eq!(schema["color"].data_type, PayloadSchemaType::Keyword);
+        assert!(!schema["color"].indexed);
+    }
+
+    #[test]
+    fn test_nested_payload() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        for (op_num, point_id, kind, tag) in &[
+            (6, 1, "pdf", "finance"),
+            (7, 2, "html", "finance"),
+            (8, 3, "pdf", "legal"),
+        ] {
+            let payload = payload_from_json(&json!({
+                "source": {"kind": kind, "page": point_id},
+                "tags": [{"name": tag}, {"name": "report"}],
+            }))
+            .unwrap();
+            segment
+                .set_full_payload(*op_num, (*point_id).into(), payload)
+                .unwrap();
+        }
+        segment
+            .create_field_index(9, &"source.kind".to_string())
+            .unwrap();
+
+        let nested_match = |key: &str, keyword: &str| {
+            Condition::Field(FieldCondition {
+                key: key.to_string(),
+                r#match: Some(Match {
+                    keyword: Some(keyword.to_string()),
+                    integer: None,
+                    text: None,
+                    boolean: None,
+                }),
+                range: None,
+                geo_bounding_box: None,
+                geo_radius: None,
+            })
+        };
+
+        let filter = Filter {
+            should: None,
+            must: Some(vec![
+                nested_match("source.kind", "pdf"),
+                nested_match("tags[].name", "finance"),
+            ]),
+            must_not: None,
+        };
+        let with_payload = WithPayload {
+            enable: true,
+            payload_selector: Some(PayloadSelector::new_include(vec!["source".to_string()])),
+        };
+        let res = segment
+            .search(&[1.0, 1.0, 1.0, 1.0], &with_payload, false, Some(&filter), 10, None)
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].id, 1.into());
+
+        let payload = res[0].payload.as_ref().unwrap();
+        assert_eq!(payload.len(), 2);
+        assert!(payload.contains_key("source.kind"));
+        assert!(payload.contains_key("source.page"));
+
+        // Whole JSON numbers are integers, so they can be matched exactly
+        let page_filter = Filter::new_must(Condition::Field(FieldCondition {
+            key: "source.page".to_string(),
+            r#match: Some(Match {
+                keyword: None,
+                integer: Some(3),
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }));
+        let res = segment
+            .search(
+                &[1.0, 1.0, 1.0, 1.0],
+                &WithPayload::default(),
+                false,
+                Some(&page_filter),
+                10,
+                None,
+            )
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].id, 3.into());
+    }
+
+    #[test]
+    fn test_delete_by_filter() {
+        let dir = TempDir::new("segment_dir").unwrap();
+
+        let mut segment = build_segment_1(dir.path());
+
+        for (op_num, point_id, document) in &[
+            (6, 1, "doc_a"),
+            (7, 2, "doc_a"),
+            (8, 3, "doc_b"),
+            (9, 4, "doc_a"),
+        ] {
+            segment
+                .set_payload(
+                    *op_num,
+                    (*point_id).into(),
+                    "document",
+                    PayloadType::Keyword(vec![document.to_string()]),
+                )
+                .unwrap();
+        }
+
+        let doc_a_filter = Filter::new_must(Condition::Field(FieldCondition {
+            key: "document".to_string(),
+            r#match: Some(Match {
+                keyword: Some("doc_a".to_string()),
+                integer: None,
+                text: None,
+                boolean: None,
+            }),
+            range: None,
+            geo_bounding_box: None,
+            geo_radius: None,
+        }));
+
+        // Point 4 was updated by a newer operation and must stay
+        segment
+            .upsert_point(12, 4.into(), &[1.0, 1.0, 1.0, 1.0])
+            .unwrap();
+
+        let deleted = segment.delete_by_filter(10, &doc_a_filter).unwrap();
+        assert_eq!(deleted, 2);
+        assert!(!segment.has_point(1.into()));
+        assert!(!segment.has_point(2.into()));
+        assert!(segment.has_point(3.into()));
+        assert!(segment.has_point(4.into()));
+        assert_eq!(segment.point_version(1.into()), Some(10));
+
+        // Outdated re-insertion of a deleted point is ignored
+        segment
+            .upsert_point(9, 1.into(), &[1.0, 1.0, 1.0, 1.0])
+            .unwrap();
+        assert!(!segment.has_point(1.into()));
+
+        // Operation is idempotent
+        assert_eq!(segment.delete_by_filter(10, &doc_a_filter).unwrap(), 0);
+        assert_eq!(segment.count(Some(&doc_a_filter), true).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_batch_upsert_and_delete() {
+        for storage_type in vec![StorageType::InMemory, StorageType::Drive] {
+            let dir = TempDir::new("segment_dir").unwrap();
+            let config = SegmentConfig {
+                vector_size: 2,
+                index: Indexes::Plain {},
+                payload_index: None,
+                distance: Distance::Dot,
+                storage_type,
+                quantization: None,
+            };
+            let mut segment = build_segment(dir.path(), &config, false).unwrap();
+
+            segment.upsert_point(5, 3.into(), &[3.0, 3.0]).unwrap();
+
+            let points = vec![
+                (1.into(), vec![1.0, 0.0]),
+                (2.into(), vec![0.0, 1.0]),
+                (3.into(), vec![0.5, 0.5]),
+                (4.into(), vec![1.0, 1.0]),
+                (1.into(), vec![2.0, 0.0]),
+            ];
+            // Point 3 was updated by a newer operation and must stay unchanged
+            assert_eq!(segment.upsert_points(4, &points).unwrap(), 3);
+            assert_eq!(segment.vectors_count(), 4);
+            assert_eq!(segment.vector(1.into()).unwrap(), vec![2.0, 0.0]);
+            assert_eq!(segment.vector(3.into()).unwrap(), vec![3.0, 3.0]);
+            assert_eq!(segment.point_version(2.into()), Some(4));
+
+            let wrong_points = vec![(5.into(), vec![1.0, 0.0]), (6.into(), vec![1.0])];
+            assert!(segment.upsert_points(6, &wrong_points).is_err());
+            assert!(!segment.has_point(5.into()));
+
+            // Replace existing vectors
+            let points = vec![(2.into(), vec![0.0, 2.0]), (4.into(), vec![4.0, 4.0])];
+            assert_eq!(segment.upsert_points(7, &points).unwrap(), 2);
+            assert_eq!(segment.vectors_count(), 4);
+            assert_eq!(segment.vector(4.into()).unwrap(), vec![4.0, 4.0]);
+
+            assert_eq!(
+                segment
+                    .delete_points(8, &[1.into(), 2.into(), 10.into(), 2.into()])
+                    .unwrap(),
+                2
+            );
+            assert!(!segment.has_point(1.into()));
+            assert!(!segment.has_point(2.into()));
+            assert_eq!(segment.point_version(10.into()), Some(8));
+            assert_eq!(segment.delete_points(4, &[3.into(), 4.into()]).unwrap(), 0);
+            assert!(segment.has_point(3.into()));
+
+            segment.flush().unwrap();
+            drop(segment);
+
+            let segment = load_segment(dir.path(), false).unwrap();
+            let mut points: Vec<_> = segment.iter_points().collect();
+            points.sort_unstable();
+            assert_eq!(points, vec![3.into(), 4.into()]);
+            assert_eq!(segment.vector(4.into()).unwrap(), vec![4.0, 4.0]);
+            assert_eq!(segment.point_version(2.into()), Some(8));
+        }
+    }
+
+    #[test]
+    fn test_extended_point_ids() {
+        let dir = TempDir::new("segment_dir").unwrap();
+        let mut segment = build_segment_1(dir.path());
+
+        let uuid_id: PointIdType =
+            serde_json::from_value(json!("c8b2c5a2-8f5e-4a7d-9a43-1d8c3f7f2e10")).unwrap();
+        let string_id: PointIdType = serde_json::from_value(json!("doc-1/par-2")).unwrap();
+        let numeric_id: PointIdType = serde_json::from_value(json!(3)).unwrap();
+        assert!(matches!(uuid_id, ExtendedPointId::Uuid(_)));
+        assert_eq!(string_id, "doc-1/par-2".into());
+        assert_eq!(numeric_id, 3.into());
+
+        segment
+            .upsert_point(6, uuid_id.clone(), &[0.0, 0.0, 1.0, 0.0])
+            .unwrap();
+        segment
+            .upsert_point(7, string_id.clone(), &[0.0, 1.0, 0.0, 0.0])
+            .unwrap();
+        assert_eq!(segment.vectors_count(), 7);
+        assert_eq!(segment.point_version(uuid_id.clone()), Some(6));
+
+        let ids: HashSet<PointIdType> = vec![uuid_id.clone(), string_id.clone()]
+            .into_iter()
+            .collect();
+        let has_id_filter = Filter::new_must(Condition::HasId(ids.into()));
+        let res = segment
+            .search(&[0.0, 1.0, 0.0, 0.0], &WithPayload::default(), false, Some(&has_id_filter), 10, None)
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].id, string_id);
+        assert_eq!(
+            serde_json::to_value(&res[0]).unwrap()["id"],
+            json!("doc-1/par-2")
+        );
+
+        segment.delete_point(8, string_id.clone()).unwrap();
+        segment.flush().unwrap();
+        drop(segment);
+
+        let segment = load_segment(dir.path(), false).unwrap();
+        assert_eq!(segment.vectors_count(), 6);
+        assert!(segment.has_point(uuid_id.clone()));
+        assert!(segment.has_point(3.into()));
+        assert!(!segment.has_point(string_id.clone()));
+        assert_eq!(segment.point_version(string_id), Some(8));
+    }
 }